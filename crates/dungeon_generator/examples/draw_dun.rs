@@ -3,9 +3,37 @@
 //! Christian Schmid - June 2021
 //! CS510 - Rust Programming
 
-use dungeon_generator::inst::Dungeon;
+use dungeon_generator::{
+    config::DungeonConfig,
+    generators::{bsp::BspGenerator, caves::CaveGenerator, walk::WalkGenerator, wfc::WfcGenerator},
+    inst::Dungeon,
+    traits::Generator,
+};
 
 fn main() {
-    let dun = Dungeon::new(20, 20);
+    // Draw the dungeon for the seed supplied as the first
+    // argument, or a random dungeon if none is supplied
+    let mut config = DungeonConfig::new(20, 20);
+    if let Some(seed) = std::env::args().nth(1).and_then(|s| s.parse().ok()) {
+        config = config.with_seed(seed);
+    }
+
+    // Generate it in the style supplied as the second argument. The
+    // wfc style learns from the sample map file given as the third
+    // argument, or from its own sample if none is given.
+    let generator: Box<dyn Generator> = match std::env::args().nth(2).as_deref() {
+        Some("bsp") => Box::new(BspGenerator::default()),
+        Some("caves") => Box::new(CaveGenerator::default()),
+        Some("wfc") => match std::env::args().nth(3) {
+            Some(path) => Box::new(WfcGenerator::new(
+                &Dungeon::load(path).expect("unreadable sample map"),
+            )),
+            None => Box::new(WfcGenerator::default()),
+        },
+        _ => Box::new(WalkGenerator),
+    };
+
+    let dun = generator.generate(&config);
+    println!("Seed: {}", dun.seed());
     println!("{:?}", dun);
 }
@@ -0,0 +1,48 @@
+//! Example for Dungeon Generator - Renders dungeons to images
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use dungeon_generator::{
+    config::DungeonConfig,
+    generators::{bsp::BspGenerator, caves::CaveGenerator, walk::WalkGenerator, wfc::WfcGenerator},
+    render::RenderOptions,
+    traits::Generator,
+};
+
+fn main() -> std::io::Result<()> {
+    // Render the dungeons into the directory supplied as the first
+    // argument, generating as many seeds as the second argument
+    let dir = std::env::args().nth(1).unwrap_or_else(|| ".".to_string());
+    let count: u64 = std::env::args()
+        .nth(2)
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(4);
+    std::fs::create_dir_all(&dir)?;
+
+    let generators: [(&str, Box<dyn Generator>); 4] = [
+        ("walk", Box::new(WalkGenerator)),
+        ("bsp", Box::new(BspGenerator::default())),
+        ("caves", Box::new(CaveGenerator::default())),
+        ("wfc", Box::new(WfcGenerator::default())),
+    ];
+    let options = RenderOptions::new().with_scale(8);
+
+    // Save each dungeon as both an SVG and a PPM image
+    for seed in 0..count {
+        let config = DungeonConfig::new(40, 40)
+            .with_seed(seed)
+            .with_loops(2)
+            .with_locks(1)
+            .with_hazards(10);
+        for (name, generator) in generators.iter() {
+            let dun = generator.generate(&config);
+            for ext in ["svg", "ppm"].iter() {
+                let path = format!("{}/{}_{}.{}", dir, name, seed, ext);
+                dun.save_image(&path, &options)?;
+                println!("Saved {}", path);
+            }
+        }
+    }
+    Ok(())
+}
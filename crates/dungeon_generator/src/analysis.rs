@@ -0,0 +1,363 @@
+//! Analysis of the layout of a Dungeon
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{gen::NEIGHBOURS, inst::Dungeon};
+
+///
+/// The analysis of a `Dungeon`'s paths, describing how
+/// far each tile is from the entrance and exit, and
+/// where the dungeon narrows or ends.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Analysis {
+    /// The length of the shortest walk from the entrance to each tile
+    pub from_entrance: HashMap<(i32, i32), u32>,
+    /// The length of the shortest walk from the exit to each tile
+    pub from_exit: HashMap<(i32, i32), u32>,
+    /// The tiles which split their region in two when walled off
+    pub chokepoints: HashSet<(i32, i32)>,
+    /// The tiles with only a single neighbouring tile
+    pub dead_ends: HashSet<(i32, i32)>,
+    /// The number of tiles in each separate region, largest first
+    pub region_sizes: Vec<usize>,
+}
+
+impl Dungeon {
+    ///
+    /// Analyses the `Dungeon`'s paths, finding the distance of
+    /// each tile from the entrance and exit, its chokepoints
+    /// and dead ends, and the size of each of its regions.
+    ///
+    pub fn analyze(&self) -> Analysis {
+        let paths = self.paths_ref();
+        Analysis {
+            from_entrance: distance_field(paths, self.entrance),
+            from_exit: distance_field(paths, self.exit),
+            chokepoints: articulation_points(paths),
+            dead_ends: dead_ends(paths),
+            region_sizes: regions(paths).iter().map(|r| r.len()).collect(),
+        }
+    }
+}
+
+/// Finds the length of the shortest route along `paths`
+/// from `from` to every point it can reach.
+pub fn distance_field(paths: &HashSet<(i32, i32)>, from: (i32, i32)) -> HashMap<(i32, i32), u32> {
+    let mut distances = HashMap::new();
+    let mut next = VecDeque::new();
+    if paths.contains(&from) {
+        distances.insert(from, 0);
+        next.push_back(from);
+    }
+
+    while let Some(point) = next.pop_front() {
+        let distance = distances[&point] + 1;
+        for dir in NEIGHBOURS.iter() {
+            let adj = (point.0 + dir.0, point.1 + dir.1);
+            if paths.contains(&adj) && !distances.contains_key(&adj) {
+                distances.insert(adj, distance);
+                next.push_back(adj);
+            }
+        }
+    }
+
+    distances
+}
+
+/// Finds the shortest route of tiles along the `paths`
+/// from `from` to `to`, including both ends.
+pub fn shortest_route(
+    paths: &HashSet<(i32, i32)>,
+    from: (i32, i32),
+    to: (i32, i32),
+) -> Option<Vec<(i32, i32)>> {
+    let mut parents = HashMap::new();
+    let mut queue = VecDeque::new();
+    if !paths.contains(&from) {
+        return None;
+    }
+    parents.insert(from, from);
+    queue.push_back(from);
+
+    while let Some(cur) = queue.pop_front() {
+        if cur == to {
+            let mut route = vec![to];
+            while *route.last().unwrap() != from {
+                route.push(parents[route.last().unwrap()]);
+            }
+            route.reverse();
+            return Some(route);
+        }
+        for (dx, dy) in NEIGHBOURS.iter() {
+            let next = (cur.0 + dx, cur.1 + dy);
+            if paths.contains(&next) && !parents.contains_key(&next) {
+                parents.insert(next, cur);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+/// Splits `paths` into its connected regions, returned
+/// largest first. Regions of equal size are ordered by their
+/// smallest point, so the order is always the same for equal `paths`.
+pub fn regions(paths: &HashSet<(i32, i32)>) -> Vec<HashSet<(i32, i32)>> {
+    let mut sorted: Vec<&(i32, i32)> = paths.iter().collect();
+    sorted.sort_unstable();
+
+    let mut visited = HashSet::new();
+    let mut regions = Vec::new();
+
+    for start in sorted {
+        if visited.contains(start) {
+            continue;
+        }
+        // Breadth-first search outwards from the start point,
+        // collecting every point it can reach
+        let mut region = HashSet::new();
+        let mut next = VecDeque::new();
+        next.push_back(*start);
+        visited.insert(*start);
+
+        while let Some(point) = next.pop_front() {
+            region.insert(point);
+            for dir in NEIGHBOURS.iter() {
+                let adj = (point.0 + dir.0, point.1 + dir.1);
+                if paths.contains(&adj) && visited.insert(adj) {
+                    next.push_back(adj);
+                }
+            }
+        }
+        regions.push(region);
+    }
+
+    // A stable sort keeps regions of equal size in order of their smallest point
+    regions.sort_by_key(|r| std::cmp::Reverse(r.len()));
+    regions
+}
+
+/// Finds every tile of the `paths` with exactly one neighbouring tile
+pub fn dead_ends(paths: &HashSet<(i32, i32)>) -> HashSet<(i32, i32)> {
+    paths
+        .iter()
+        .filter(|p| {
+            NEIGHBOURS
+                .iter()
+                .filter(|dir| paths.contains(&(p.0 + dir.0, p.1 + dir.1)))
+                .count()
+                == 1
+        })
+        .cloned()
+        .collect()
+}
+
+/// Finds every tile of the `paths` which, when walled off,
+/// splits the region it lies in into two or more.
+pub fn articulation_points(paths: &HashSet<(i32, i32)>) -> HashSet<(i32, i32)> {
+    let mut points = HashSet::new();
+    for region in regions(paths) {
+        let root = *region.iter().min().unwrap();
+        let search = LowPoints::search(paths, root);
+
+        for (tile, parent) in search.parent.iter() {
+            // The root splits its region if the search
+            // leaves it more than once
+            if *parent == root {
+                continue;
+            }
+            if search.low[tile] >= search.found[parent] {
+                points.insert(*parent);
+            }
+        }
+        if search.parent.values().filter(|p| **p == root).count() > 1 {
+            points.insert(root);
+        }
+    }
+    points
+}
+
+/// Finds every tile of the `paths` which cuts `to` off from
+/// `from` when walled off, other than `from` and `to` themselves.
+pub fn chokepoints_between(
+    paths: &HashSet<(i32, i32)>,
+    from: (i32, i32),
+    to: (i32, i32),
+) -> HashSet<(i32, i32)> {
+    let mut points = HashSet::new();
+    if !paths.contains(&from) {
+        return points;
+    }
+    let search = LowPoints::search(paths, from);
+    let target = match search.found.get(&to) {
+        Some(order) => *order,
+        None => return points,
+    };
+
+    // A tile cuts `to` off if nothing found beneath one of its
+    // children reaches back past it, and `to` was found beneath it
+    for (tile, parent) in search.parent.iter() {
+        let beneath = search.found[tile] <= target && target <= search.last[tile];
+        if *parent != from && search.low[tile] >= search.found[parent] && beneath {
+            points.insert(*parent);
+        }
+    }
+    points.remove(&to);
+    points
+}
+
+///
+/// The results of a depth-first search through a region of the paths,
+/// recording for each tile the order it was found in, the earliest
+/// found tile it can reach back to without passing through its parent,
+/// and the last tile found beneath it.
+///
+struct LowPoints {
+    found: HashMap<(i32, i32), u32>,
+    low: HashMap<(i32, i32), u32>,
+    last: HashMap<(i32, i32), u32>,
+    parent: HashMap<(i32, i32), (i32, i32)>,
+}
+
+impl LowPoints {
+    /// Searches the region of the `paths` holding `root`
+    fn search(paths: &HashSet<(i32, i32)>, root: (i32, i32)) -> Self {
+        let mut search = LowPoints {
+            found: HashMap::new(),
+            low: HashMap::new(),
+            last: HashMap::new(),
+            parent: HashMap::new(),
+        };
+
+        // The search is kept on a stack of each tile,
+        // and the index of the next neighbour of it to visit
+        let mut stack = vec![(root, 0)];
+        search.found.insert(root, 0);
+        search.low.insert(root, 0);
+
+        while let Some((tile, next)) = stack.pop() {
+            if next < NEIGHBOURS.len() {
+                stack.push((tile, next + 1));
+                let (dx, dy) = NEIGHBOURS[next];
+                let adj = (tile.0 + dx, tile.1 + dy);
+                if !paths.contains(&adj) || search.parent.get(&tile) == Some(&adj) {
+                    continue;
+                }
+                match search.found.get(&adj) {
+                    Some(order) => {
+                        let order = *order;
+                        let low = search.low.get_mut(&tile).unwrap();
+                        *low = (*low).min(order);
+                    }
+                    None => {
+                        let order = search.found.len() as u32;
+                        search.found.insert(adj, order);
+                        search.low.insert(adj, order);
+                        search.parent.insert(adj, tile);
+                        stack.push((adj, 0));
+                    }
+                }
+            } else {
+                // The tile is finished, along with every tile beneath it
+                search.last.insert(tile, search.found.len() as u32 - 1);
+                if let Some(parent) = search.parent.get(&tile) {
+                    let tile_low = search.low[&tile];
+                    let low = search.low.get_mut(parent).unwrap();
+                    *low = (*low).min(tile_low);
+                }
+            }
+        }
+        search
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::DungeonConfig,
+        generators::{bsp::BspGenerator, caves::CaveGenerator, walk::WalkGenerator},
+        traits::Generator,
+    };
+
+    /// Whether `to` can be reached from `from` by a plain search,
+    /// which looks through every path for the neighbours of each
+    /// point it visits
+    fn reaches(paths: &HashSet<(i32, i32)>, from: (i32, i32), to: (i32, i32)) -> bool {
+        let mut visited = HashSet::new();
+        let mut next = vec![from];
+        visited.insert(from);
+        while let Some(point) = next.pop() {
+            if point == to {
+                return true;
+            }
+            for p in paths {
+                if (point.0 - p.0).abs() + (point.1 - p.1).abs() == 1 && visited.insert(*p) {
+                    next.push(*p);
+                }
+            }
+        }
+        false
+    }
+
+    /// Tests the analysis of dungeons against simpler, slower
+    /// searches - the distance field must reach the exit just when a
+    /// plain search does, walling off each chokepoint must split its
+    /// region, and walling off any other tile must not.
+    #[test]
+    fn test_analysis() {
+        let generators: [&dyn Generator; 3] = [
+            &WalkGenerator,
+            &BspGenerator::default(),
+            &CaveGenerator::default(),
+        ];
+
+        for generator in generators.iter() {
+            for seed in 0..3 {
+                let dun = generator.generate(&DungeonConfig::new(25, 25).with_seed(seed));
+                let analysis = dun.analyze();
+                let paths = dun.paths_ref();
+
+                let reached = distance_field(paths, dun.entrance);
+                assert!(reached.contains_key(&dun.exit) && reaches(paths, dun.entrance, dun.exit));
+                assert!(reached == analysis.from_entrance);
+                assert_eq!(
+                    analysis.from_entrance[&dun.exit],
+                    analysis.from_exit[&dun.entrance]
+                );
+                assert_eq!(analysis.from_entrance.len(), paths.len());
+                assert_eq!(analysis.region_sizes.iter().sum::<usize>(), paths.len());
+
+                for tile in paths.iter() {
+                    let mut walled = paths.clone();
+                    walled.remove(tile);
+                    let splits = regions(&walled).len() > analysis.region_sizes.len();
+                    assert_eq!(analysis.chokepoints.contains(tile), splits);
+
+                    let neighbours = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+                        .iter()
+                        .filter(|d| paths.contains(&(tile.0 + d.0, tile.1 + d.1)))
+                        .count();
+                    assert_eq!(analysis.dead_ends.contains(tile), neighbours == 1);
+                }
+            }
+        }
+
+        // A corridor is all chokepoints but its ends, which are dead ends
+        let line = Dungeon::from_text("dungeon 1\nmap\n<..>\n")
+            .unwrap()
+            .analyze();
+        assert_eq!(line.chokepoints.len(), 2);
+        assert_eq!(line.dead_ends.len(), 2);
+        assert_eq!(line.from_entrance[&(3, 0)], 3);
+
+        // Neither reaches an exit cut off from the entrance
+        let split = Dungeon::from_text("dungeon 1\nmap\n<#>\n").unwrap();
+        assert!(!distance_field(split.paths_ref(), split.entrance).contains_key(&split.exit));
+        assert!(!reaches(split.paths_ref(), split.entrance, split.exit));
+    }
+}
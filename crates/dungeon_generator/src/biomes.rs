@@ -0,0 +1,181 @@
+//! Themed regions of a Dungeon, and the decorations placed in them
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+//!
+//! A dungeon is split into regions around a handful of sites on its
+//! paths, each tile belonging to the region of the closest site, and
+//! each region is given a `Biome`. Decorations are then scattered
+//! through the dungeon, each chosen from those of the biome it lands in.
+//! Neither changes how the dungeon is walked; they are there for the
+//! client to theme each area, and for the server to choose monsters by.
+
+use rand::{prelude::*, rngs::StdRng};
+use simple_serializer::{Decode, Deserialize, Encode, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::{config::DungeonConfig, gen::NEIGHBOURS, inst::Dungeon, tiles::Tile};
+
+///
+/// The theme of a region of the dungeon
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Biome {
+    /// Dressed stone halls, lined with the dead
+    Crypt,
+    /// Rough natural rock
+    Cave,
+    /// Damp brick tunnels
+    Sewer,
+}
+
+simple_serializer::numbered_enum!(Biome {
+    Crypt => "crypt",
+    Cave => "cave",
+    Sewer => "sewer",
+});
+
+impl Biome {
+    /// The decorations found in the `Biome`, each listed
+    /// once for each point of weight it has
+    pub fn decorations(&self) -> &'static [Decoration] {
+        match self {
+            Biome::Crypt => &[Decoration::Torch, Decoration::Bones, Decoration::Bones],
+            Biome::Cave => &[Decoration::Bones, Decoration::Puddle],
+            Biome::Sewer => &[Decoration::Puddle, Decoration::Puddle, Decoration::Torch],
+        }
+    }
+}
+
+///
+/// A purely cosmetic feature of a single tile. Torches
+/// hang on the walls beside the paths, and everything
+/// else lies on plain floor.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Decoration {
+    /// A torch in a bracket on the wall
+    Torch,
+    /// A scattering of old bones
+    Bones,
+    /// A shallow puddle, too small to slow anyone down
+    Puddle,
+}
+
+simple_serializer::numbered_enum!(Decoration {
+    Torch => "torch",
+    Bones => "bones",
+    Puddle => "puddle",
+});
+
+impl Decoration {
+    /// Tests if the `Decoration` hangs on a wall, rather than lying on the floor
+    pub fn on_wall(&self) -> bool {
+        *self == Decoration::Torch
+    }
+}
+
+///
+/// A region of the dungeon - every tile closer to its
+/// `site` than to the site of any other region - and
+/// the `Biome` it is themed as.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Encode, Decode)]
+pub struct Region {
+    pub site: (i32, i32),
+    pub biome: Biome,
+}
+
+impl Dungeon {
+    ///
+    /// The region `point` belongs to - the one with the closest
+    /// site, or the first of those equally close. Returns `None`
+    /// if the dungeon isn't split into regions.
+    ///
+    pub fn region_at(&self, point: (i32, i32)) -> Option<&Region> {
+        self.regions().iter().min_by_key(|region| {
+            let (dx, dy) = (region.site.0 - point.0, region.site.1 - point.1);
+            dx * dx + dy * dy
+        })
+    }
+    /// The `Biome` of the region `point` belongs to, if there are regions
+    pub fn biome_at(&self, point: (i32, i32)) -> Option<Biome> {
+        self.region_at(point).map(|region| region.biome)
+    }
+    /// The `Decoration` at `point`, if there is one
+    pub fn decoration_at(&self, point: (i32, i32)) -> Option<Decoration> {
+        self.decorations().get(&point).cloned()
+    }
+}
+
+///
+/// Splits the `dungeon` into the number of regions set in the
+/// `config`, each around a site chosen at random on the paths and
+/// given a random `Biome`, then scatters up to the number of
+/// decorations set through them. Decorations are kept off the
+/// entrance, exits, hazards, locks and vault markers. Draws nothing
+/// from `rng` unless the `config` asks for regions.
+///
+pub fn theme(rng: &mut StdRng, dungeon: Dungeon, config: &DungeonConfig) -> Dungeon {
+    if config.biomes() == 0 {
+        return dungeon;
+    }
+    let mut paths: Vec<(i32, i32)> = dungeon.paths().cloned().collect();
+    paths.sort_unstable();
+    let regions: Vec<Region> = paths
+        .choose_multiple(rng, config.biomes() as usize)
+        .map(|site| Region {
+            site: *site,
+            biome: *Biome::ALL.choose(rng).unwrap(),
+        })
+        .collect();
+    let dungeon = dungeon.with_regions(regions);
+
+    let taken: HashSet<(i32, i32)> = dungeon
+        .exits()
+        .iter()
+        .map(|exit| exit.pos)
+        .chain(std::iter::once(dungeon.entrance))
+        .chain(
+            dungeon
+                .locks()
+                .iter()
+                .flat_map(|lock| [lock.door, lock.key]),
+        )
+        .chain(
+            dungeon
+                .vaults()
+                .iter()
+                .flat_map(|v| v.spawns.iter().chain(v.treasure.iter()).cloned()),
+        )
+        .collect();
+    // Floors for anything but torches, and the walls beside the paths for torches
+    let floors = paths
+        .iter()
+        .filter(|p| dungeon.tile_at(**p) == Tile::Floor && !taken.contains(p));
+    let walls: HashSet<(i32, i32)> = paths
+        .iter()
+        .flat_map(|p| NEIGHBOURS.iter().map(move |d| (p.0 + d.0, p.1 + d.1)))
+        .filter(|p| {
+            (0..dungeon.width() as i32).contains(&p.0)
+                && (0..dungeon.height() as i32).contains(&p.1)
+                && dungeon.tile_at(*p) == Tile::Wall
+        })
+        .collect();
+    let mut spots: Vec<(i32, i32)> = floors.cloned().chain(walls.iter().cloned()).collect();
+    spots.sort_unstable();
+    spots.shuffle(rng);
+
+    let mut decorations = HashMap::new();
+    for spot in spots {
+        if decorations.len() == config.decorations() as usize {
+            break;
+        }
+        let biome = dungeon.biome_at(spot).unwrap();
+        let decoration = *biome.decorations().choose(rng).unwrap();
+        if decoration.on_wall() == walls.contains(&spot) {
+            decorations.insert(spot, decoration);
+        }
+    }
+    dungeon.with_decorations(decorations)
+}
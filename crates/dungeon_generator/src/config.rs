@@ -0,0 +1,318 @@
+//! Generation parameters for Dungeon Generator
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use crate::{difficulty::DifficultyBand, vaults::Vault};
+
+/// The default space between perlin noise values
+const DEFAULT_NOISE_SCALE: (f64, f64) = (0.25, 0.25);
+
+/// The default threshold a perlin noise value must be greater than to be a path
+const DEFAULT_NOISE_THRESHOLD: f64 = 0.05;
+
+///
+/// An edge of the `Dungeon` that an entrance
+/// or exit can be placed along.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+///
+/// The rule used to place the entrance or exit
+/// of a `Dungeon`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// For the entrance, a random spot on either the top
+    /// or bottom edge. For the exit, a random spot on the
+    /// edge opposite the entrance. For any extra exits, a
+    /// random spot on any edge.
+    Random,
+    /// A random spot along the given `Edge`
+    Edge(Edge),
+    /// A random spot away from every edge
+    Interior,
+    /// The exact (x, y) position given, moved to the
+    /// nearest point within the dungeon if it lies outside
+    At(i32, i32),
+}
+
+///
+/// The weighting of each direction the main path can
+/// step in while travelling from the entrance to the exit.
+/// Higher values make that direction more likely.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirectionBias {
+    /// The weight of stepping left or right
+    pub sideways: u32,
+    /// The extra weight given to a sideways step
+    /// which brings the path closer to the exit
+    pub towards_exit: u32,
+    /// The weight of stepping vertically towards the exit
+    pub forward: u32,
+}
+
+impl Default for DirectionBias {
+    fn default() -> Self {
+        Self {
+            sideways: 2,
+            towards_exit: 1,
+            forward: 1,
+        }
+    }
+}
+
+///
+/// A collection of parameters which control how
+/// a `Dungeon` is generated. Built by chaining
+/// `with_*` methods onto `DungeonConfig::new`.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DungeonConfig {
+    seed: Option<u64>,
+    width: u32,
+    height: u32,
+
+    noise_scale: (f64, f64),
+    noise_threshold: f64,
+    direction_bias: DirectionBias,
+
+    entrance: Placement,
+    exit: Placement,
+    exit_destination: Option<String>,
+    extra_exits: Vec<(Placement, Option<String>)>,
+
+    loops: u32,
+    locks: u32,
+
+    vaults: Vec<Vault>,
+    vault_count: u32,
+
+    hazards: u32,
+
+    biomes: u32,
+    decorations: u32,
+
+    difficulty: DifficultyBand,
+    attempts: u32,
+}
+
+impl DungeonConfig {
+    /// Creates a new `DungeonConfig` for a `Dungeon` with
+    /// the given `width` and `height`, using the default
+    /// generation parameters and a random seed. Each of the
+    /// `width` and `height` is kept at a minimum of 1.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            seed: None,
+            width: width.max(1),
+            height: height.max(1),
+            noise_scale: DEFAULT_NOISE_SCALE,
+            noise_threshold: DEFAULT_NOISE_THRESHOLD,
+            direction_bias: DirectionBias::default(),
+            entrance: Placement::Random,
+            exit: Placement::Random,
+            exit_destination: None,
+            extra_exits: Vec::new(),
+            loops: 0,
+            locks: 0,
+            vaults: Vec::new(),
+            vault_count: 0,
+            hazards: 0,
+            biomes: 0,
+            decorations: 0,
+            difficulty: DifficultyBand::default(),
+            attempts: 1,
+        }
+    }
+    /// Sets the `seed` the `Dungeon` is generated from.
+    /// If no seed is set, a random one is chosen each time
+    /// a `Dungeon` is generated.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+    /// Sets the `width` and `height` of the `Dungeon`,
+    /// each kept at a minimum of 1
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = width.max(1);
+        self.height = height.max(1);
+        self
+    }
+    /// Sets the space between perlin noise values, along
+    /// the x and y axes. Smaller values give larger caverns.
+    pub fn with_noise_scale(mut self, x: f64, y: f64) -> Self {
+        self.noise_scale = (x, y);
+        self
+    }
+    /// Sets the threshold a perlin noise value must be greater
+    /// than to become a path. Higher values give sparser dungeons.
+    pub fn with_noise_threshold(mut self, threshold: f64) -> Self {
+        self.noise_threshold = threshold;
+        self
+    }
+    /// Sets the weighting of each direction the main path can step in.
+    /// `forward` is kept at a minimum of 1, so the path always reaches the exit.
+    pub fn with_direction_bias(mut self, bias: DirectionBias) -> Self {
+        self.direction_bias = DirectionBias {
+            forward: bias.forward.max(1),
+            ..bias
+        };
+        self
+    }
+    /// Sets the rule used to place the entrance
+    pub fn with_entrance(mut self, entrance: Placement) -> Self {
+        self.entrance = entrance;
+        self
+    }
+    /// Sets the rule used to place the exit
+    pub fn with_exit(mut self, exit: Placement) -> Self {
+        self.exit = exit;
+        self
+    }
+    /// Tags the exit with the `destination` it leads to
+    pub fn with_exit_destination(mut self, destination: &str) -> Self {
+        self.exit_destination = Some(destination.to_string());
+        self
+    }
+    /// Adds another exit, placed by the `placement` rule after
+    /// the entrance and exit, and optionally tagged with the
+    /// `destination` it leads to
+    pub fn with_extra_exit(mut self, placement: Placement, destination: Option<&str>) -> Self {
+        self.extra_exits
+            .push((placement, destination.map(|d| d.to_string())));
+        self
+    }
+    /// Sets the number of loops carved into the dungeon, each
+    /// joining two parts of the paths which are close together,
+    /// but far apart to walk between
+    pub fn with_loops(mut self, count: u32) -> Self {
+        self.loops = count;
+        self
+    }
+    /// Sets the number of locked doors placed between the
+    /// entrance and exit, each with a key to be found before it
+    pub fn with_locks(mut self, count: u32) -> Self {
+        self.locks = count;
+        self
+    }
+    /// Sets the number of vaults stamped into the dungeon,
+    /// each chosen at random from the `library`
+    pub fn with_vaults(mut self, library: Vec<Vault>, count: u32) -> Self {
+        self.vaults = library;
+        self.vault_count = count;
+        self
+    }
+    /// Sets the number of hazards - water, pits, traps and
+    /// rubble - placed off the way from the entrance to the exit
+    pub fn with_hazards(mut self, count: u32) -> Self {
+        self.hazards = count;
+        self
+    }
+    /// Sets the number of regions the dungeon is split into,
+    /// each themed as a biome chosen at random
+    pub fn with_biomes(mut self, regions: u32) -> Self {
+        self.biomes = regions;
+        self
+    }
+    /// Sets the number of decorations - torches, bones and puddles -
+    /// scattered through the dungeon to suit the biome of each region.
+    /// Only placed when the dungeon is split into regions.
+    pub fn with_decorations(mut self, count: u32) -> Self {
+        self.decorations = count;
+        self
+    }
+    /// Sets the difficulty `band` each floor of a `Tower` should fall
+    /// within. Floors outside it are generated again, up to `attempts`
+    /// times in all. `attempts` is kept at a minimum of 1.
+    pub fn with_difficulty(mut self, band: DifficultyBand, attempts: u32) -> Self {
+        self.difficulty = band;
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// The seed the `Dungeon` will be generated from, if one was set
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+    /// The horizontal bounds of the `Dungeon`
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+    /// The vertical bounds of the `Dungeon`
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+    /// The space between perlin noise values
+    pub fn noise_scale(&self) -> (f64, f64) {
+        self.noise_scale
+    }
+    /// The threshold a perlin noise value must be greater than to be a path
+    pub fn noise_threshold(&self) -> f64 {
+        self.noise_threshold
+    }
+    /// The weighting of each direction the main path can step in
+    pub fn direction_bias(&self) -> DirectionBias {
+        self.direction_bias
+    }
+    /// The rule used to place the entrance
+    pub fn entrance(&self) -> Placement {
+        self.entrance
+    }
+    /// The rule used to place the exit
+    pub fn exit(&self) -> Placement {
+        self.exit
+    }
+    /// The destination the exit is tagged with, if any
+    pub fn exit_destination(&self) -> Option<&str> {
+        self.exit_destination.as_deref()
+    }
+    /// The rules used to place any extra exits, and their destinations
+    pub fn extra_exits(&self) -> &[(Placement, Option<String>)] {
+        &self.extra_exits
+    }
+    /// The number of loops carved into the dungeon
+    pub fn loops(&self) -> u32 {
+        self.loops
+    }
+    /// The number of locked doors placed between the entrance and exit
+    pub fn locks(&self) -> u32 {
+        self.locks
+    }
+    /// The library of vaults stamped into the dungeon
+    pub fn vaults(&self) -> &[Vault] {
+        &self.vaults
+    }
+    /// The number of vaults stamped into the dungeon
+    pub fn vault_count(&self) -> u32 {
+        self.vault_count
+    }
+    /// The number of hazards placed in the dungeon
+    pub fn hazards(&self) -> u32 {
+        self.hazards
+    }
+    /// The number of biome regions the dungeon is split into
+    pub fn biomes(&self) -> u32 {
+        self.biomes
+    }
+    /// The number of decorations scattered through the dungeon
+    pub fn decorations(&self) -> u32 {
+        self.decorations
+    }
+    /// The difficulty band each floor of a `Tower` should fall within
+    pub fn difficulty(&self) -> &DifficultyBand {
+        &self.difficulty
+    }
+    /// The most times each floor of a `Tower` is generated
+    /// while looking for one within the difficulty band
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+}
@@ -0,0 +1,130 @@
+//! Difficulty scoring of a Dungeon's layout
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use std::{collections::HashSet, ops::RangeInclusive};
+
+use crate::{
+    analysis::{chokepoints_between, shortest_route},
+    gen::NEIGHBOURS,
+    inst::Dungeon,
+};
+
+///
+/// A report on how hard a `Dungeon`'s layout is to get through,
+/// measured along its critical path - the shortest walk
+/// from the entrance to the exit.
+///
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DifficultyReport {
+    /// The number of steps along the critical path
+    pub path_length: u32,
+    /// The number of tiles on the critical path, over the
+    /// number of tiles of floor. Low ratios leave more of
+    /// the dungeon to wander off into.
+    pub path_ratio: f32,
+    /// The average number of side branches leaving
+    /// each tile of the critical path
+    pub branching: f32,
+    /// The number of tiles on the critical path which every
+    /// walk to the exit must pass through, not counting
+    /// the entrance and exit themselves
+    pub chokepoints: usize,
+}
+
+impl Dungeon {
+    ///
+    /// Computes the `DifficultyReport` of the `Dungeon`'s layout.
+    /// Returns `None` if the exit can't be reached from the entrance.
+    ///
+    pub fn difficulty_report(&self) -> Option<DifficultyReport> {
+        let paths = self.paths_ref();
+        let route = shortest_route(paths, self.entrance, self.exit)?;
+        let on_route: HashSet<&(i32, i32)> = route.iter().collect();
+
+        let branches = route
+            .iter()
+            .flat_map(|tile| {
+                NEIGHBOURS
+                    .iter()
+                    .map(move |dir| (tile.0 + dir.0, tile.1 + dir.1))
+            })
+            .filter(|adj| paths.contains(adj) && !on_route.contains(adj))
+            .count();
+
+        Some(DifficultyReport {
+            path_length: route.len() as u32 - 1,
+            path_ratio: route.len() as f32 / paths.len() as f32,
+            branching: branches as f32 / route.len() as f32,
+            chokepoints: chokepoints_between(paths, self.entrance, self.exit).len(),
+        })
+    }
+}
+
+///
+/// A target band of difficulty, as the range each measure of
+/// a `DifficultyReport` must fall within. By default every
+/// range is unbounded, so every report falls in the band.
+///
+#[derive(Debug, Clone, PartialEq)]
+pub struct DifficultyBand {
+    path_length: RangeInclusive<u32>,
+    path_ratio: RangeInclusive<f32>,
+    branching: RangeInclusive<f32>,
+    chokepoints: RangeInclusive<usize>,
+}
+
+impl Default for DifficultyBand {
+    fn default() -> Self {
+        Self {
+            path_length: 0..=u32::MAX,
+            path_ratio: 0.0..=f32::INFINITY,
+            branching: 0.0..=f32::INFINITY,
+            chokepoints: 0..=usize::MAX,
+        }
+    }
+}
+
+impl DifficultyBand {
+    /// Creates a new, unbounded `DifficultyBand`
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the range of critical path lengths in the band
+    pub fn with_path_length(mut self, range: RangeInclusive<u32>) -> Self {
+        self.path_length = range;
+        self
+    }
+    /// Sets the range of critical path to floor area ratios in the band
+    pub fn with_path_ratio(mut self, range: RangeInclusive<f32>) -> Self {
+        self.path_ratio = range;
+        self
+    }
+    /// Sets the range of branching factors in the band
+    pub fn with_branching(mut self, range: RangeInclusive<f32>) -> Self {
+        self.branching = range;
+        self
+    }
+    /// Sets the range of chokepoint counts in the band
+    pub fn with_chokepoints(mut self, range: RangeInclusive<usize>) -> Self {
+        self.chokepoints = range;
+        self
+    }
+    /// Tests if every measure of the `report` falls in the band
+    pub fn contains(&self, report: &DifficultyReport) -> bool {
+        self.path_length.contains(&report.path_length)
+            && self.path_ratio.contains(&report.path_ratio)
+            && self.branching.contains(&report.branching)
+            && self.chokepoints.contains(&report.chokepoints)
+    }
+    ///
+    /// Tests if the `dungeon` falls in the band. A `Dungeon`
+    /// whose exit can't be reached never does.
+    ///
+    pub fn accepts(&self, dungeon: &Dungeon) -> bool {
+        dungeon
+            .difficulty_report()
+            .is_some_and(|report| self.contains(&report))
+    }
+}
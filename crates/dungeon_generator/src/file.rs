@@ -0,0 +1,345 @@
+//! Human-editable file format for Dungeons
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+//!
+//! A dungeon file begins with a version line, followed by
+//! any number of header lines, and ends with the map itself:
+//!
+//! ```text
+//! dungeon 1
+//! // comments start with two slashes
+//! seed 42
+//! loops 1
+//! exit 7 3 surface
+//! exit 7 2
+//! room start start 1 1 3 3
+//! room exit exit 5 1 3 3
+//! corridor 0 1 2,2 3,2 4,2 5,2
+//! lock 4 2 2 1
+//! vault guard\sroom 5 1 3 3
+//! spawn 6 1
+//! treasure 6 2
+//! region crypt 2 2
+//! region sewer 6 2
+//! decoration torch 0 2
+//! decoration bones 3 3
+//! map
+//! #########
+//! #<..#...#
+//! #...+..>#
+//! #~..#..>#
+//! #########
+//! ```
+//!
+//! In the map, `#` is a wall, `.` is a path, `<` is the entrance
+//! and `>` is the exit. Other tiles are drawn as their `Tile::symbol`:
+//! `~` is water, `_` a pit, `+` a door, `^` a trap and `%` rubble.
+//! The map's rows give the dungeon's height, and its longest row
+//! gives the width. The number of loops carved into the paths is
+//! given if there are any. Exits are given by their position, and
+//! the destination they lead to, if they have one, starting with
+//! the main exit. Files without exits take the only `>` in their
+//! map as the exit, so simple maps need no exit lines. Rooms are
+//! given by their name, kind, x, y, width and height; corridors by the indices of
+//! the rooms they join and their tiles; locks by the position
+//! of their door, then of their key; and vaults by their name, x, y,
+//! width and height, followed by the position of each of their
+//! monster spawns and treasure. Biome regions are given by their
+//! biome and the position of their site, and decorations by their
+//! name and position.
+//!
+//! The names of rooms and vaults, and the destinations of exits, are
+//! each written as a single word by `simple_serializer::escape_word`,
+//! escaped as they are on the wire, with spaces written as `\s` and
+//! other whitespace escaped too - so `guard room` is written
+//! `guard\sroom`. An empty name is written as `\e`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::Path,
+    str::FromStr,
+};
+
+use simple_serializer::{escape_word, unescape};
+
+use crate::{
+    biomes::{Biome, Decoration, Region},
+    inst::{Dungeon, Exit},
+    locks::Lock,
+    rooms::{Corridor, Rect, Room, RoomKind},
+    tiles::Tile,
+    vaults::PlacedVault,
+};
+
+/// The version of the file format written by `Dungeon::to_text`
+pub const FORMAT_VERSION: u32 = 1;
+
+impl Dungeon {
+    ///
+    /// Writes the `Dungeon` in the human-editable file
+    /// format, which `Dungeon::from_text` reads back.
+    ///
+    pub fn to_text(&self) -> String {
+        let mut text = format!("dungeon {}\nseed {}\n", FORMAT_VERSION, self.seed());
+        if self.loops() > 0 {
+            text.push_str(&format!("loops {}\n", self.loops()));
+        }
+
+        for exit in self.exits() {
+            text.push_str(&format!("exit {} {}", exit.pos.0, exit.pos.1));
+            if let Some(destination) = &exit.destination {
+                text.push_str(&format!(" {}", escape_word(destination)));
+            }
+            text.push('\n');
+        }
+
+        for room in self.rooms() {
+            let Rect { x, y, w, h } = room.bounds;
+            text.push_str(&format!(
+                "room {} {} {} {} {} {}\n",
+                escape_word(&room.name),
+                room.kind.name(),
+                x,
+                y,
+                w,
+                h
+            ));
+        }
+        for corridor in self.corridors() {
+            text.push_str(&format!("corridor {} {}", corridor.from, corridor.to));
+            for tile in corridor.tiles.iter() {
+                text.push_str(&format!(" {},{}", tile.0, tile.1));
+            }
+            text.push('\n');
+        }
+        for lock in self.locks() {
+            text.push_str(&format!(
+                "lock {} {} {} {}\n",
+                lock.door.0, lock.door.1, lock.key.0, lock.key.1
+            ));
+        }
+        for vault in self.vaults() {
+            let Rect { x, y, w, h } = vault.bounds;
+            text.push_str(&format!(
+                "vault {} {} {} {} {}\n",
+                escape_word(&vault.name),
+                x,
+                y,
+                w,
+                h
+            ));
+            for spawn in vault.spawns.iter() {
+                text.push_str(&format!("spawn {} {}\n", spawn.0, spawn.1));
+            }
+            for treasure in vault.treasure.iter() {
+                text.push_str(&format!("treasure {} {}\n", treasure.0, treasure.1));
+            }
+        }
+
+        for region in self.regions() {
+            let (x, y) = region.site;
+            text.push_str(&format!("region {} {} {}\n", region.biome.name(), x, y));
+        }
+        let mut decorations: Vec<_> = self.decorations().iter().collect();
+        decorations.sort_unstable_by_key(|(point, _)| **point);
+        for ((x, y), decoration) in decorations {
+            text.push_str(&format!("decoration {} {} {}\n", decoration.name(), x, y));
+        }
+
+        text.push_str("map\n");
+        for row in 0..self.height() as i32 {
+            for col in 0..self.width() as i32 {
+                text.push(if self.entrance == (col, row) {
+                    '<'
+                } else if self.is_exit((col, row)) {
+                    '>'
+                } else {
+                    self.tile_at((col, row)).symbol()
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    ///
+    /// Reads a `Dungeon` from the human-editable file format.
+    /// Returns `None` if the text is malformed, is of an unknown
+    /// version, its map doesn't have exactly one entrance, its exits
+    /// don't match the map, or a spawn or treasure comes before any vault.
+    ///
+    pub fn from_text(text: &str) -> Option<Dungeon> {
+        let mut lines = text.lines().map(|line| line.trim_end());
+        let version: u32 = match lines
+            .next()?
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            ["dungeon", version] => version.parse().ok()?,
+            _ => return None,
+        };
+        if version != FORMAT_VERSION {
+            return None;
+        }
+
+        let (mut seed, mut loops) = (0, 0);
+        let (mut rooms, mut corridors, mut locks) = (vec![], vec![], vec![]);
+        let mut vaults = Vec::<PlacedVault>::new();
+        let mut exits = Vec::new();
+        let (mut regions, mut decorations) = (Vec::new(), HashMap::new());
+        for line in &mut lines {
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [] => {}
+                [comment, ..] if comment.starts_with("//") => {}
+                ["seed", s] => seed = s.parse().ok()?,
+                ["loops", count] => loops = count.parse().ok()?,
+                ["exit", x, y, destination @ ..] if destination.len() < 2 => exits.push(Exit {
+                    pos: (x.parse().ok()?, y.parse().ok()?),
+                    destination: match destination.first() {
+                        Some(destination) => Some(unescape(destination).ok()?),
+                        None => None,
+                    },
+                }),
+                ["room", room, kind, x, y, w, h] => rooms.push(Room {
+                    name: unescape(room).ok()?,
+                    kind: RoomKind::from_name(kind)?,
+                    bounds: Rect::new(
+                        x.parse().ok()?,
+                        y.parse().ok()?,
+                        w.parse().ok()?,
+                        h.parse().ok()?,
+                    ),
+                }),
+                ["corridor", from, to, tiles @ ..] => corridors.push(Corridor {
+                    from: from.parse().ok()?,
+                    to: to.parse().ok()?,
+                    tiles: tiles
+                        .iter()
+                        .map(|tile| parse_point(tile))
+                        .collect::<Option<_>>()?,
+                }),
+                ["lock", dx, dy, kx, ky] => locks.push(Lock {
+                    door: (dx.parse().ok()?, dy.parse().ok()?),
+                    key: (kx.parse().ok()?, ky.parse().ok()?),
+                }),
+                ["vault", vault, x, y, w, h] => vaults.push(PlacedVault {
+                    name: unescape(vault).ok()?,
+                    bounds: Rect::new(
+                        x.parse().ok()?,
+                        y.parse().ok()?,
+                        w.parse().ok()?,
+                        h.parse().ok()?,
+                    ),
+                    spawns: Vec::new(),
+                    treasure: Vec::new(),
+                }),
+                ["spawn", x, y] => vaults
+                    .last_mut()?
+                    .spawns
+                    .push((x.parse().ok()?, y.parse().ok()?)),
+                ["treasure", x, y] => vaults
+                    .last_mut()?
+                    .treasure
+                    .push((x.parse().ok()?, y.parse().ok()?)),
+                ["region", biome, x, y] => regions.push(Region {
+                    site: (x.parse().ok()?, y.parse().ok()?),
+                    biome: Biome::from_name(biome)?,
+                }),
+                ["decoration", name, x, y] => {
+                    decorations.insert(
+                        (x.parse().ok()?, y.parse().ok()?),
+                        Decoration::from_name(name)?,
+                    );
+                }
+                ["map"] => break,
+                _ => return None,
+            }
+        }
+
+        // The rest of the lines are the map
+        let mut entrance = None;
+        let mut marked = Vec::new();
+        let mut paths = HashSet::new();
+        let mut tiles = HashMap::new();
+        let (mut width, mut height) = (0, 0);
+        for (row, line) in lines.enumerate() {
+            for (col, tile) in line.chars().enumerate() {
+                let point = (col as i32, row as i32);
+                match tile {
+                    '<' if entrance.is_none() => entrance = Some(point),
+                    '<' => return None,
+                    '>' => marked.push(point),
+                    _ => match Tile::from_symbol(tile)? {
+                        Tile::Wall => continue,
+                        Tile::Floor => {}
+                        other => {
+                            tiles.insert(point, other);
+                            if !other.is_walkable() {
+                                continue;
+                            }
+                        }
+                    },
+                }
+                paths.insert(point);
+            }
+            width = width.max(line.chars().count() as u32);
+            height = row as u32 + 1;
+        }
+
+        // Without any exits given, the map must mark only one
+        if exits.is_empty() && marked.len() == 1 {
+            exits.push(Exit {
+                pos: marked[0],
+                destination: None,
+            });
+        }
+        let mut positions: Vec<(i32, i32)> = exits.iter().map(|e| e.pos).collect();
+        positions.sort_unstable();
+        marked.sort_unstable();
+        if exits.is_empty() || positions != marked {
+            return None;
+        }
+
+        Some(
+            Dungeon::from_paths(seed, width, height, entrance?, exits[0].pos, paths)
+                .with_loops(loops)
+                .with_rooms(rooms, corridors)
+                .with_locks(locks)
+                .with_vaults(vaults)
+                .with_tiles(tiles)
+                .with_exits(exits)
+                .with_regions(regions)
+                .with_decorations(decorations),
+        )
+    }
+
+    ///
+    /// Loads a `Dungeon` from the file at `path`, written in the
+    /// human-editable file format.
+    ///
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Dungeon> {
+        Dungeon::from_text(&fs::read_to_string(path)?)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed dungeon file"))
+    }
+
+    ///
+    /// Saves the `Dungeon` to the file at `path`, in the
+    /// human-editable file format.
+    ///
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+}
+
+/// Parses a point written as `x,y`
+fn parse_point(text: &str) -> Option<(i32, i32)> {
+    let mut parts = text.split(',').map(i32::from_str);
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(x)), Some(Ok(y)), None) => Some((x, y)),
+        _ => None,
+    }
+}
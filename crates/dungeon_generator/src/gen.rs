@@ -3,144 +3,241 @@
 //! Christian Schmid - April 2021
 //! CS510 - Rust Programming
 
-use rand::prelude::*;
+use rand::{prelude::*, rngs::StdRng};
 use std::collections::HashSet;
 
-use noise::{NoiseFn, Perlin};
+use crate::{
+    config::{DungeonConfig, Edge, Placement},
+    inst::Exit,
+};
 
-/// The space between perlin noise values
-const PERLIN_SCALE: (f64, f64) = (0.25, 0.25);
+/// The four cardinal directions a path connects through
+pub const NEIGHBOURS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
 
-/// The threshold a perlin noise value must be greater than to be a path
-const PERLIN_THRESHOLD: f64 = 0.05;
+/// The number of times an entrance or exit is placed again
+/// when it lands on a point already taken
+const PLACE_ATTEMPTS: u32 = 100;
 
-/// Generates a path, given a perlin `seed`,
-/// `width` and `height` of the map, and the
-/// `entrance` and `exit` points
-pub fn gen_paths(
-    seed: f64,
-    width: u32,
-    height: u32,
+/// Resolves the seed `config` is generated from, choosing a random
+/// one if it has none, and creates the generator every random
+/// choice is drawn from.
+pub fn seeded_rng(config: &DungeonConfig) -> (u64, StdRng) {
+    let seed = config.seed().unwrap_or_else(|| thread_rng().gen());
+    (seed, StdRng::seed_from_u64(seed))
+}
+
+/// Chooses the entrance and exit points of a dungeon,
+/// following the placement rules in `config`. The exit is
+/// kept off the entrance, even when placed at an exact position.
+pub fn gen_entrance_exit(rng: &mut StdRng, config: &DungeonConfig) -> ((i32, i32), (i32, i32)) {
+    let (width, height) = (config.width(), config.height());
+
+    // By default, the entrance starts on a random spot
+    // on the top or bottom edge, and the exit starts on
+    // the opposite edge.
+    let entrance = match config.entrance() {
+        Placement::Random => (
+            (rng.next_u32() % width) as i32,
+            if rng.gen::<bool>() {
+                0
+            } else {
+                (height - 1) as i32
+            },
+        ),
+        other => place(rng, other, width, height),
+    };
+    let exit = match config.exit() {
+        // An entrance on the left or right edge has its
+        // exit on the opposite side
+        Placement::Random if is_on_side(entrance, width, height) => (
+            if entrance.0 == 0 {
+                (width - 1) as i32
+            } else {
+                0
+            },
+            (rng.next_u32() % height) as i32,
+        ),
+        Placement::Random => (
+            (rng.next_u32() % width) as i32,
+            if entrance.1 < (height / 2) as i32 {
+                (height - 1) as i32
+            } else {
+                0
+            },
+        ),
+        other => place_clear(rng, other, width, height, &[entrance]),
+    };
+    (entrance, exit)
+}
+
+/// Chooses the points of any extra exits of a dungeon, following
+/// the placement rules in `config`, and clear of the `entrance`,
+/// the main `exit` and each other. Randomly placed extra exits
+/// lie on any edge.
+pub fn gen_extra_exits(
+    rng: &mut StdRng,
+    config: &DungeonConfig,
     entrance: (i32, i32),
     exit: (i32, i32),
-) -> HashSet<(i32, i32)> {
-    // Convert the u32 values to i32s, to ensure that there
-    // is no extra conversions, or overflow in the increment / decrementation
-    let (width, height) = (width as i32, height as i32);
-    let entrance = (entrance.0 as i32, entrance.1 as i32);
-    let exit = (exit.0 as i32, exit.1 as i32);
-
-    let mut paths = build_path(entrance, exit, width);
-    layer_path(&mut paths, seed, width, height);
+) -> Vec<(i32, i32)> {
+    let (width, height) = (config.width(), config.height());
+    let mut taken = vec![entrance, exit];
+    for (placement, _) in config.extra_exits() {
+        let extra = place_clear(rng, *placement, width, height, &taken);
+        taken.push(extra);
+    }
+    taken.split_off(2)
+}
 
-    paths
+/// Collects the points of the main `exit` and any `extra_exits`
+pub fn exits_of(exit: (i32, i32), extra_exits: &[(i32, i32)]) -> Vec<(i32, i32)> {
+    std::iter::once(exit)
+        .chain(extra_exits.iter().cloned())
+        .collect()
 }
 
-/// Generate a path from the entrance to exit. This is NOT
-/// a shortest-path route, but rather a random path
-fn build_path(entrance: (i32, i32), exit: (i32, i32), width: i32) -> HashSet<(i32, i32)> {
-    // Random number generator
-    let mut rnd = thread_rng();
-
-    // The paths set, returned at end
-    let mut paths: HashSet<(i32, i32)> = [entrance, exit].iter().cloned().collect();
-
-    // This algorithm ensures that the path being generated is always heading
-    // towards the exit.
-    let mut current = entrance;
-    let mut last = current;
-    let mut move_dirs = Vec::with_capacity(3);
-    let y_dir: i32 = if entrance.1 == 0 { 1 } else { -1 };
-
-    // While the exit hasn't been reached, continue building the path
-    while current != exit {
-        // Clear the potential move directions
-        move_dirs.clear();
-
-        // If the current point and exit have the same
-        // y-coordinate, travers current to the exit.
-        if current.1 == exit.1 {
-            current = if current.0 < exit.0 {
-                (current.0 + 1, current.1)
-            } else {
-                (current.0 - 1, current.1)
-            }
-        // Otherwise, create a list of potential directions
-        // the path can traverse next
-        } else {
-            // If current's X is not on the left border, add
-            // left direction
-            if current.0 != 0 {
-                move_dirs.push((current.0 - 1, current.1));
-                move_dirs.push((current.0 - 1, current.1));
-                if current.0 > exit.0 {
-                    move_dirs.push((current.0 - 1, current.1));
-                }
-            }
-            // If current's X is not on the right border, add
-            // right direction
-            if current.0 < width - 1 {
-                move_dirs.push((current.0 + 1, current.1));
-                move_dirs.push((current.0 + 1, current.1));
-                if current.0 < exit.0 {
-                    move_dirs.push((current.0 + 1, current.1));
-                }
-            }
-            // Finally, push moving the y-coord towards the exit.
-            move_dirs.push((current.0, current.1 + y_dir));
-
-            // Choose one of the potential directions as the new direction for the path.
-            current = *move_dirs
+/// Tags the main `exit` and `extra_exits` with the
+/// destinations given for them in `config`
+pub fn tag_exits(
+    config: &DungeonConfig,
+    exit: (i32, i32),
+    extra_exits: &[(i32, i32)],
+) -> Vec<Exit> {
+    let main = Exit {
+        pos: exit,
+        destination: config.exit_destination().map(|d| d.to_string()),
+    };
+    std::iter::once(main)
+        .chain(
+            extra_exits
                 .iter()
-                .filter(|dir| **dir != last)
-                .choose(&mut rnd)
+                .zip(config.extra_exits())
+                .map(|(pos, (_, destination))| Exit {
+                    pos: *pos,
+                    destination: destination.clone(),
+                }),
+        )
+        .collect()
+}
+
+/// Chooses a spot following a `placement` rule. A `Random`
+/// placement is a random spot on any edge, and an exact
+/// position outside the dungeon is moved to the nearest
+/// point within it, as is an interior spot of a dungeon
+/// too thin to have an interior.
+fn place(rng: &mut StdRng, placement: Placement, width: u32, height: u32) -> (i32, i32) {
+    match placement {
+        Placement::Random => {
+            let edge = *[Edge::Top, Edge::Bottom, Edge::Left, Edge::Right]
+                .choose(rng)
                 .unwrap();
+            on_edge(rng, edge, width, height)
         }
-
-        last = current;
-        paths.insert(current);
+        Placement::Edge(edge) => on_edge(rng, edge, width, height),
+        Placement::Interior => {
+            let x = 1 + (rng.next_u32() % width.saturating_sub(2).max(1)) as i32;
+            let y = 1 + (rng.next_u32() % height.saturating_sub(2).max(1)) as i32;
+            (x.clamp(0, width as i32 - 1), y.clamp(0, height as i32 - 1))
+        }
+        Placement::At(x, y) => (x.clamp(0, width as i32 - 1), y.clamp(0, height as i32 - 1)),
     }
-
-    paths
 }
 
-/// Layers a given path with perlin noise, adding more naturalness and
-/// variability to the path.
-fn layer_path(paths: &mut HashSet<(i32, i32)>, seed: f64, width: i32, height: i32) {
-    let perlin = Perlin::new();
-    let mut prln_path = HashSet::new();
-    let seed = if seed.is_sign_positive() { seed } else { -seed };
-
-    // Generate perlin noise paths, as a rough
-    // estimate of the dungeon
-    for row in 1..=height {
-        for col in 1..=width {
-            let p = perlin.get([
-                seed + row as f64 * PERLIN_SCALE.0,
-                seed + col as f64 * PERLIN_SCALE.1,
-            ]);
-            if p >= PERLIN_THRESHOLD {
-                prln_path.insert((col - 1, row - 1));
-            }
+/// Chooses a point by the `placement` rule, as `place` does, choosing
+/// again while it lands on one of the `taken` points. The last point
+/// chosen is kept if every attempt lands on a taken point. An exact
+/// position which is taken is moved to the nearest point which isn't,
+/// and is only kept if every point of the dungeon is taken.
+fn place_clear(
+    rng: &mut StdRng,
+    placement: Placement,
+    width: u32,
+    height: u32,
+    taken: &[(i32, i32)],
+) -> (i32, i32) {
+    let mut point = place(rng, placement, width, height);
+    if let Placement::At(..) = placement {
+        // Ties are broken top to bottom, then left to right,
+        // so the same spot is always chosen
+        return (0..height as i32)
+            .flat_map(|y| (0..width as i32).map(move |x| (x, y)))
+            .filter(|p| !taken.contains(p))
+            .min_by_key(|p| ((p.0 - point.0).abs() + (p.1 - point.1).abs(), p.1, p.0))
+            .unwrap_or(point);
+    }
+    for _ in 0..PLACE_ATTEMPTS {
+        if !taken.contains(&point) {
+            break;
         }
+        point = place(rng, placement, width, height);
     }
+    point
+}
 
-    // Ensure that the perlin noise added to the dungeon
-    // is connected to the path. Remove those sections if they are not.
-    let mut added_to = true;
-
-    while added_to {
-        added_to = false;
-        for path_seg in prln_path.clone().iter() {
-            if paths.contains(&(path_seg.0 - 1, path_seg.1))
-                || paths.contains(&(path_seg.0 + 1, path_seg.1))
-                || paths.contains(&(path_seg.0, path_seg.1 + 1))
-                || paths.contains(&(path_seg.0, path_seg.1 - 1))
-            {
-                paths.insert(*path_seg);
-                prln_path.remove(&path_seg);
-                added_to = true;
-            }
-        }
+/// Chooses a random spot along the given `edge`
+fn on_edge(rng: &mut StdRng, edge: Edge, width: u32, height: u32) -> (i32, i32) {
+    match edge {
+        Edge::Top => ((rng.next_u32() % width) as i32, 0),
+        Edge::Bottom => ((rng.next_u32() % width) as i32, (height - 1) as i32),
+        Edge::Left => (0, (rng.next_u32() % height) as i32),
+        Edge::Right => ((width - 1) as i32, (rng.next_u32() % height) as i32),
     }
 }
+
+/// Tests if the given `point` lies on the left or right edge,
+/// but not in a corner shared with the top or bottom edge
+pub fn is_on_side(point: (i32, i32), width: u32, height: u32) -> bool {
+    (point.0 == 0 || point.0 == width as i32 - 1) && point.1 > 0 && point.1 < height as i32 - 1
+}
+
+/// Tests if the given `point` lies within `width` and `height`
+pub fn in_bounds(point: (i32, i32), width: u32, height: u32) -> bool {
+    point.0 >= 0 && point.1 >= 0 && point.0 < width as i32 && point.1 < height as i32
+}
+
+/// Carves an L-shaped corridor into `paths` from `from` to `to`,
+/// randomly choosing whether to travel horizontally or vertically first.
+/// Returns the tiles of the corridor, in order from `from` to `to`.
+pub fn carve_corridor(
+    rng: &mut StdRng,
+    paths: &mut HashSet<(i32, i32)>,
+    from: (i32, i32),
+    to: (i32, i32),
+) -> Vec<(i32, i32)> {
+    let corner = if rng.gen::<bool>() {
+        (to.0, from.1)
+    } else {
+        (from.0, to.1)
+    };
+    let mut tiles = Vec::new();
+    carve_line(&mut tiles, from, corner);
+    tiles.pop();
+    carve_line(&mut tiles, corner, to);
+
+    paths.extend(tiles.iter().cloned());
+    tiles
+}
+
+/// Pushes each tile of a straight horizontal or vertical
+/// line onto `tiles` from `from` to `to`, inclusive.
+fn carve_line(tiles: &mut Vec<(i32, i32)>, from: (i32, i32), to: (i32, i32)) {
+    let step = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+    let mut current = from;
+    tiles.push(current);
+    while current != to {
+        current = (current.0 + step.0, current.1 + step.1);
+        tiles.push(current);
+    }
+}
+
+/// Finds the point in `points` closest to `target`, by
+/// manhattan distance. Ties are broken by position, so the
+/// result does not depend on the iteration order of `points`.
+pub fn nearest<'a>(
+    points: impl Iterator<Item = &'a (i32, i32)>,
+    target: (i32, i32),
+) -> Option<(i32, i32)> {
+    points
+        .min_by_key(|p| ((p.0 - target.0).abs() + (p.1 - target.1).abs(), **p))
+        .cloned()
+}
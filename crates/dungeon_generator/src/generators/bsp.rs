@@ -0,0 +1,178 @@
+//! Binary space partitioning Generator, for rooms and corridors
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use rand::{prelude::*, rngs::StdRng};
+use std::collections::HashSet;
+
+use crate::{
+    config::DungeonConfig,
+    gen::{carve_corridor, exits_of, gen_entrance_exit, gen_extra_exits, nearest, seeded_rng},
+    generators::{finish, Carved},
+    inst::Dungeon,
+    rooms::{Corridor, Rect},
+    traits::Generator,
+};
+
+///
+/// Generates dungeons of rectangular rooms joined by
+/// corridors. The dungeon is recursively split in two
+/// until each partition is too small to split further,
+/// a room is placed in each partition, and the rooms of
+/// each pair of partitions are joined. The rooms and the
+/// corridors joining them are recorded on the `Dungeon`.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct BspGenerator {
+    /// The smallest width or height a partition can be split down to
+    pub min_partition: u32,
+    /// The smallest width or height of a room
+    pub min_room: u32,
+}
+
+impl Default for BspGenerator {
+    fn default() -> Self {
+        Self {
+            min_partition: 9,
+            min_room: 3,
+        }
+    }
+}
+
+impl Generator for BspGenerator {
+    fn generate(&self, config: &DungeonConfig) -> Dungeon {
+        let (seed, mut rng) = seeded_rng(config);
+        let (entrance, exit) = gen_entrance_exit(&mut rng, config);
+        let extra_exits = gen_extra_exits(&mut rng, config, entrance, exit);
+        let exits = exits_of(exit, &extra_exits);
+
+        let bounds = Rect::new(0, 0, config.width() as i32, config.height() as i32);
+        let mut layout = Layout::default();
+        self.split(&mut rng, bounds, &mut layout);
+        let Layout {
+            mut paths,
+            rooms,
+            corridors,
+        } = layout;
+
+        // Join the entrance and exits to the rooms closest to them
+        for point in std::iter::once(&entrance).chain(exits.iter()) {
+            let target = nearest(paths.iter(), *point).unwrap_or(*point);
+            carve_corridor(&mut rng, &mut paths, *point, target);
+        }
+
+        finish(
+            &mut rng,
+            config,
+            Carved {
+                seed,
+                entrance,
+                exit,
+                extra_exits,
+                paths,
+                rooms,
+                corridors,
+            },
+        )
+    }
+}
+
+///
+/// The paths, rooms and corridors carved so far
+/// while splitting the dungeon.
+///
+#[derive(Default)]
+struct Layout {
+    paths: HashSet<(i32, i32)>,
+    rooms: Vec<Rect>,
+    corridors: Vec<Corridor>,
+}
+
+impl BspGenerator {
+    /// Recursively splits `rect` into two partitions, carving a room
+    /// into the `layout` for each partition too small to split, and
+    /// joining the rooms of both sides with a corridor.
+    /// Returns the indices of every room carved within `rect`.
+    fn split(&self, rng: &mut StdRng, rect: Rect, layout: &mut Layout) -> Vec<usize> {
+        let min = self.min_partition.max(1) as i32;
+        let (can_split_x, can_split_y) = (rect.w >= min * 2, rect.h >= min * 2);
+
+        // If the partition can't be split, it becomes a room
+        if !can_split_x && !can_split_y {
+            let room = self.place_room(rng, rect);
+            layout.paths.extend(room.points());
+            layout.rooms.push(room);
+            return vec![layout.rooms.len() - 1];
+        }
+
+        // Split along the longer side, or randomly if both
+        // sides can be split and are of similar length
+        let split_x = match (can_split_x, can_split_y) {
+            (true, false) => true,
+            (false, true) => false,
+            _ if rect.w > rect.h * 5 / 4 => true,
+            _ if rect.h > rect.w * 5 / 4 => false,
+            _ => rng.gen::<bool>(),
+        };
+
+        let (first, second) = if split_x {
+            let at = rng.gen_range(min..=rect.w - min);
+            (
+                Rect { w: at, ..rect },
+                Rect {
+                    x: rect.x + at,
+                    w: rect.w - at,
+                    ..rect
+                },
+            )
+        } else {
+            let at = rng.gen_range(min..=rect.h - min);
+            (
+                Rect { h: at, ..rect },
+                Rect {
+                    y: rect.y + at,
+                    h: rect.h - at,
+                    ..rect
+                },
+            )
+        };
+
+        let mut rooms = self.split(rng, first, layout);
+        let second_rooms = self.split(rng, second, layout);
+
+        // Join a random room from each side
+        let from = *rooms.choose(rng).unwrap();
+        let to = *second_rooms.choose(rng).unwrap();
+        let tiles = carve_corridor(
+            rng,
+            &mut layout.paths,
+            layout.rooms[from].center(),
+            layout.rooms[to].center(),
+        );
+        layout.corridors.push(Corridor { from, to, tiles });
+
+        rooms.extend(second_rooms);
+        rooms
+    }
+
+    /// Places a randomly sized room within `rect`, leaving
+    /// a margin of one tile on each side where there is space.
+    fn place_room(&self, rng: &mut StdRng, rect: Rect) -> Rect {
+        let (x, w) = Self::place_span(rng, rect.x, rect.w, self.min_room as i32);
+        let (y, h) = Self::place_span(rng, rect.y, rect.h, self.min_room as i32);
+        Rect::new(x, y, w, h)
+    }
+
+    /// Places a span of at least `min` length within the
+    /// span starting at `start` of length `len`
+    fn place_span(rng: &mut StdRng, start: i32, len: i32, min: i32) -> (i32, i32) {
+        if len < 3 {
+            return (start, len.max(1));
+        }
+        let max = len - 2;
+        let size = rng.gen_range(min.min(max).max(1)..=max);
+        let offset = rng.gen_range(0..=max - size);
+        (start + 1 + offset, size)
+    }
+}
@@ -0,0 +1,129 @@
+//! Cellular automata Generator, for open caves
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use rand::prelude::*;
+use std::collections::HashSet;
+
+use crate::{
+    analysis::regions,
+    config::DungeonConfig,
+    gen::{carve_corridor, exits_of, gen_entrance_exit, gen_extra_exits, nearest, seeded_rng},
+    generators::{finish, Carved},
+    inst::Dungeon,
+    traits::Generator,
+};
+
+///
+/// Generates open, organic caves. Every tile starts as
+/// a random wall or floor, and is repeatedly smoothed to
+/// match its neighbours. The largest cave left over is
+/// kept, and joined to the entrance and exit.
+///
+#[derive(Debug, Clone, Copy)]
+pub struct CaveGenerator {
+    /// The chance each tile starts as a wall, from 0 to 1
+    pub wall_chance: f64,
+    /// The number of smoothing passes made over the tiles
+    pub iterations: u32,
+}
+
+impl Default for CaveGenerator {
+    fn default() -> Self {
+        Self {
+            wall_chance: 0.45,
+            iterations: 4,
+        }
+    }
+}
+
+impl Generator for CaveGenerator {
+    fn generate(&self, config: &DungeonConfig) -> Dungeon {
+        let (seed, mut rng) = seeded_rng(config);
+        let (entrance, exit) = gen_entrance_exit(&mut rng, config);
+        let extra_exits = gen_extra_exits(&mut rng, config, entrance, exit);
+        let exits = exits_of(exit, &extra_exits);
+        let (width, height) = (config.width() as i32, config.height() as i32);
+
+        // Randomly fill the tiles with walls. The border is
+        // always walled, so the caves stay within the dungeon.
+        let mut walls = vec![false; (width * height) as usize];
+        for y in 0..height {
+            for x in 0..width {
+                walls[(y * width + x) as usize] = x == 0
+                    || y == 0
+                    || x == width - 1
+                    || y == height - 1
+                    || rng.gen::<f64>() < self.wall_chance;
+            }
+        }
+
+        // Smooth the tiles - a tile surrounded mostly by walls
+        // becomes a wall, and one surrounded mostly by floor
+        // becomes floor.
+        for _ in 0..self.iterations {
+            let last = walls.clone();
+            for y in 0..height {
+                for x in 0..width {
+                    let count = wall_neighbours(&last, x, y, width, height);
+                    if count > 4 {
+                        walls[(y * width + x) as usize] = true;
+                    } else if count < 4 {
+                        walls[(y * width + x) as usize] = false;
+                    }
+                }
+            }
+        }
+
+        let floor: HashSet<(i32, i32)> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|(x, y)| !walls[(y * width + x) as usize])
+            .collect();
+
+        // Keep only the largest cave, and join the entrance
+        // and exits to it.
+        // If there is no cave to join, join the entrance and exit directly.
+        let mut paths = regions(&floor).into_iter().next().unwrap_or_default();
+        if paths.is_empty() {
+            carve_corridor(&mut rng, &mut paths, entrance, exit);
+        }
+        for point in std::iter::once(&entrance).chain(exits.iter()) {
+            let target = nearest(paths.iter(), *point).unwrap();
+            carve_corridor(&mut rng, &mut paths, *point, target);
+        }
+
+        finish(
+            &mut rng,
+            config,
+            Carved {
+                seed,
+                entrance,
+                exit,
+                extra_exits,
+                paths,
+                rooms: Vec::new(),
+                corridors: Vec::new(),
+            },
+        )
+    }
+}
+
+/// Counts the walls surrounding the tile at (`x`, `y`), including
+/// diagonals. Tiles outside of `width` and `height` count as walls.
+fn wall_neighbours(walls: &[bool], x: i32, y: i32, width: i32, height: i32) -> u32 {
+    let mut count = 0;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 || nx >= width || ny >= height || walls[(ny * width + nx) as usize]
+            {
+                count += 1;
+            }
+        }
+    }
+    count
+}
@@ -0,0 +1,89 @@
+//! The Generators of Dungeon layouts, and the steps they share
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use rand::rngs::StdRng;
+use std::collections::HashSet;
+
+use crate::{
+    biomes::theme,
+    config::DungeonConfig,
+    gen::{exits_of, tag_exits},
+    inst::Dungeon,
+    locks::place_locks,
+    loops::add_loops,
+    rooms::{name_rooms, Corridor, Rect},
+    tiles::place_hazards,
+    vaults::stamp_vaults,
+};
+
+pub mod bsp;
+pub mod caves;
+pub mod walk;
+pub mod wfc;
+
+///
+/// The layout a `Generator` has carved, joined
+/// to its entrance and exits, before it is finished.
+///
+pub(crate) struct Carved {
+    pub seed: u64,
+    pub entrance: (i32, i32),
+    pub exit: (i32, i32),
+    pub extra_exits: Vec<(i32, i32)>,
+    pub paths: HashSet<(i32, i32)>,
+    /// The bounds of each room, for generators which carve rooms
+    pub rooms: Vec<Rect>,
+    pub corridors: Vec<Corridor>,
+}
+
+///
+/// Finishes a `Carved` layout into a `Dungeon`, following `config`:
+/// stamps in vaults, carves loops, names any rooms, places locks and
+/// hazards, tags the exits with their destinations, and themes it.
+/// Every generator finishes its layout the same way, drawing from
+/// its own `rng`, so the same seed always finishes the same way.
+///
+pub(crate) fn finish(rng: &mut StdRng, config: &DungeonConfig, carved: Carved) -> Dungeon {
+    let Carved {
+        seed,
+        entrance,
+        exit,
+        extra_exits,
+        mut paths,
+        rooms,
+        corridors,
+    } = carved;
+    let (width, height) = (config.width(), config.height());
+
+    let vaults = stamp_vaults(
+        rng,
+        &mut paths,
+        width,
+        height,
+        config.vaults(),
+        config.vault_count(),
+    );
+    let loops = add_loops(rng, &mut paths, config.loops());
+    let rooms = name_rooms(&rooms, &corridors, &paths, entrance, exit);
+    let locks = place_locks(rng, &paths, entrance, exit, config.locks());
+    let hazards = place_hazards(
+        rng,
+        &mut paths,
+        entrance,
+        &exits_of(exit, &extra_exits),
+        &locks,
+        &vaults,
+        config.hazards(),
+    );
+
+    let dungeon = Dungeon::from_paths(seed, width, height, entrance, exit, paths)
+        .with_loops(loops)
+        .with_rooms(rooms, corridors)
+        .with_locks(locks)
+        .with_vaults(vaults)
+        .with_tiles(hazards)
+        .with_exits(tag_exits(config, exit, &extra_exits));
+    theme(rng, dungeon, config)
+}
@@ -0,0 +1,233 @@
+//! Random walk Generator, layered with perlin noise
+//!
+//! Christian Schmid - April 2021
+//! CS510 - Rust Programming
+
+use rand::{prelude::*, rngs::StdRng};
+use std::collections::HashSet;
+
+use noise::{NoiseFn, Perlin};
+
+use crate::{
+    config::{DirectionBias, DungeonConfig},
+    gen::{gen_entrance_exit, gen_extra_exits, is_on_side, seeded_rng},
+    generators::{finish, Carved},
+    inst::Dungeon,
+    traits::Generator,
+};
+
+///
+/// Generates winding, cave-like dungeons. A random
+/// path is walked from the entrance to the exit, and
+/// perlin noise is layered on top of it.
+///
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WalkGenerator;
+
+impl Generator for WalkGenerator {
+    fn generate(&self, config: &DungeonConfig) -> Dungeon {
+        let (seed, mut rng) = seeded_rng(config);
+
+        // Generate an entrance and exits, following the config's placement rules
+        let (entrance, exit) = gen_entrance_exit(&mut rng, config);
+        let extra_exits = gen_extra_exits(&mut rng, config, entrance, exit);
+        let paths = gen_paths(&mut rng, config, entrance, exit, &extra_exits);
+        finish(
+            &mut rng,
+            config,
+            Carved {
+                seed,
+                entrance,
+                exit,
+                extra_exits,
+                paths,
+                rooms: Vec::new(),
+                corridors: Vec::new(),
+            },
+        )
+    }
+}
+
+/// Generates a path, given the seeded `rng`,
+/// generation `config`, and the `entrance`, `exit`
+/// and `extra_exits` points. Every random choice is
+/// drawn from `rng`, so the same seed always produces
+/// the same paths.
+fn gen_paths(
+    rng: &mut StdRng,
+    config: &DungeonConfig,
+    entrance: (i32, i32),
+    exit: (i32, i32),
+    extra_exits: &[(i32, i32)],
+) -> HashSet<(i32, i32)> {
+    // Convert the u32 values to i32s, to ensure that there
+    // is no extra conversions, or overflow in the increment / decrementation
+    let (width, height) = (config.width() as i32, config.height() as i32);
+
+    let mut paths = HashSet::new();
+    for exit in std::iter::once(&exit).chain(extra_exits.iter()) {
+        // Paths between the left and right edges are walked
+        // sideways, by walking them top to bottom with
+        // the x and y coordinates swapped
+        if is_on_side(entrance, config.width(), config.height())
+            || is_on_side(*exit, config.width(), config.height())
+        {
+            let flip = |p: (i32, i32)| (p.1, p.0);
+            let path = build_path(
+                rng,
+                config.direction_bias(),
+                flip(entrance),
+                flip(*exit),
+                height,
+            );
+            paths.extend(path.into_iter().map(flip));
+        } else {
+            paths.extend(build_path(
+                rng,
+                config.direction_bias(),
+                entrance,
+                *exit,
+                width,
+            ));
+        }
+    }
+    layer_path(
+        &mut paths,
+        rng.gen::<f64>(),
+        config.noise_scale(),
+        config.noise_threshold(),
+        width,
+        height,
+    );
+
+    paths
+}
+
+/// Generate a path from the entrance to exit. This is NOT
+/// a shortest-path route, but rather a random path
+fn build_path(
+    rng: &mut StdRng,
+    bias: DirectionBias,
+    entrance: (i32, i32),
+    exit: (i32, i32),
+    width: i32,
+) -> HashSet<(i32, i32)> {
+    // The paths set, returned at end
+    let mut paths: HashSet<(i32, i32)> = [entrance, exit].iter().cloned().collect();
+
+    // This algorithm ensures that the path being generated is always heading
+    // towards the exit.
+    let mut current = entrance;
+    let mut last = current;
+    let mut move_dirs = Vec::new();
+    let y_dir: i32 = (exit.1 - entrance.1).signum();
+
+    // While the exit hasn't been reached, continue building the path
+    while current != exit {
+        // Clear the potential move directions
+        move_dirs.clear();
+
+        // If the current point and exit have the same
+        // y-coordinate, travers current to the exit.
+        if current.1 == exit.1 {
+            current = if current.0 < exit.0 {
+                (current.0 + 1, current.1)
+            } else {
+                (current.0 - 1, current.1)
+            }
+        // Otherwise, create a list of potential directions
+        // the path can traverse next
+        } else {
+            // Each direction is pushed once for each point of
+            // weight it has in the `bias`.
+            // If current's X is not on the left border, add
+            // left direction
+            if current.0 != 0 {
+                let weight = bias.sideways
+                    + if current.0 > exit.0 {
+                        bias.towards_exit
+                    } else {
+                        0
+                    };
+                for _ in 0..weight {
+                    move_dirs.push((current.0 - 1, current.1));
+                }
+            }
+            // If current's X is not on the right border, add
+            // right direction
+            if current.0 < width - 1 {
+                let weight = bias.sideways
+                    + if current.0 < exit.0 {
+                        bias.towards_exit
+                    } else {
+                        0
+                    };
+                for _ in 0..weight {
+                    move_dirs.push((current.0 + 1, current.1));
+                }
+            }
+            // Finally, push moving the y-coord towards the exit.
+            for _ in 0..bias.forward {
+                move_dirs.push((current.0, current.1 + y_dir));
+            }
+
+            // Choose one of the potential directions as the new direction for the path.
+            current = *move_dirs
+                .iter()
+                .filter(|dir| **dir != last)
+                .choose(rng)
+                .unwrap();
+        }
+
+        last = current;
+        paths.insert(current);
+    }
+
+    paths
+}
+
+/// Layers a given path with perlin noise, adding more naturalness and
+/// variability to the path. Noise values are sampled `scale` apart,
+/// and must be at least `threshold` to become a path.
+fn layer_path(
+    paths: &mut HashSet<(i32, i32)>,
+    seed: f64,
+    scale: (f64, f64),
+    threshold: f64,
+    width: i32,
+    height: i32,
+) {
+    let perlin = Perlin::default();
+    let mut prln_path = HashSet::new();
+    let seed = if seed.is_sign_positive() { seed } else { -seed };
+
+    // Generate perlin noise paths, as a rough
+    // estimate of the dungeon
+    for row in 1..=height {
+        for col in 1..=width {
+            let p = perlin.get([seed + row as f64 * scale.0, seed + col as f64 * scale.1]);
+            if p >= threshold {
+                prln_path.insert((col - 1, row - 1));
+            }
+        }
+    }
+
+    // Ensure that the perlin noise added to the dungeon
+    // is connected to the path. Remove those sections if they are not.
+    let mut added_to = true;
+
+    while added_to {
+        added_to = false;
+        for path_seg in prln_path.clone().iter() {
+            if paths.contains(&(path_seg.0 - 1, path_seg.1))
+                || paths.contains(&(path_seg.0 + 1, path_seg.1))
+                || paths.contains(&(path_seg.0, path_seg.1 + 1))
+                || paths.contains(&(path_seg.0, path_seg.1 - 1))
+            {
+                paths.insert(*path_seg);
+                prln_path.remove(path_seg);
+                added_to = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,386 @@
+//! Wave function collapse Generator, for dungeons drawn from a sample
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+//!
+//! Every 3x3 window of walls and floor in a sample map is learned as a
+//! pattern, along with which patterns can overlap each other side by
+//! side. The dungeon starts as a wave of spots which could each hold
+//! any pattern. The spot with the fewest patterns left is collapsed to
+//! one of them, chosen by how often it appears in the sample, and the
+//! patterns which no longer fit are ruled out of the spots around it,
+//! until every spot holds a single pattern.
+
+use rand::{prelude::*, rngs::StdRng};
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analysis::regions,
+    config::DungeonConfig,
+    gen::{
+        carve_corridor, exits_of, gen_entrance_exit, gen_extra_exits, nearest, seeded_rng,
+        NEIGHBOURS,
+    },
+    generators::{finish, Carved},
+    inst::Dungeon,
+    traits::Generator,
+};
+
+/// The width and height of each pattern learned from a sample
+const PATTERN_SIZE: i32 = 3;
+
+/// The number of times the wave is collapsed, starting
+/// again each time it contradicts itself
+const ATTEMPTS: u32 = 10;
+
+/// The sample the default `WfcGenerator` learns from - square
+/// halls with pillars, joined by narrow corridors
+pub const SAMPLE: &str = "dungeon 1
+map
+####################
+#<.....#############
+#......######.....##
+#..#...######.#.#.##
+#......######.....##
+###.#########.....##
+###.######.....#####
+###.######.#.#.#####
+###........#...#####
+#######.####.#.#####
+#######.####...#####
+###.........########
+###.#####.#.....####
+#.....###.#.#.#.####
+#.#.#.###.#.....####
+#.....###.#.#.#.####
+#.....#####.....####
+###.#######.#.#....>
+###.........#.....##
+####################
+";
+
+///
+/// Generates dungeons in the style of a sample map, by wave
+/// function collapse. The patterns of walls and floor in the sample
+/// are learned in every rotation and reflection, so any arrangement
+/// of them in the sample can appear facing any way. The largest area
+/// left over is kept, and joined to the entrance and exit.
+///
+#[derive(Debug, Clone)]
+pub struct WfcGenerator {
+    /// Each pattern, row by row, true where it is a wall, in order
+    patterns: Vec<Vec<bool>>,
+    /// How many times each pattern appears in the sample
+    weights: Vec<u32>,
+    /// The patterns which can lie beside each
+    /// pattern, in each of the `NEIGHBOURS` directions
+    beside: Vec<[Vec<usize>; 4]>,
+}
+
+impl Default for WfcGenerator {
+    fn default() -> Self {
+        Self::from_text(SAMPLE).unwrap()
+    }
+}
+
+impl WfcGenerator {
+    ///
+    /// Learns the patterns of walls and floor in the `sample`
+    /// dungeon, and how they fit together. Every tile which can't
+    /// be walked over counts as a wall. Samples smaller than a
+    /// pattern have none to learn, and only generate dungeons
+    /// of a corridor from the entrance to each exit.
+    ///
+    pub fn new(sample: &Dungeon) -> Self {
+        let (width, height) = (sample.width() as i32, sample.height() as i32);
+        let mut counts = HashMap::<Vec<bool>, u32>::new();
+        for y in 0..=height - PATTERN_SIZE {
+            for x in 0..=width - PATTERN_SIZE {
+                let pattern = window(|dx, dy| !sample.tile_at((x + dx, y + dy)).is_walkable());
+                for variant in variants(pattern) {
+                    *counts.entry(variant).or_insert(0) += 1;
+                }
+            }
+        }
+        let mut counts: Vec<(Vec<bool>, u32)> = counts.into_iter().collect();
+        counts.sort_unstable();
+        let (patterns, weights): (Vec<Vec<bool>>, Vec<u32>) = counts.into_iter().unzip();
+
+        let beside = patterns
+            .iter()
+            .map(|pattern| {
+                let mut lists: [Vec<usize>; 4] = Default::default();
+                for (list, (dx, dy)) in lists.iter_mut().zip(NEIGHBOURS.iter()) {
+                    *list = (0..patterns.len())
+                        .filter(|other| overlaps(pattern, &patterns[*other], *dx, *dy))
+                        .collect();
+                }
+                lists
+            })
+            .collect();
+
+        Self {
+            patterns,
+            weights,
+            beside,
+        }
+    }
+
+    ///
+    /// Learns the patterns of a sample map written in the
+    /// human-editable file format. Returns `None` if the
+    /// sample can't be read.
+    ///
+    pub fn from_text(text: &str) -> Option<Self> {
+        Dungeon::from_text(text).map(|sample| Self::new(&sample))
+    }
+
+    /// The number of patterns learned from the sample
+    pub fn pattern_count(&self) -> usize {
+        self.patterns.len()
+    }
+
+    ///
+    /// Tests if the 3x3 window of the `dungeon` with its top-left
+    /// corner at `point` matches a pattern learned from the sample.
+    ///
+    pub fn matches_sample(&self, dungeon: &Dungeon, point: (i32, i32)) -> bool {
+        let (x, y) = point;
+        let pattern = window(|dx, dy| !dungeon.tile_at((x + dx, y + dy)).is_walkable());
+        self.patterns.binary_search(&pattern).is_ok()
+    }
+
+    ///
+    /// Collapses a wave of `columns` by `rows` spots. Returns the
+    /// pattern chosen for each spot, row by row, or `None` if the
+    /// wave contradicts itself, leaving a spot no pattern fits.
+    ///
+    fn collapse(&self, rng: &mut StdRng, columns: usize, rows: usize) -> Option<Vec<usize>> {
+        let count = self.patterns.len();
+        let spots = columns * rows;
+        // At first, each pattern at each spot is supported
+        // by every pattern which can lie beside it
+        let supported: Vec<u16> = self
+            .beside
+            .iter()
+            .flat_map(|lists| (0..4).map(move |dir| lists[dir ^ 1].len() as u16))
+            .collect();
+        let mut wave = Wave {
+            columns,
+            rows,
+            count,
+            possible: vec![true; spots * count],
+            left: vec![count as u32; spots],
+            support: supported.repeat(spots),
+            banned: Vec::new(),
+        };
+        // Rule out the patterns nothing can lie beside
+        for (pattern, lists) in self.beside.iter().enumerate() {
+            for dir in (0..4).filter(|dir| lists[*dir].is_empty()) {
+                for spot in 0..spots {
+                    if wave.neighbour(spot, dir).is_some() && wave.possible[spot * count + pattern]
+                    {
+                        wave.ban(spot, pattern);
+                    }
+                }
+            }
+        }
+        if !self.propagate(&mut wave) {
+            return None;
+        }
+
+        // A little noise breaks ties between spots with as many patterns left
+        let noise: Vec<u32> = (0..spots).map(|_| rng.gen()).collect();
+        loop {
+            let mut next = None;
+            let mut fewest = u64::MAX;
+            for (spot, (left, noise)) in wave.left.iter().zip(noise.iter()).enumerate() {
+                let key = (*left as u64) << 32 | *noise as u64;
+                if *left > 1 && key < fewest {
+                    fewest = key;
+                    next = Some(spot);
+                }
+            }
+            let spot = match next {
+                Some(spot) => spot,
+                None => break,
+            };
+
+            let choices: Vec<usize> = (0..count)
+                .filter(|p| wave.possible[spot * count + p])
+                .collect();
+            let pattern = *choices.choose_weighted(rng, |p| self.weights[*p]).unwrap();
+            for other in choices.into_iter().filter(|other| *other != pattern) {
+                wave.ban(spot, other);
+            }
+            if !self.propagate(&mut wave) {
+                return None;
+            }
+        }
+        (0..spots)
+            .map(|spot| (0..count).find(|p| wave.possible[spot * count + p]))
+            .collect()
+    }
+
+    ///
+    /// Takes away the support of each pattern banned from the wave
+    /// from the patterns beside it, banning in turn any pattern left
+    /// without support, until nothing more changes. Returns false if
+    /// a spot is left without any pattern which fits.
+    ///
+    fn propagate(&self, wave: &mut Wave) -> bool {
+        let count = wave.count;
+        while let Some((spot, pattern)) = wave.banned.pop() {
+            for (dir, list) in self.beside[pattern].iter().enumerate() {
+                let neighbour = match wave.neighbour(spot, dir) {
+                    Some(neighbour) => neighbour,
+                    None => continue,
+                };
+                for other in list.iter() {
+                    let support = &mut wave.support[(neighbour * count + other) * 4 + dir];
+                    *support -= 1;
+                    if *support == 0 && wave.possible[neighbour * count + other] {
+                        wave.ban(neighbour, *other);
+                        if wave.left[neighbour] == 0 {
+                            return false;
+                        }
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+/// The patterns which could still lie at each spot being collapsed
+struct Wave {
+    columns: usize,
+    rows: usize,
+    /// The number of patterns learned from the sample
+    count: usize,
+    /// Whether each pattern could still lie at each spot, spot by spot
+    possible: Vec<bool>,
+    /// The number of patterns which could still lie at each spot
+    left: Vec<u32>,
+    /// The number of patterns which could still lie beside each
+    /// pattern at each spot, on the side facing each direction
+    support: Vec<u16>,
+    /// The patterns banned from spots, whose support is yet to be taken away
+    banned: Vec<(usize, usize)>,
+}
+
+impl Wave {
+    /// The spot beside `spot` in the `NEIGHBOURS` direction `dir`, if there is one
+    fn neighbour(&self, spot: usize, dir: usize) -> Option<usize> {
+        let (dx, dy) = NEIGHBOURS[dir];
+        let x = (spot % self.columns) as i32 + dx;
+        let y = (spot / self.columns) as i32 + dy;
+        if x < 0 || y < 0 || x >= self.columns as i32 || y >= self.rows as i32 {
+            None
+        } else {
+            Some(y as usize * self.columns + x as usize)
+        }
+    }
+    /// Rules the `pattern` out of the `spot`
+    fn ban(&mut self, spot: usize, pattern: usize) {
+        self.possible[spot * self.count + pattern] = false;
+        self.left[spot] -= 1;
+        self.banned.push((spot, pattern));
+    }
+}
+
+impl Generator for WfcGenerator {
+    fn generate(&self, config: &DungeonConfig) -> Dungeon {
+        let (seed, mut rng) = seeded_rng(config);
+        let (entrance, exit) = gen_entrance_exit(&mut rng, config);
+        let extra_exits = gen_extra_exits(&mut rng, config, entrance, exit);
+        let exits = exits_of(exit, &extra_exits);
+        let (width, height) = (config.width() as i32, config.height() as i32);
+
+        // Each spot of the wave is the top-left corner of a pattern,
+        // so the patterns overlap to cover every tile
+        let columns = (width - PATTERN_SIZE + 1).max(0) as usize;
+        let rows = (height - PATTERN_SIZE + 1).max(0) as usize;
+        let chosen = (0..ATTEMPTS).find_map(|_| self.collapse(&mut rng, columns, rows));
+
+        // Each tile is drawn from the nearest pattern covering it. The
+        // border is always walled, so the paths stay within the dungeon.
+        // If the wave never collapsed, there is no floor at all.
+        let floor: HashSet<(i32, i32)> = match &chosen {
+            Some(chosen) => (1..height - 1)
+                .flat_map(|y| (1..width - 1).map(move |x| (x, y)))
+                .filter(|(x, y)| {
+                    let spot = ((*x).min(columns as i32 - 1), (*y).min(rows as i32 - 1));
+                    let pattern =
+                        &self.patterns[chosen[spot.1 as usize * columns + spot.0 as usize]];
+                    !pattern[((y - spot.1) * PATTERN_SIZE + x - spot.0) as usize]
+                })
+                .collect(),
+            None => HashSet::new(),
+        };
+
+        // Keep only the largest area, and join the entrance
+        // and exits to it.
+        // If there is no area to join, join the entrance and exit directly.
+        let mut paths = regions(&floor).into_iter().next().unwrap_or_default();
+        if paths.is_empty() {
+            carve_corridor(&mut rng, &mut paths, entrance, exit);
+        }
+        for point in std::iter::once(&entrance).chain(exits.iter()) {
+            let target = nearest(paths.iter(), *point).unwrap();
+            carve_corridor(&mut rng, &mut paths, *point, target);
+        }
+
+        finish(
+            &mut rng,
+            config,
+            Carved {
+                seed,
+                entrance,
+                exit,
+                extra_exits,
+                paths,
+                rooms: Vec::new(),
+                corridors: Vec::new(),
+            },
+        )
+    }
+}
+
+/// Builds a pattern, row by row, from whether
+/// each (x, y) within it `is_wall`
+fn window(is_wall: impl Fn(i32, i32) -> bool) -> Vec<bool> {
+    (0..PATTERN_SIZE)
+        .flat_map(|y| (0..PATTERN_SIZE).map(move |x| (x, y)))
+        .map(|(x, y)| is_wall(x, y))
+        .collect()
+}
+
+/// Every rotation and reflection of the `pattern`
+fn variants(pattern: Vec<bool>) -> Vec<Vec<bool>> {
+    let last = PATTERN_SIZE - 1;
+    let at = |pattern: &[bool], x: i32, y: i32| pattern[(y * PATTERN_SIZE + x) as usize];
+    let mut variants = Vec::with_capacity(8);
+    let mut current = pattern;
+    for _ in 0..4 {
+        variants.push(window(|x, y| at(&current, last - x, y)));
+        let rotated = window(|x, y| at(&current, y, last - x));
+        variants.push(current);
+        current = rotated;
+    }
+    variants
+}
+
+/// Tests if `other`, placed `dx` and `dy` away
+/// from `pattern`, agrees with it where they overlap
+fn overlaps(pattern: &[bool], other: &[bool], dx: i32, dy: i32) -> bool {
+    let range = 0..PATTERN_SIZE;
+    window(|x, y| {
+        let (px, py) = (x + dx, y + dy);
+        range.contains(&px)
+            && range.contains(&py)
+            && other[(y * PATTERN_SIZE + x) as usize] != pattern[(py * PATTERN_SIZE + px) as usize]
+    })
+    .iter()
+    .all(|clash| !clash)
+}
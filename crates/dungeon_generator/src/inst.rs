@@ -3,28 +3,75 @@
 //! Christian Schmid - June 2021
 //! CS510 - Rust Programming
 
-use std::collections::{hash_set, HashSet};
+use std::collections::{hash_set, HashMap, HashSet};
 use std::fmt::Debug;
 
 use rand::prelude::*;
-use simple_serializer::Serialize;
+use simple_serializer::{
+    escape, Bytes, Decode, Deserialize, DeserializeError, DeserializeSegments, Encode, ErrorKind,
+    Segments, Serialize, SerializeSegments, Versioned,
+};
 
-use super::gen::gen_paths;
+use super::{
+    biomes::{Decoration, Region},
+    config::DungeonConfig,
+    generators::walk::WalkGenerator,
+    locks::{is_solvable, Lock},
+    rooms::{Corridor, Room, RoomKind},
+    tiles::Tile,
+    traits::Generator,
+    vaults::PlacedVault,
+};
+
+///
+/// A way out of a `Dungeon` at `pos`, optionally
+/// tagged with the `destination` it leads to.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Exit {
+    pub pos: (i32, i32),
+    pub destination: Option<String>,
+}
 
 ///
 /// A collection of paths which represents
 /// a given dungeon. The dungeon has a width, height,
-/// entrance, and exit.
+/// entrance, and exit, and the seed it was generated from.
+/// Dungeons built from rooms also record each `Room`, and
+/// the `Corridor`s joining them, any locked doors are
+/// recorded with the position of their key, and any vaults
+/// stamped in are recorded with their markers. Every tile is
+/// a floor on the paths, or a wall off them, unless it is
+/// recorded as another `Tile`. A dungeon may have several
+/// exits, the first of which is its main `exit`. Themed
+/// dungeons are split into biome `Region`s, and record the
+/// `Decoration` on each decorated tile. Generated dungeons
+/// record how many loops were carved into their paths.
 ///
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub struct Dungeon {
+    seed: u64,
     width: u32,
     height: u32,
 
     pub entrance: (i32, i32),
     pub exit: (i32, i32),
+    exits: Vec<Exit>,
 
     paths: HashSet<(i32, i32)>,
+    loops: u32,
+
+    rooms: Vec<Room>,
+    corridors: Vec<Corridor>,
+
+    locks: Vec<Lock>,
+
+    vaults: Vec<PlacedVault>,
+
+    tiles: HashMap<(i32, i32), Tile>,
+
+    regions: Vec<Region>,
+    decorations: HashMap<(i32, i32), Decoration>,
 }
 
 impl Dungeon {
@@ -34,46 +81,207 @@ impl Dungeon {
     /// paths constrained to size.
     ///
     pub fn new(width: u32, height: u32) -> Self {
-        let mut rng = thread_rng();
-
-        // Generate an entrance and exit, which will start on opposite corners
-        // rand 0 to 1 for (x, y) position on entrance, multiplied by (width, height),
-        // and calculate the opposite corner for exit
-        let entrance = (
-            (rng.next_u32() % width) as i32,
-            if rng.next_u32() % 2 == 0 {
-                0
-            } else {
-                (height - 1) as i32
-            },
-        );
-        let exit = (
-            (rng.next_u32() % width) as i32,
-            if entrance.1 == 0 {
-                (height - 1) as i32
-            } else {
-                0
-            },
-        );
-
-        let paths = gen_paths(rng.gen::<f64>(), width, height, entrance, exit);
+        Self::from_seed(thread_rng().gen(), width, height)
+    }
 
+    ///
+    /// Generates a new `Dungeon` with the specified
+    /// `width` and `height`, drawing every random choice
+    /// from a generator seeded with `seed`. The same `seed`
+    /// and size always produce the same `Dungeon`.
+    ///
+    pub fn from_seed(seed: u64, width: u32, height: u32) -> Self {
+        Self::with_config(&DungeonConfig::new(width, height).with_seed(seed))
+    }
+
+    ///
+    /// Generates a new `Dungeon` using the size, placement
+    /// rules and generation parameters in `config`. If `config`
+    /// has no seed, a random one is chosen. Uses the random walk
+    /// `Generator`; see `generators` for the other styles.
+    ///
+    pub fn with_config(config: &DungeonConfig) -> Self {
+        WalkGenerator.generate(config)
+    }
+
+    ///
+    /// Creates a `Dungeon` directly from its `paths`, `entrance`
+    /// and `exit`, bounded by `width` and `height`. Used by
+    /// `Generator`s to build their result, tagged with the
+    /// `seed` they generated it from.
+    ///
+    pub fn from_paths(
+        seed: u64,
+        width: u32,
+        height: u32,
+        entrance: (i32, i32),
+        exit: (i32, i32),
+        paths: HashSet<(i32, i32)>,
+    ) -> Self {
         Dungeon {
+            seed,
             width,
             height,
             entrance,
             exit,
+            exits: vec![Exit {
+                pos: exit,
+                destination: None,
+            }],
             paths,
+            loops: 0,
+            rooms: Vec::new(),
+            corridors: Vec::new(),
+            locks: Vec::new(),
+            vaults: Vec::new(),
+            tiles: HashMap::new(),
+            regions: Vec::new(),
+            decorations: HashMap::new(),
         }
     }
+    ///
+    /// Records every exit of the `Dungeon`. The first of the
+    /// `exits` becomes the main exit. If there are none, the
+    /// main exit is kept, without a destination.
+    ///
+    pub fn with_exits(mut self, exits: Vec<Exit>) -> Self {
+        if let Some(first) = exits.first() {
+            self.exit = first.pos;
+            self.exits = exits;
+        }
+        self
+    }
+    /// Records the number of `loops` carved into the `Dungeon`'s paths
+    pub fn with_loops(mut self, loops: u32) -> Self {
+        self.loops = loops;
+        self
+    }
+    /// Records the named `rooms` of the `Dungeon`, and the
+    /// `corridors` joining them.
+    pub fn with_rooms(mut self, rooms: Vec<Room>, corridors: Vec<Corridor>) -> Self {
+        self.rooms = rooms;
+        self.corridors = corridors;
+        self
+    }
+    /// Records the locked doors of the `Dungeon`, and their
+    /// keys, marking the tile of each door as a `Tile::Door`
+    pub fn with_locks(mut self, locks: Vec<Lock>) -> Self {
+        self.tiles
+            .extend(locks.iter().map(|lock| (lock.door, Tile::Door)));
+        self.locks = locks;
+        self
+    }
+    /// Records the `tiles` of the `Dungeon` other than
+    /// plain floors on the paths, and walls off them
+    pub fn with_tiles(mut self, tiles: HashMap<(i32, i32), Tile>) -> Self {
+        self.tiles.extend(tiles);
+        self
+    }
+    /// Records the vaults stamped into the `Dungeon`
+    pub fn with_vaults(mut self, vaults: Vec<PlacedVault>) -> Self {
+        self.vaults = vaults;
+        self
+    }
+    /// Records the biome `regions` the `Dungeon` is split into
+    pub fn with_regions(mut self, regions: Vec<Region>) -> Self {
+        self.regions = regions;
+        self
+    }
+    /// Records the `decorations` of the `Dungeon`, by position
+    pub fn with_decorations(mut self, decorations: HashMap<(i32, i32), Decoration>) -> Self {
+        self.decorations.extend(decorations);
+        self
+    }
+    /// The seed the dungeon was generated from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
     /// An iterator over the paths in the dungeon.
-    pub fn paths(&self) -> hash_set::Iter<(i32, i32)> {
+    pub fn paths(&self) -> hash_set::Iter<'_, (i32, i32)> {
         self.paths.iter()
     }
     /// A reference to the paths HashSet
     pub fn paths_ref(&self) -> &HashSet<(i32, i32)> {
         &self.paths
     }
+    /// The number of loops carved into the dungeon's paths
+    pub fn loops(&self) -> u32 {
+        self.loops
+    }
+    /// Every exit of the dungeon, starting with the main exit
+    pub fn exits(&self) -> &[Exit] {
+        &self.exits
+    }
+    /// Tests if there is an exit at `point`
+    pub fn is_exit(&self, point: (i32, i32)) -> bool {
+        self.exits.iter().any(|exit| exit.pos == point)
+    }
+    /// The named rooms of the dungeon. Empty if the
+    /// dungeon wasn't generated from rooms.
+    pub fn rooms(&self) -> &[Room] {
+        &self.rooms
+    }
+    /// The first room of the given `kind`, if there is one
+    pub fn room_of_kind(&self, kind: RoomKind) -> Option<&Room> {
+        self.rooms.iter().find(|r| r.kind == kind)
+    }
+    /// The corridors joining the dungeon's rooms, indexing into `rooms`
+    pub fn corridors(&self) -> &[Corridor] {
+        &self.corridors
+    }
+    /// The locked doors of the dungeon, in the order
+    /// they are met on the way to the exit
+    pub fn locks(&self) -> &[Lock] {
+        &self.locks
+    }
+    /// The vaults stamped into the dungeon
+    pub fn vaults(&self) -> &[PlacedVault] {
+        &self.vaults
+    }
+    /// The biome regions of the dungeon. Empty if the dungeon isn't themed.
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+    /// Every decorated tile of the dungeon, and its `Decoration`
+    pub fn decorations(&self) -> &HashMap<(i32, i32), Decoration> {
+        &self.decorations
+    }
+    /// The `Tile` at `point`. Points outside the dungeon are walls.
+    pub fn tile_at(&self, point: (i32, i32)) -> Tile {
+        match self.tiles.get(&point) {
+            Some(tile) => *tile,
+            None if self.paths.contains(&point) => Tile::Floor,
+            None => Tile::Wall,
+        }
+    }
+    /// Every tile of the dungeon other than plain
+    /// floors on the paths, and walls off them
+    pub fn tiles(&self) -> &HashMap<(i32, i32), Tile> {
+        &self.tiles
+    }
+    /// Tests if the exit can be reached from the entrance, picking
+    /// up the key to each locked door before passing through it
+    pub fn is_solvable(&self) -> bool {
+        is_solvable(&self.paths, self.entrance, self.exit, &self.locks)
+    }
+    /// Sets the `Tile` at `point`, adding it to the paths if
+    /// it can be walked over, and taking it out if it can't
+    pub(crate) fn put_tile(&mut self, point: (i32, i32), tile: Tile) {
+        if tile.is_walkable() {
+            self.paths.insert(point);
+        } else {
+            self.paths.remove(&point);
+        }
+        if matches!(tile, Tile::Floor | Tile::Wall) {
+            self.tiles.remove(&point);
+        } else {
+            self.tiles.insert(point, tile);
+        }
+    }
+    /// Takes away any decoration at `point`
+    pub(crate) fn clear_decoration(&mut self, point: (i32, i32)) {
+        self.decorations.remove(&point);
+    }
     /// The horizontal bounds of the dungeon
     pub fn width(&self) -> u32 {
         self.width
@@ -84,37 +292,251 @@ impl Dungeon {
     }
 }
 
+///
+/// The fields of a `Dungeon`, in the order they are serialized.
+/// Paths, tiles and decorations are sorted, so equal `Dungeon`s
+/// serialize identically. Dungeons from earlier servers end
+/// after the exit, and later ones may end after any field
+/// which follows it, leaving the rest as their defaults.
+///
+#[derive(Serialize, Deserialize, Encode, Decode, Versioned)]
+struct Wire {
+    paths: Vec<(i32, i32)>,
+    entrance: (i32, i32),
+    exit: (i32, i32),
+    #[serialize(since = 2)]
+    seed: u64,
+    #[serialize(since = 2)]
+    rooms: Vec<Room>,
+    #[serialize(since = 2)]
+    corridors: Vec<Corridor>,
+    #[serialize(since = 2)]
+    locks: Vec<Lock>,
+    #[serialize(since = 2)]
+    size: (u32, u32),
+    #[serialize(since = 2)]
+    vaults: Vec<PlacedVault>,
+    #[serialize(since = 2)]
+    tiles: Vec<((i32, i32), Tile)>,
+    #[serialize(since = 2)]
+    exits: Vec<Exit>,
+    #[serialize(since = 2)]
+    regions: Vec<Region>,
+    #[serialize(since = 2)]
+    decorations: Vec<((i32, i32), Decoration)>,
+    #[serialize(since = 2)]
+    loops: u32,
+}
+
+impl Dungeon {
+    /// The fields of the `Dungeon` as they are serialized
+    fn wire(&self) -> Wire {
+        let mut paths: Vec<(i32, i32)> = self.paths().cloned().collect();
+        paths.sort_unstable();
+        let mut tiles: Vec<((i32, i32), Tile)> = self.tiles.iter().map(|(p, t)| (*p, *t)).collect();
+        tiles.sort_unstable_by_key(|(point, _)| *point);
+        let mut decorations: Vec<((i32, i32), Decoration)> =
+            self.decorations.iter().map(|(p, d)| (*p, *d)).collect();
+        decorations.sort_unstable_by_key(|(point, _)| *point);
+
+        Wire {
+            paths,
+            entrance: self.entrance,
+            exit: self.exit,
+            seed: self.seed,
+            rooms: self.rooms.clone(),
+            corridors: self.corridors.clone(),
+            locks: self.locks.clone(),
+            size: (self.width, self.height),
+            vaults: self.vaults.clone(),
+            tiles,
+            exits: self.exits.clone(),
+            regions: self.regions.clone(),
+            decorations,
+            loops: self.loops,
+        }
+    }
+
+    /// Builds the `Dungeon` its fields were read back into, or
+    /// `None` if its main exit isn't the first of its exits.
+    fn from_wire(wire: Wire) -> Option<Dungeon> {
+        if wire.exits.first().is_some_and(|e| e.pos != wire.exit) {
+            return None;
+        }
+
+        // Dungeons from earlier servers have no size
+        let (width, height) = match wire.size {
+            (0, 0) => {
+                let ends = [wire.entrance, wire.exit];
+                let points = wire.paths.iter().chain(ends.iter());
+                let width = points.clone().map(|p| p.0 + 1).max().unwrap_or(0);
+                let height = points.map(|p| p.1 + 1).max().unwrap_or(0);
+                (width as u32, height as u32)
+            }
+            size => size,
+        };
+
+        Some(
+            Dungeon::from_paths(
+                wire.seed,
+                width,
+                height,
+                wire.entrance,
+                wire.exit,
+                wire.paths.into_iter().collect(),
+            )
+            .with_loops(wire.loops)
+            .with_rooms(wire.rooms, wire.corridors)
+            .with_locks(wire.locks)
+            .with_vaults(wire.vaults)
+            .with_tiles(wire.tiles.into_iter().collect())
+            .with_exits(wire.exits)
+            .with_regions(wire.regions)
+            .with_decorations(wire.decorations.into_iter().collect()),
+        )
+    }
+}
+
+///
+/// Dungeons are written for version 1 of the server's protocol
+/// as the first servers sent them - only their paths, entrance
+/// and exit - and in full from version 2.
+///
+impl Versioned for Dungeon {
+    fn since(&self) -> u32 {
+        1
+    }
+    fn serialize_segments_for(&self, version: u32, segs: &mut Vec<String>) {
+        self.wire().serialize_segments_for(version, segs);
+    }
+    fn encode_for(&self, version: u32, out: &mut Vec<u8>) {
+        self.wire().encode_for(version, out);
+    }
+}
+
 impl Serialize for Dungeon {
     type SerializeTo = String;
     /// For the purpose of the Dungeon Crawler project,
     /// Dungeon will Serialize to a String with the appropriate
-    /// delimeter between each value ("::")
+    /// delimeter between each value ("::"). The number of paths
+    /// and each path are followed by the entrance, the exit, and
+    /// the seed, then the rooms (name, kind, x, y, w, h), corridors
+    /// (from, to, tiles), locks (door x, door y, key x, key y),
+    /// the width and height, the vaults (name, x, y, w, h, spawns,
+    /// treasure), the tiles other than plain floors and walls
+    /// (x, y, tile), every exit (x, y, destination), starting with
+    /// the main exit, the biome regions (x, y, biome) and decorations
+    /// (x, y, decoration), and lastly the number of loops carved into
+    /// the paths. Exits without a destination have an empty one. Room
+    /// and vault names and exit destinations are escaped, so they
+    /// can hold anything.
     fn serialize(&self) -> String {
-        let mut path_str = self.paths().len().to_string();
+        self.wire().serialize()
+    }
+}
 
-        for path in self.paths() {
-            path_str.push_str("::");
-            path_str.push_str(&path.0.to_string());
-            path_str.push_str("::");
-            path_str.push_str(&path.1.to_string());
+///
+/// Dungeons nested in other messages are written in full, or
+/// for the message's version when it is `versioned`, and are
+/// read back in the same way as `Deserialize` reads them.
+///
+impl SerializeSegments for Dungeon {
+    fn serialize_segments(&self, segs: &mut Vec<String>) {
+        self.wire().serialize_segments(segs);
+    }
+}
+
+impl DeserializeSegments for Dungeon {
+    fn deserialize_segments(segs: &mut Segments) -> Result<Self, DeserializeError> {
+        Dungeon::from_wire(Wire::deserialize_segments(segs)?).ok_or_else(mismatched_exit)
+    }
+}
+
+impl Encode for Dungeon {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.wire().encode(out);
+    }
+}
+
+impl Decode for Dungeon {
+    fn decode(bytes: &mut Bytes) -> Result<Self, DeserializeError> {
+        Dungeon::from_wire(Wire::decode(bytes)?).ok_or_else(mismatched_exit)
+    }
+}
+
+/// The error for dungeons whose exits don't start with their main exit
+fn mismatched_exit() -> DeserializeError {
+    DeserializeError::new(ErrorKind::Malformed("main exit".to_string())).within("exits")
+}
+
+impl Deserialize for Dungeon {
+    type DeserializeTo = Option<Dungeon>;
+    /// Reads a `Dungeon` back from the String it serialized to,
+    /// returning `None` if the String is malformed. Strings which
+    /// end after the exit (as sent by earlier servers) are read with
+    /// a seed of 0 and no rooms or locks, and sized to fit their paths.
+    /// Strings which end after any later field have the defaults of
+    /// those which follow it - no vaults, other tiles, biome regions
+    /// or decorations, no loops recorded, and only their main exit.
+    fn deserialize(from: &str) -> Option<Dungeon> {
+        let mut segs = Segments::new(from);
+        let wire = Wire::deserialize_segments(&mut segs).ok()?;
+        // Nothing may follow the last field
+        if !segs.is_empty() {
+            return None;
         }
+        Dungeon::from_wire(wire)
+    }
+}
 
-        path_str.push_str("::");
-        path_str.push_str(&self.entrance.0.to_string());
-        path_str.push_str("::");
-        path_str.push_str(&self.entrance.1.to_string());
+///
+/// Exits are written as their position and destination, in
+/// either format, which is empty for exits without one.
+///
+impl SerializeSegments for Exit {
+    fn serialize_segments(&self, segs: &mut Vec<String>) {
+        self.pos.serialize_segments(segs);
+        segs.push(escape(self.destination.as_deref().unwrap_or("")));
+    }
+}
 
-        path_str.push_str("::");
-        path_str.push_str(&self.exit.0.to_string());
-        path_str.push_str("::");
-        path_str.push_str(&self.exit.1.to_string());
+impl Encode for Exit {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.pos.encode(out);
+        self.destination
+            .as_deref()
+            .unwrap_or("")
+            .to_string()
+            .encode(out);
+    }
+}
+
+impl DeserializeSegments for Exit {
+    fn deserialize_segments(segs: &mut Segments) -> Result<Self, DeserializeError> {
+        let pos = <(i32, i32)>::deserialize_segments(segs).map_err(|e| e.within("pos"))?;
+        let destination =
+            String::deserialize_segments(segs).map_err(|e| e.within("destination"))?;
+        Ok(Exit {
+            pos,
+            destination: Some(destination).filter(|d| !d.is_empty()),
+        })
+    }
+}
 
-        path_str
+impl Decode for Exit {
+    fn decode(bytes: &mut Bytes) -> Result<Self, DeserializeError> {
+        let pos = <(i32, i32)>::decode(bytes).map_err(|e| e.within("pos"))?;
+        let destination = String::decode(bytes).map_err(|e| e.within("destination"))?;
+        Ok(Exit {
+            pos,
+            destination: Some(destination).filter(|d| !d.is_empty()),
+        })
     }
 }
 
 // A simple text generation of the Dungeon, displaying its
-// walls, paths, entrance, and exit
+// walls, paths, entrance, exit, locked doors and keys,
+// the monster spawns and treasure of vaults, and other tiles
 impl Debug for Dungeon {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Dungeon:\n{}", {
@@ -123,8 +545,24 @@ impl Debug for Dungeon {
                 for col in 0..self.width as i32 {
                     map.push_str(if self.entrance == (col, row) {
                         "O "
-                    } else if self.exit == (col, row) {
+                    } else if self.is_exit((col, row)) {
                         "X "
+                    } else if self.locks.iter().any(|l| l.door == (col, row)) {
+                        "D "
+                    } else if self.locks.iter().any(|l| l.key == (col, row)) {
+                        "k "
+                    } else if self.vaults.iter().any(|v| v.spawns.contains(&(col, row))) {
+                        "M "
+                    } else if self.vaults.iter().any(|v| v.treasure.contains(&(col, row))) {
+                        "$ "
+                    } else if let Some(tile) = self.tiles.get(&(col, row)) {
+                        match tile {
+                            Tile::Water => "~ ",
+                            Tile::Pit => "_ ",
+                            Tile::Trap => "^ ",
+                            Tile::Rubble => "% ",
+                            _ => "  ",
+                        }
                     } else if self.paths.contains(&(col, row)) {
                         "  "
                     } else {
@@ -1,2 +1,19 @@
+pub mod analysis;
+pub mod biomes;
+pub mod config;
+pub mod difficulty;
+pub mod file;
 mod gen;
+pub mod generators;
 pub mod inst;
+pub mod locks;
+pub mod loops;
+pub mod mutate;
+pub mod render;
+pub mod rooms;
+pub mod sight;
+pub mod stats;
+pub mod tiles;
+pub mod tower;
+pub mod traits;
+pub mod vaults;
@@ -0,0 +1,204 @@
+//! Locked doors and keys of a Dungeon
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use rand::{prelude::*, rngs::StdRng};
+use simple_serializer::{Decode, Deserialize, Encode, Serialize};
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    analysis::{chokepoints_between, shortest_route},
+    gen::NEIGHBOURS,
+};
+
+///
+/// A locked door blocking the path at `door`, which
+/// is opened by picking up the key lying at `key`.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Encode, Decode)]
+pub struct Lock {
+    pub door: (i32, i32),
+    pub key: (i32, i32),
+}
+
+///
+/// Places up to `count` locked doors along the way from the
+/// `entrance` to the `exit`, each on a tile which every route
+/// to the exit must pass through. The key to each door is placed
+/// in the region reachable before it, once the doors before it
+/// are opened, so the locks can always be solved in order.
+///
+/// Fewer doors are placed if the `paths` don't have enough
+/// tiles to block, or no room for their keys.
+///
+pub fn place_locks(
+    rng: &mut StdRng,
+    paths: &HashSet<(i32, i32)>,
+    entrance: (i32, i32),
+    exit: (i32, i32),
+    count: u32,
+) -> Vec<Lock> {
+    if count == 0 {
+        return Vec::new();
+    }
+    let route = match shortest_route(paths, entrance, exit) {
+        Some(route) if route.len() > 2 => route,
+        _ => return Vec::new(),
+    };
+
+    // The tiles along the route which cut the exit off when blocked
+    let cuts = chokepoints_between(paths, entrance, exit);
+    let chokepoints: Vec<(i32, i32)> = route
+        .iter()
+        .filter(|tile| cuts.contains(tile))
+        .cloned()
+        .collect();
+
+    // Choose the doors, ordered from the entrance to the exit
+    let mut doors: Vec<(i32, i32)> = chokepoints
+        .choose_multiple(rng, count as usize)
+        .cloned()
+        .collect();
+    doors.sort_by_key(|door| route.iter().position(|tile| tile == door));
+
+    let mut locks = Vec::<Lock>::new();
+    for (i, door) in doors.iter().enumerate() {
+        // Every tile reachable with this door, and the doors after it, closed
+        let blocked: HashSet<(i32, i32)> = doors[i..].iter().cloned().collect();
+        let mut spots: Vec<(i32, i32)> = reachable(paths, entrance, &blocked)
+            .into_iter()
+            .filter(|spot| *spot != entrance && !doors.contains(spot))
+            .filter(|spot| !locks.iter().any(|lock| lock.key == *spot))
+            .collect();
+        spots.sort_unstable();
+
+        if let Some(key) = spots.choose(rng) {
+            locks.push(Lock {
+                door: *door,
+                key: *key,
+            });
+        }
+    }
+    locks
+}
+
+///
+/// Tests if the `exit` can be reached from the `entrance` along
+/// the `paths`, when every door in `locks` starts closed and is
+/// only opened once its key has been reached.
+///
+pub fn is_solvable(
+    paths: &HashSet<(i32, i32)>,
+    entrance: (i32, i32),
+    exit: (i32, i32),
+    locks: &[Lock],
+) -> bool {
+    let mut closed: HashSet<(i32, i32)> = locks.iter().map(|lock| lock.door).collect();
+    loop {
+        let reached = reachable(paths, entrance, &closed);
+        if reached.contains(&exit) {
+            return true;
+        }
+
+        // Pick up every key reached so far, opening their doors.
+        // If no new doors can be opened, the exit is out of reach.
+        let opened: Vec<(i32, i32)> = locks
+            .iter()
+            .filter(|lock| closed.contains(&lock.door) && reached.contains(&lock.key))
+            .map(|lock| lock.door)
+            .collect();
+        if opened.is_empty() {
+            return false;
+        }
+        for door in opened {
+            closed.remove(&door);
+        }
+    }
+}
+
+/// Finds every tile of the `paths` reachable from `from`,
+/// without passing through any `blocked` tiles.
+fn reachable(
+    paths: &HashSet<(i32, i32)>,
+    from: (i32, i32),
+    blocked: &HashSet<(i32, i32)>,
+) -> HashSet<(i32, i32)> {
+    let mut reached = HashSet::new();
+    if !paths.contains(&from) || blocked.contains(&from) {
+        return reached;
+    }
+    let mut queue = VecDeque::new();
+    reached.insert(from);
+    queue.push_back(from);
+
+    while let Some(cur) = queue.pop_front() {
+        for (dx, dy) in NEIGHBOURS.iter() {
+            let next = (cur.0 + dx, cur.1 + dy);
+            if paths.contains(&next) && !blocked.contains(&next) && reached.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    reached
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::DungeonConfig,
+        generators::{bsp::BspGenerator, caves::CaveGenerator, walk::WalkGenerator},
+        traits::Generator,
+    };
+
+    /// Tests that locked doors block every route to the exit, that
+    /// each one's key can be reached before it, and that the dungeon
+    /// can be solved by opening the doors in order.
+    #[test]
+    fn test_locks() {
+        let generators: [&dyn Generator; 3] = [
+            &WalkGenerator,
+            &BspGenerator::default(),
+            &CaveGenerator::default(),
+        ];
+
+        let mut placed = 0;
+        for generator in generators.iter() {
+            for seed in 0..10 {
+                let config = DungeonConfig::new(50, 50).with_seed(seed).with_locks(3);
+                let dun = generator.generate(&config);
+
+                assert!(dun.locks().len() <= 3);
+                assert!(dun.is_solvable());
+                placed += dun.locks().len();
+
+                for (i, lock) in dun.locks().iter().enumerate() {
+                    assert!(dun.paths_ref().contains(&lock.door));
+                    assert!(dun.paths_ref().contains(&lock.key));
+
+                    // Without this door's key, the exit can't be reached
+                    let mut missing = dun.locks().to_vec();
+                    missing[i].key = lock.door;
+                    assert!(!is_solvable(
+                        dun.paths_ref(),
+                        dun.entrance,
+                        dun.exit,
+                        &missing
+                    ));
+                }
+            }
+        }
+        assert!(placed > 0);
+
+        // Without locks, the dungeon is unchanged
+        let config = DungeonConfig::new(40, 40).with_seed(3);
+        assert!(WalkGenerator.generate(&config).locks().is_empty());
+        assert!(
+            WalkGenerator.generate(&config).paths_ref()
+                == WalkGenerator
+                    .generate(&config.clone().with_locks(0))
+                    .paths_ref()
+        );
+    }
+}
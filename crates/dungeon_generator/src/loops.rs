@@ -0,0 +1,167 @@
+//! Loops carved into generated Dungeons
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use rand::{prelude::*, rngs::StdRng};
+use std::collections::{HashSet, VecDeque};
+
+use crate::gen::NEIGHBOURS;
+
+/// The most wall tiles a loop will carve through
+const MAX_GAP: i32 = 3;
+
+/// The shortest distance along the paths that two tiles
+/// must be apart for a loop to be carved between them
+const MIN_DETOUR: u32 = 12;
+
+///
+/// Carves up to `count` loops into the `paths`. Each loop is a
+/// short, straight tunnel through the walls between two tiles
+/// which lie close together, but are far apart along the paths,
+/// giving a second way around. Returns the number of loops carved,
+/// which is fewer than `count` if there are no more places to carve.
+///
+pub fn add_loops(rng: &mut StdRng, paths: &mut HashSet<(i32, i32)>, count: u32) -> u32 {
+    if count == 0 {
+        return 0;
+    }
+    let mut tunnels = tunnels(paths);
+    tunnels.shuffle(rng);
+
+    let mut added = 0;
+    for (from, to, gap) in tunnels {
+        if added == count {
+            break;
+        }
+        // Skip tunnels crossed by an earlier loop, or which
+        // wouldn't save enough of a walk to be worth a loop
+        if gap.iter().any(|tile| paths.contains(tile)) || is_within(paths, from, to, MIN_DETOUR) {
+            continue;
+        }
+        paths.extend(gap);
+        added += 1;
+    }
+    added
+}
+
+/// Finds every straight tunnel of up to `MAX_GAP` walls between
+/// two tiles of the `paths`, with walls along both of its sides.
+/// Returns the tiles at each end of the tunnel, and the walls
+/// to be carved, in a deterministic order.
+#[allow(clippy::type_complexity)]
+fn tunnels(paths: &HashSet<(i32, i32)>) -> Vec<((i32, i32), (i32, i32), Vec<(i32, i32)>)> {
+    let mut sorted: Vec<&(i32, i32)> = paths.iter().collect();
+    sorted.sort_unstable();
+
+    let mut tunnels = Vec::new();
+    for from in sorted {
+        // Only look right and down, so each tunnel is found once
+        for (dx, dy) in [(1, 0), (0, 1)].iter() {
+            let mut gap = Vec::new();
+            for step in 1..=MAX_GAP + 1 {
+                let tile = (from.0 + dx * step, from.1 + dy * step);
+                if paths.contains(&tile) {
+                    if !gap.is_empty() {
+                        tunnels.push((*from, tile, gap));
+                    }
+                    break;
+                }
+                // The walls on either side of the tunnel must be
+                // left standing, so the tunnel doesn't widen a room
+                let sides = [(tile.0 + dy, tile.1 + dx), (tile.0 - dy, tile.1 - dx)];
+                if sides.iter().any(|side| paths.contains(side)) {
+                    break;
+                }
+                gap.push(tile);
+            }
+        }
+    }
+    tunnels
+}
+
+/// Tests if `to` can be reached from `from` along the
+/// `paths` in fewer than `limit` steps.
+fn is_within(paths: &HashSet<(i32, i32)>, from: (i32, i32), to: (i32, i32), limit: u32) -> bool {
+    let mut visited = HashSet::new();
+    let mut next = VecDeque::new();
+    visited.insert(from);
+    next.push_back((from, 0));
+
+    while let Some((point, distance)) = next.pop_front() {
+        if point == to {
+            return true;
+        }
+        if distance + 1 >= limit {
+            continue;
+        }
+        for dir in NEIGHBOURS.iter() {
+            let adj = (point.0 + dir.0, point.1 + dir.1);
+            if paths.contains(&adj) && visited.insert(adj) {
+                next.push_back((adj, distance + 1));
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use simple_serializer::{Deserialize, Serialize};
+
+    use crate::{
+        config::DungeonConfig,
+        generators::{bsp::BspGenerator, caves::CaveGenerator, walk::WalkGenerator},
+        inst::Dungeon,
+        traits::Generator,
+    };
+
+    /// Tests that loops carved into dungeons add cycles to their
+    /// paths, keep them traversable, are counted in their stats, and
+    /// leave dungeons generated without loops unchanged.
+    #[test]
+    fn test_loops() {
+        let generators: [&dyn Generator; 3] = [
+            &WalkGenerator,
+            &BspGenerator::default(),
+            &CaveGenerator::default(),
+        ];
+
+        let (mut added, mut carved) = (0, 0);
+        for generator in generators.iter() {
+            for seed in 0..10 {
+                let config = DungeonConfig::new(50, 50).with_seed(seed);
+                let plain = generator.generate(&config);
+                let looped = generator.generate(&config.clone().with_loops(4));
+
+                assert!(looped.is_connected());
+                assert!(plain.paths().all(|p| looped.paths_ref().contains(p)));
+                assert!(looped.stats().cycles >= plain.stats().cycles);
+                assert_eq!(looped.stats().regions, 1);
+                assert_eq!(plain.stats().loops, 0);
+                assert!(looped.stats().loops <= 4);
+                added += looped.stats().cycles - plain.stats().cycles;
+                carved += looped.stats().loops;
+
+                // The count survives serializing and saving
+                assert!(Dungeon::deserialize(&looped.serialize()) == Some(looped.clone()));
+                assert!(Dungeon::from_text(&looped.to_text()) == Some(looped.clone()));
+            }
+        }
+        assert!(added > 0);
+        // Most dungeons have room for every loop asked for
+        assert!(carved > 3 * 10 * 4 / 2);
+
+        // Rooms and corridors always leave room for a few loops
+        for seed in 0..5 {
+            let config = DungeonConfig::new(60, 60).with_seed(seed).with_loops(3);
+            assert_eq!(BspGenerator::default().generate(&config).stats().loops, 3);
+        }
+
+        // A straight corridor has no cycles, and a ring has one
+        let line = Dungeon::from_text("dungeon 1\nmap\n<..>\n").unwrap();
+        assert_eq!(line.stats().cycles, 0);
+        let ring = Dungeon::from_text("dungeon 1\nmap\n<..\n.#.\n..>\n").unwrap();
+        assert_eq!(ring.stats().cycles, 1);
+    }
+}
@@ -0,0 +1,249 @@
+//! Changing the tiles of a Dungeon after it is generated
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use crate::{analysis::distance_field, gen::in_bounds, inst::Dungeon, rooms::Rect, tiles::Tile};
+
+///
+/// Whether a change to a `Dungeon`'s tiles must keep
+/// its exits within reach of its entrance.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Refuse any change which cuts an exit off from the entrance
+    Keep,
+    /// Make the change even if it cuts an exit off
+    Override,
+}
+
+impl Dungeon {
+    ///
+    /// Tests if every exit can be reached from the entrance, and
+    /// the main exit can be reached picking up the key to each
+    /// locked door before passing through it.
+    ///
+    pub fn is_connected(&self) -> bool {
+        let reached = distance_field(self.paths_ref(), self.entrance);
+        self.is_solvable() && self.exits().iter().all(|e| reached.contains_key(&e.pos))
+    }
+
+    ///
+    /// Sets the tile at each point of `changes`, in order. Returns the
+    /// changes which replaced a different tile, or `None` if nothing
+    /// was changed - because a change lies outside the dungeon or on
+    /// the door or key of a lock, or, if `connectivity` is `Keep`,
+    /// because the changes would cut an exit off from the entrance.
+    ///
+    pub fn set_tiles(
+        &mut self,
+        changes: &[((i32, i32), Tile)],
+        connectivity: Connectivity,
+    ) -> Option<Vec<((i32, i32), Tile)>> {
+        let on_lock = |p: &(i32, i32)| self.locks().iter().any(|l| l.door == *p || l.key == *p);
+        if changes
+            .iter()
+            .any(|(p, _)| !in_bounds(*p, self.width(), self.height()) || on_lock(p))
+        {
+            return None;
+        }
+
+        // Keep each replaced tile, to put back if the changes are refused
+        let mut changed = Vec::new();
+        let mut replaced = Vec::new();
+        for (point, tile) in changes.iter() {
+            let old = self.tile_at(*point);
+            if old != *tile {
+                replaced.push((*point, old));
+                changed.push((*point, *tile));
+                self.put_tile(*point, *tile);
+            }
+        }
+
+        if connectivity == Connectivity::Keep && !self.is_connected() {
+            for (point, old) in replaced.into_iter().rev() {
+                self.put_tile(point, old);
+            }
+            return None;
+        }
+        // Decorations go with the tiles they were on
+        for (point, _) in changed.iter() {
+            self.clear_decoration(*point);
+        }
+        Some(changed)
+    }
+
+    ///
+    /// Opens the wall at `point` into floor. Returns `None` if
+    /// there is no wall there, or it lies on the dungeon's edge.
+    ///
+    pub fn open_wall(&mut self, point: (i32, i32)) -> Option<Vec<((i32, i32), Tile)>> {
+        let inner = point.0 > 0
+            && point.1 > 0
+            && point.0 < self.width() as i32 - 1
+            && point.1 < self.height() as i32 - 1;
+        if !inner || self.tile_at(point) != Tile::Wall {
+            return None;
+        }
+        self.set_tiles(&[(point, Tile::Floor)], Connectivity::Keep)
+    }
+
+    ///
+    /// Collapses each of the `points`, such as the tiles of a corridor,
+    /// into solid wall. Returns `None` if nothing was changed, as
+    /// for `Dungeon::set_tiles`.
+    ///
+    pub fn collapse(
+        &mut self,
+        points: &[(i32, i32)],
+        connectivity: Connectivity,
+    ) -> Option<Vec<((i32, i32), Tile)>> {
+        let changes: Vec<((i32, i32), Tile)> = points.iter().map(|p| (*p, Tile::Wall)).collect();
+        self.set_tiles(&changes, connectivity)
+    }
+
+    ///
+    /// Floods the tiles within `area`, such as the bounds of a room,
+    /// with water. Only tiles which can be walked over are flooded,
+    /// leaving doors, and the doors and keys of locks, as they are.
+    /// Returns the tiles which were flooded.
+    ///
+    pub fn flood(&mut self, area: Rect) -> Vec<((i32, i32), Tile)> {
+        let changes: Vec<((i32, i32), Tile)> = area
+            .points()
+            .filter(|p| in_bounds(*p, self.width(), self.height()))
+            .filter(|p| {
+                let tile = self.tile_at(*p);
+                tile.is_walkable() && tile != Tile::Door
+            })
+            .filter(|p| !self.locks().iter().any(|l| l.door == *p || l.key == *p))
+            .map(|p| (p, Tile::Water))
+            .collect();
+        // Water can be walked through, so never cuts an exit off
+        self.set_tiles(&changes, Connectivity::Override)
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use simple_serializer::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{
+        analysis::chokepoints_between,
+        config::DungeonConfig,
+        generators::{bsp::BspGenerator, caves::CaveGenerator, walk::WalkGenerator},
+        traits::Generator,
+    };
+
+    /// Changes the tiles of dungeons from every `Generator`, and tests
+    /// that walls are opened, passages collapsed and rooms flooded,
+    /// that changes cutting off the exit are refused unless overridden,
+    /// and that changed dungeons survive serializing and saving.
+    #[test]
+    fn test_mutate() {
+        let generators: [Box<dyn Generator>; 3] = [
+            Box::new(WalkGenerator),
+            Box::new(BspGenerator::default()),
+            Box::new(CaveGenerator::default()),
+        ];
+        for generator in generators.iter() {
+            for seed in 0..5 {
+                let config = DungeonConfig::new(40, 40).with_seed(seed).with_locks(1);
+                let mut dun = generator.generate(&config);
+                let original = dun.clone();
+                assert!(dun.is_connected());
+
+                // Changes outside the dungeon or on a lock change nothing
+                assert_eq!(
+                    dun.set_tiles(&[((40, 0), Tile::Floor)], Connectivity::Override),
+                    None
+                );
+                for lock in original.locks() {
+                    assert_eq!(dun.collapse(&[lock.key], Connectivity::Override), None);
+                }
+                assert!(dun == original);
+
+                // Walls on the edge of the dungeon can't be opened
+                let edge_wall = (0..40)
+                    .map(|x| (x, 0))
+                    .find(|p| dun.tile_at(*p) == Tile::Wall);
+                assert_eq!(dun.open_wall(edge_wall.unwrap()), None);
+                let wall = (1..39)
+                    .flat_map(|y| (1..39).map(move |x| (x, y)))
+                    .find(|p| dun.tile_at(*p) == Tile::Wall)
+                    .unwrap();
+                assert_eq!(dun.open_wall(wall), Some(vec![(wall, Tile::Floor)]));
+                assert!(dun.paths_ref().contains(&wall));
+                assert_eq!(dun.open_wall(wall), None);
+                assert_eq!(
+                    dun.set_tiles(&[(wall, Tile::Floor)], Connectivity::Keep),
+                    Some(vec![])
+                );
+
+                // Collapsing a chokepoint is refused, unless overridden
+                let lock_tiles: Vec<(i32, i32)> =
+                    dun.locks().iter().flat_map(|l| [l.door, l.key]).collect();
+                let mut chokepoints: Vec<(i32, i32)> =
+                    chokepoints_between(dun.paths_ref(), dun.entrance, dun.exit)
+                        .into_iter()
+                        .filter(|p| !lock_tiles.contains(p))
+                        .collect();
+                chokepoints.sort_unstable();
+                if let Some(choke) = chokepoints.first() {
+                    let before = dun.clone();
+                    assert_eq!(dun.collapse(&[*choke], Connectivity::Keep), None);
+                    assert!(dun == before);
+
+                    let mut cut = dun.clone();
+                    assert_eq!(
+                        cut.collapse(&[*choke], Connectivity::Override),
+                        Some(vec![(*choke, Tile::Wall)])
+                    );
+                    assert!(!cut.is_connected() && !cut.paths_ref().contains(choke));
+                }
+
+                // A tile off the way to the exit can always be collapsed.
+                // The wall opened above may be cut off from the entrance,
+                // so only tiles which can be reached are tried
+                let reached = distance_field(dun.paths_ref(), dun.entrance);
+                let spare = dun
+                    .paths()
+                    .filter(|p| reached.contains_key(p))
+                    .filter(|p| !dun.is_exit(**p) && **p != dun.entrance && !lock_tiles.contains(p))
+                    .find(|p| {
+                        let mut without = dun.clone();
+                        without.collapse(&[**p], Connectivity::Override);
+                        without.is_connected()
+                    })
+                    .cloned();
+                if let Some(spare) = spare {
+                    assert!(dun.collapse(&[spare], Connectivity::Keep).is_some());
+                    assert_eq!(dun.tile_at(spare), Tile::Wall);
+                    assert!(dun.is_connected());
+                }
+
+                // Flooding a room turns every tile of it which can be walked
+                // over to water, and never cuts off the exit
+                if let Some(room) = dun.rooms().first().cloned() {
+                    let flooded = dun.flood(room.bounds);
+                    assert!(!flooded.is_empty());
+                    for point in room.bounds.points() {
+                        let tile = dun.tile_at(point);
+                        assert!(
+                            !tile.is_walkable()
+                                || tile == Tile::Water
+                                || tile == Tile::Door
+                                || lock_tiles.contains(&point)
+                        );
+                    }
+                    assert!(dun.is_connected());
+                }
+
+                assert_eq!(Dungeon::deserialize(&dun.serialize()), Some(dun.clone()));
+                assert_eq!(Dungeon::from_text(&dun.to_text()), Some(dun.clone()));
+            }
+        }
+    }
+}
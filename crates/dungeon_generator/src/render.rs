@@ -0,0 +1,259 @@
+//! Image export of Dungeons, as SVG and plain PGM / PPM images
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+//!
+//! Each tile of the dungeon is drawn as a square of a single color,
+//! chosen in the same order as `Debug for Dungeon`: actors first,
+//! then the entrance and exits, locks, vault markers, hazards,
+//! the floors of rooms by their kind, and finally plain floor
+//! and wall. PGM images draw each color in its shade of gray.
+
+use std::{fs, io, path::Path};
+
+use crate::{inst::Dungeon, rooms::RoomKind, tiles::Tile};
+
+///
+/// A color, as its red, green and blue components
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Color(pub u8, pub u8, pub u8);
+
+impl Color {
+    /// The shade of gray the `Color` is drawn as in PGM images
+    pub fn gray(&self) -> u8 {
+        ((299 * self.0 as u32 + 587 * self.1 as u32 + 114 * self.2 as u32) / 1000) as u8
+    }
+    /// The `Color` written as an SVG hex color
+    pub fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+/// The color walls are drawn as
+pub const WALL: Color = Color(40, 40, 40);
+/// The color floors outside of rooms are drawn as
+pub const FLOOR: Color = Color(200, 200, 200);
+/// The color the entrance is drawn as
+pub const ENTRANCE: Color = Color(0, 110, 255);
+/// The color every exit is drawn as
+pub const EXIT: Color = Color(0, 170, 0);
+/// The color actors are drawn as
+pub const ACTOR: Color = Color(255, 0, 0);
+
+///
+/// Options for rendering a `Dungeon` to an image - the
+/// number of pixels across each tile, and the positions
+/// of any actors to draw over it.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderOptions {
+    scale: u32,
+    actors: Vec<(i32, i32)>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1,
+            actors: Vec::new(),
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Creates new `RenderOptions`, with a pixel per tile and no actors
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the number of pixels across each tile, of at least 1
+    pub fn with_scale(mut self, scale: u32) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+    /// Sets the positions of the actors drawn over the dungeon
+    pub fn with_actors(mut self, actors: Vec<(i32, i32)>) -> Self {
+        self.actors = actors;
+        self
+    }
+    /// The number of pixels across each tile
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+    /// The positions of the actors drawn over the dungeon
+    pub fn actors(&self) -> &[(i32, i32)] {
+        &self.actors
+    }
+}
+
+impl Dungeon {
+    ///
+    /// The `Color` the tile at `point` is drawn as, with
+    /// the actors of the `options` drawn over it.
+    ///
+    pub fn color_at(&self, point: (i32, i32), options: &RenderOptions) -> Color {
+        if options.actors.contains(&point) {
+            ACTOR
+        } else if self.entrance == point {
+            ENTRANCE
+        } else if self.is_exit(point) {
+            EXIT
+        } else if self.locks().iter().any(|l| l.door == point) {
+            Color(140, 80, 20)
+        } else if self.locks().iter().any(|l| l.key == point) {
+            Color(255, 160, 0)
+        } else if self.vaults().iter().any(|v| v.spawns.contains(&point)) {
+            Color(150, 0, 0)
+        } else if self.vaults().iter().any(|v| v.treasure.contains(&point)) {
+            Color(255, 215, 0)
+        } else {
+            match self.tile_at(point) {
+                Tile::Wall => WALL,
+                Tile::Water => Color(60, 120, 220),
+                Tile::Pit => Color(10, 10, 10),
+                Tile::Door => Color(140, 80, 20),
+                Tile::Trap => Color(200, 60, 200),
+                Tile::Rubble => Color(130, 110, 90),
+                Tile::Floor => match self.rooms().iter().find(|r| r.bounds.contains(point)) {
+                    Some(room) => match room.kind {
+                        RoomKind::Start => Color(160, 200, 255),
+                        RoomKind::Normal => FLOOR,
+                        RoomKind::Treasure => Color(255, 235, 160),
+                        RoomKind::Boss => Color(235, 150, 150),
+                        RoomKind::Exit => Color(170, 230, 170),
+                    },
+                    None => FLOOR,
+                },
+            }
+        }
+    }
+
+    ///
+    /// Renders the `Dungeon` to an SVG image, with a square
+    /// `scale` pixels across for each tile. Runs of tiles of the
+    /// same color along a row are drawn as a single rectangle,
+    /// and actors are drawn as circles.
+    ///
+    pub fn to_svg(&self, options: &RenderOptions) -> String {
+        let (w, h) = (self.width(), self.height());
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" \
+             viewBox=\"0 0 {} {}\" shape-rendering=\"crispEdges\">\n",
+            w * options.scale,
+            h * options.scale,
+            w,
+            h
+        );
+        svg.push_str(&format!(
+            "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+            w,
+            h,
+            WALL.hex()
+        ));
+
+        // Actors are drawn as circles afterwards, rather than as tiles
+        let tiles = RenderOptions::new();
+        for row in 0..h as i32 {
+            let mut col = 0;
+            while col < w as i32 {
+                let color = self.color_at((col, row), &tiles);
+                let start = col;
+                while col < w as i32 && self.color_at((col, row), &tiles) == color {
+                    col += 1;
+                }
+                if color != WALL {
+                    svg.push_str(&format!(
+                        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"{}\"/>\n",
+                        start,
+                        row,
+                        col - start,
+                        color.hex()
+                    ));
+                }
+            }
+        }
+        for actor in options.actors.iter() {
+            svg.push_str(&format!(
+                "<circle cx=\"{}.5\" cy=\"{}.5\" r=\"0.4\" fill=\"{}\"/>\n",
+                actor.0,
+                actor.1,
+                ACTOR.hex()
+            ));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    ///
+    /// Renders the `Dungeon` to a plain (ASCII) PPM image, with a
+    /// square `scale` pixels across for each tile.
+    ///
+    pub fn to_ppm(&self, options: &RenderOptions) -> String {
+        self.to_netpbm("P3", options, |color| {
+            format!("{} {} {}", color.0, color.1, color.2)
+        })
+    }
+
+    ///
+    /// Renders the `Dungeon` to a plain (ASCII) PGM image, with a
+    /// square `scale` pixels across for each tile, drawing each
+    /// color as its shade of gray.
+    ///
+    pub fn to_pgm(&self, options: &RenderOptions) -> String {
+        self.to_netpbm("P2", options, |color| color.gray().to_string())
+    }
+
+    ///
+    /// Saves the `Dungeon` to an image at `path`, as an SVG, PPM or
+    /// PGM image depending on the extension of the `path`.
+    /// Any other extension is an `InvalidInput` error.
+    ///
+    pub fn save_image<P: AsRef<Path>>(&self, path: P, options: &RenderOptions) -> io::Result<()> {
+        let path = path.as_ref();
+        let image = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("svg") => self.to_svg(options),
+            Some("ppm") => self.to_ppm(options),
+            Some("pgm") => self.to_pgm(options),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "image must be .svg, .ppm or .pgm",
+                ))
+            }
+        };
+        fs::write(path, image)
+    }
+
+    /// Writes the plain netpbm image with the given `magic` number,
+    /// writing each pixel with `pixel`. Each row of pixels starts on a
+    /// new line, and lines are kept within 70 characters.
+    fn to_netpbm<F: Fn(Color) -> String>(
+        &self,
+        magic: &str,
+        options: &RenderOptions,
+        pixel: F,
+    ) -> String {
+        let scale = options.scale as i32;
+        let (w, h) = (self.width() as i32, self.height() as i32);
+        let mut image = format!("{}\n{} {}\n255\n", magic, w * scale, h * scale);
+
+        for y in 0..h * scale {
+            let mut line = String::new();
+            for x in 0..w * scale {
+                let value = pixel(self.color_at((x / scale, y / scale), options));
+                if !line.is_empty() && line.len() + value.len() >= 70 {
+                    image.push_str(&line);
+                    image.push('\n');
+                    line.clear();
+                }
+                if !line.is_empty() {
+                    line.push(' ');
+                }
+                line.push_str(&value);
+            }
+            image.push_str(&line);
+            image.push('\n');
+        }
+        image
+    }
+}
@@ -0,0 +1,186 @@
+//! Rooms and Corridors of a Dungeon
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use std::collections::{HashMap, HashSet};
+
+use simple_serializer::{Decode, Deserialize, Encode, Serialize};
+
+use crate::analysis::distance_field;
+
+///
+/// A rectangular area of the dungeon, with its
+/// top-left corner at (`x`, `y`).
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Encode, Decode)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+impl Rect {
+    /// Creates a new `Rect` with its top-left corner
+    /// at (`x`, `y`), `w` wide and `h` tall.
+    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        Self { x, y, w, h }
+    }
+    /// The point at the center of the `Rect`
+    pub fn center(&self) -> (i32, i32) {
+        (self.x + self.w / 2, self.y + self.h / 2)
+    }
+    /// Tests if `point` lies within the `Rect`
+    pub fn contains(&self, point: (i32, i32)) -> bool {
+        point.0 >= self.x
+            && point.1 >= self.y
+            && point.0 < self.x + self.w
+            && point.1 < self.y + self.h
+    }
+    /// An iterator over every point within the `Rect`,
+    /// row by row.
+    pub fn points(&self) -> impl Iterator<Item = (i32, i32)> {
+        let Rect { x, y, w, h } = *self;
+        (y..y + h).flat_map(move |row| (x..x + w).map(move |col| (col, row)))
+    }
+    /// The manhattan distance from `point` to the closest
+    /// point within the `Rect`
+    pub fn distance(&self, point: (i32, i32)) -> i32 {
+        let dx = (self.x - point.0)
+            .max(point.0 - (self.x + self.w - 1))
+            .max(0);
+        let dy = (self.y - point.1)
+            .max(point.1 - (self.y + self.h - 1))
+            .max(0);
+        dx + dy
+    }
+}
+
+///
+/// The purpose a `Room` serves in the dungeon
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RoomKind {
+    /// The room the players enter the dungeon through
+    Start,
+    /// A room with no purpose of its own
+    Normal,
+    /// A dead-end room, off the way to the exit
+    Treasure,
+    /// The room furthest from the start, other than the exit
+    Boss,
+    /// The room the players escape the dungeon through
+    Exit,
+}
+
+simple_serializer::numbered_enum!(RoomKind {
+    Start => "start",
+    Normal => "normal",
+    Treasure => "treasure",
+    Boss => "boss",
+    Exit => "exit",
+});
+
+///
+/// A named room of the dungeon, with its
+/// `RoomKind` and bounding `Rect`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct Room {
+    pub name: String,
+    pub kind: RoomKind,
+    pub bounds: Rect,
+}
+
+///
+/// A corridor joining the rooms at index `from` and `to`
+/// in the dungeon's rooms, made up of its `tiles`.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct Corridor {
+    pub from: usize,
+    pub to: usize,
+    pub tiles: Vec<(i32, i32)>,
+}
+
+///
+/// Creates the named `Room`s for each of the `bounds` given,
+/// deciding the kind of each by how the `corridors` join them,
+/// and where they lie along the `paths` between the `entrance`
+/// and `exit`.
+///
+/// The room closest to the entrance is the start, and the room
+/// closest to the exit is the exit. Of the others, the room
+/// furthest from the entrance is the boss, and the rooms joined
+/// by a single corridor are treasure rooms.
+///
+pub fn name_rooms(
+    bounds: &[Rect],
+    corridors: &[Corridor],
+    paths: &HashSet<(i32, i32)>,
+    entrance: (i32, i32),
+    exit: (i32, i32),
+) -> Vec<Room> {
+    let mut kinds = vec![RoomKind::Normal; bounds.len()];
+    if bounds.is_empty() {
+        return Vec::new();
+    }
+
+    // The closest room to a point, with ties broken by index
+    let closest = |point: (i32, i32)| {
+        (0..bounds.len())
+            .min_by_key(|i| bounds[*i].distance(point))
+            .unwrap()
+    };
+    let start = closest(entrance);
+    let end = closest(exit);
+    kinds[end] = RoomKind::Exit;
+    kinds[start] = RoomKind::Start;
+
+    // The boss room is the room furthest along the paths from the entrance
+    let distances = distance_field(paths, entrance);
+    let boss = (0..bounds.len())
+        .filter(|i| *i != start && *i != end)
+        .filter_map(|i| distances.get(&bounds[i].center()).map(|d| (*d, i)))
+        .max_by_key(|(d, i)| (*d, std::cmp::Reverse(*i)));
+    if let Some((_, boss)) = boss {
+        kinds[boss] = RoomKind::Boss;
+    }
+
+    // Rooms joined by a single corridor are dead ends, holding treasure
+    let mut joins = HashMap::<usize, u32>::new();
+    for corridor in corridors {
+        *joins.entry(corridor.from).or_default() += 1;
+        *joins.entry(corridor.to).or_default() += 1;
+    }
+    for (i, kind) in kinds.iter_mut().enumerate() {
+        if *kind == RoomKind::Normal && joins.get(&i).cloned().unwrap_or(0) <= 1 {
+            *kind = RoomKind::Treasure;
+        }
+    }
+
+    // Name each room by its kind, numbering those
+    // kinds which can appear more than once
+    let mut counts = HashMap::<RoomKind, u32>::new();
+    bounds
+        .iter()
+        .zip(kinds)
+        .map(|(bounds, kind)| {
+            let count = counts.entry(kind).or_default();
+            *count += 1;
+            let name = match kind {
+                RoomKind::Start => "start".to_string(),
+                RoomKind::Boss => "boss".to_string(),
+                RoomKind::Exit => "exit".to_string(),
+                RoomKind::Treasure => format!("treasure_{}", count),
+                RoomKind::Normal => format!("room_{}", count),
+            };
+            Room {
+                name,
+                bounds: *bounds,
+                kind,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,355 @@
+//! Line of sight through a Dungeon, and a cache of it
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+//!
+//! The tiles visible from a point are found by symmetric
+//! shadowcasting: each quarter of the view around the point is
+//! scanned row by row outwards, narrowing the slopes still in view
+//! whenever a wall casts a shadow over the rows behind it. Floors
+//! are only seen when their centre is in view, so any tile can see
+//! a point exactly when that point can see it back. Walls are seen
+//! whenever any part of them is in view, and block the view past them.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{inst::Dungeon, tiles::Tile};
+
+///
+/// A map of which tiles can be seen from each tile of a `Dungeon`
+/// that can be walked over, out to a fixed radius. The view from
+/// every tile can be found up front, with `Dungeon::sight_map`, or
+/// only the first time it is looked at, with `Dungeon::sight_cache`.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SightMap {
+    radius: u32,
+    grid: Grid,
+    complete: bool,
+    visible: HashMap<(i32, i32), Vec<(i32, i32)>>,
+}
+
+impl SightMap {
+    /// How far from each tile its view reaches
+    pub fn radius(&self) -> u32 {
+        self.radius
+    }
+    ///
+    /// The tiles visible from `point`, in order, if it can be walked
+    /// over and its view has been found.
+    ///
+    pub fn visible_from(&self, point: (i32, i32)) -> Option<&[(i32, i32)]> {
+        self.visible.get(&point).map(|visible| visible.as_slice())
+    }
+    /// Tests if the tile at `to` is visible from `from`, if its view has been found
+    pub fn can_see(&self, from: (i32, i32), to: (i32, i32)) -> bool {
+        self.visible
+            .get(&from)
+            .is_some_and(|visible| visible.binary_search(&to).is_ok())
+    }
+    ///
+    /// The tiles visible from `point`, in order, if it can be walked
+    /// over, finding and keeping its view if it hasn't been found yet.
+    ///
+    pub fn look(&mut self, point: (i32, i32)) -> Option<&[(i32, i32)]> {
+        if !self.grid.tile(point).is_walkable() {
+            return None;
+        }
+        let (grid, radius) = (&self.grid, self.radius);
+        Some(
+            self.visible
+                .entry(point)
+                .or_insert_with(|| grid.view(point, radius)),
+        )
+    }
+    ///
+    /// Updates the map after the tiles at `changed` are changed in
+    /// the `dungeon`, finding the view again only from the tiles
+    /// within reach of a change. A cache forgets those views
+    /// instead, to be found again when next looked at.
+    ///
+    pub fn update(&mut self, dungeon: &Dungeon, changed: &[(i32, i32)]) {
+        self.grid = Grid::of(dungeon);
+        let radius = self.radius as i32;
+        for (x, y) in changed.iter() {
+            for dy in -radius..=radius {
+                for dx in -radius..=radius {
+                    let point = (x + dx, y + dy);
+                    if self.complete && self.grid.tile(point).is_walkable() {
+                        self.visible
+                            .insert(point, self.grid.view(point, self.radius));
+                    } else {
+                        self.visible.remove(&point);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Dungeon {
+    ///
+    /// Finds the tiles visible from every tile of the `Dungeon`
+    /// that can be walked over, out to the given `radius`.
+    ///
+    pub fn sight_map(&self, radius: u32) -> SightMap {
+        let grid = Grid::of(self);
+        SightMap {
+            radius,
+            complete: true,
+            visible: self
+                .paths()
+                .filter(|p| self.tile_at(**p).is_walkable())
+                .map(|p| (*p, grid.view(*p, radius)))
+                .collect(),
+            grid,
+        }
+    }
+
+    ///
+    /// Creates a `SightMap` of the `Dungeon` out to the given
+    /// `radius`, which finds the view from each tile only the
+    /// first time it is looked at.
+    ///
+    pub fn sight_cache(&self, radius: u32) -> SightMap {
+        SightMap {
+            radius,
+            grid: Grid::of(self),
+            complete: false,
+            visible: HashMap::new(),
+        }
+    }
+}
+
+///
+/// Finds the tiles of the `dungeon` visible from `origin`, including
+/// the `origin` itself, no further than `radius` tiles away.
+///
+pub fn field_of_view(dungeon: &Dungeon, origin: (i32, i32), radius: u32) -> HashSet<(i32, i32)> {
+    Grid::of(dungeon).view(origin, radius).into_iter().collect()
+}
+
+/// The tiles of a dungeon, row by row
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Grid {
+    width: i32,
+    height: i32,
+    tiles: Vec<Tile>,
+}
+
+impl Grid {
+    /// Lays out the tiles of the `dungeon`
+    fn of(dungeon: &Dungeon) -> Self {
+        let (width, height) = (dungeon.width() as i32, dungeon.height() as i32);
+        Self {
+            width,
+            height,
+            tiles: (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|p| dungeon.tile_at(p))
+                .collect(),
+        }
+    }
+
+    /// Tests if `point` lies within the dungeon
+    fn contains(&self, point: (i32, i32)) -> bool {
+        point.0 >= 0 && point.1 >= 0 && point.0 < self.width && point.1 < self.height
+    }
+
+    /// The tile at `point`, which is wall anywhere outside the dungeon
+    fn tile(&self, point: (i32, i32)) -> Tile {
+        if self.contains(point) {
+            self.tiles[(point.1 * self.width + point.0) as usize]
+        } else {
+            Tile::Wall
+        }
+    }
+
+    /// Finds the tiles visible from `origin`, no further
+    /// than `radius` away, in order
+    fn view(&self, origin: (i32, i32), radius: u32) -> Vec<(i32, i32)> {
+        let mut visible = vec![origin];
+        for quadrant in 0..4 {
+            let scan = Scan {
+                grid: self,
+                origin,
+                quadrant,
+                radius: radius as i64,
+            };
+            scan.row(1, (-1, 1), (1, 1), &mut visible);
+        }
+        // The tiles along the diagonals are seen from two quarters
+        visible.sort_unstable();
+        visible.dedup();
+        visible
+    }
+}
+
+/// A scan of one quarter of the view around the `origin`
+struct Scan<'a> {
+    grid: &'a Grid,
+    origin: (i32, i32),
+    quadrant: u8,
+    radius: i64,
+}
+
+impl Scan<'_> {
+    /// The tile `depth` rows out from the origin, and `col` across
+    fn point(&self, depth: i64, col: i64) -> (i32, i32) {
+        let (depth, col) = (depth as i32, col as i32);
+        let (x, y) = self.origin;
+        match self.quadrant {
+            0 => (x + col, y - depth),
+            1 => (x + depth, y + col),
+            2 => (x + col, y + depth),
+            _ => (x - depth, y + col),
+        }
+    }
+
+    ///
+    /// Scans the row `depth` rows out, between the slopes `start` and
+    /// `end`, each a fraction of its numerator and denominator, then
+    /// scans each part of the next row still in view.
+    ///
+    fn row(
+        &self,
+        depth: i64,
+        mut start: (i64, i64),
+        end: (i64, i64),
+        visible: &mut Vec<(i32, i32)>,
+    ) {
+        if depth > self.radius {
+            return;
+        }
+        // The columns whose centre lies within the slopes,
+        // rounding ties towards the middle of the row
+        let first = (2 * depth * start.0 + start.1).div_euclid(2 * start.1);
+        let last = -(end.1 - 2 * depth * end.0).div_euclid(2 * end.1);
+
+        let mut prev_wall = None;
+        for col in first..=last {
+            let point = self.point(depth, col);
+            let wall = self.grid.tile(point).is_opaque();
+            let symmetric = col * start.1 >= depth * start.0 && col * end.1 <= depth * end.0;
+            if (wall || symmetric)
+                && col * col + depth * depth <= self.radius * self.radius
+                && self.grid.contains(point)
+            {
+                visible.push(point);
+            }
+            // The slope to the near edge of the tile
+            let slope = (2 * col - 1, 2 * depth);
+            match prev_wall {
+                Some(true) if !wall => start = slope,
+                Some(false) if wall => self.row(depth + 1, start, slope, visible),
+                _ => {}
+            }
+            prev_wall = Some(wall);
+        }
+        if prev_wall == Some(false) {
+            self.row(depth + 1, start, end, visible);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::DungeonConfig,
+        generators::{bsp::BspGenerator, caves::CaveGenerator, walk::WalkGenerator},
+        mutate::Connectivity,
+        traits::Generator,
+    };
+
+    /// Tests the tiles seen from points of a hand-written map, and that
+    /// in dungeons from every `Generator` sight is symmetric, stays within
+    /// its radius, and is kept up to date as tiles change.
+    #[test]
+    fn test_sight() {
+        let map = "dungeon 1
+map
+#########
+#<......#
+#...#...#
+#.......#
+#......>#
+#########
+";
+        let dun = Dungeon::from_text(map).unwrap();
+        let sight = dun.sight_map(10);
+        assert!(sight.can_see((1, 1), (7, 1)) && sight.can_see((1, 1), (7, 4)));
+        assert!(sight.can_see((1, 1), (4, 2)) && sight.can_see((1, 1), (0, 0)));
+        assert!(!sight.can_see((1, 1), (7, 3)) && !sight.can_see((7, 3), (1, 1)));
+        assert!(sight.visible_from((4, 2)).is_none());
+        let visible = sight.visible_from((1, 1)).unwrap();
+        assert_eq!(
+            field_of_view(&dun, (1, 1), 10),
+            visible.iter().cloned().collect()
+        );
+        assert!(!dun.sight_map(3).can_see((1, 1), (7, 1)));
+
+        // A cache only finds the view from tiles once they're looked at
+        let mut cache = dun.sight_cache(10);
+        assert!(!cache.can_see((1, 1), (7, 1)) && cache.look((4, 2)).is_none());
+        assert_eq!(cache.look((1, 1)), Some(visible));
+        assert!(cache.can_see((1, 1), (7, 1)));
+
+        let generators: [Box<dyn Generator>; 3] = [
+            Box::new(WalkGenerator),
+            Box::new(BspGenerator::default()),
+            Box::new(CaveGenerator::default()),
+        ];
+        for generator in generators.iter() {
+            for seed in 0..3 {
+                let config = DungeonConfig::new(30, 30).with_seed(seed).with_hazards(10);
+                let mut dun = generator.generate(&config);
+                let original = dun.clone();
+                let mut sight = dun.sight_map(6);
+                for from in dun.paths() {
+                    let visible = sight.visible_from(*from).unwrap();
+                    assert!(visible.contains(from));
+                    for to in visible.iter() {
+                        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+                        assert!(dx * dx + dy * dy <= 36);
+                        if dun.tile_at(*to).is_walkable() {
+                            assert!(sight.can_see(*to, *from));
+                        }
+                    }
+                }
+
+                // Opening a wall and collapsing a tile only changes the
+                // view from the tiles around them
+                let wall = (1..29)
+                    .flat_map(|y| (1..29).map(move |x| (x, y)))
+                    .find(|p| dun.tile_at(*p) == Tile::Wall)
+                    .unwrap();
+                let mut changed = dun.open_wall(wall).unwrap_or_default();
+                let mut floors: Vec<(i32, i32)> = dun.paths().cloned().collect();
+                floors.sort_unstable();
+                let floor = floors.into_iter().find(|p| {
+                    *p != dun.entrance
+                        && !dun.is_exit(*p)
+                        && !dun.locks().iter().any(|l| l.door == *p || l.key == *p)
+                });
+                changed.extend(
+                    dun.collapse(&[floor.unwrap()], Connectivity::Override)
+                        .unwrap(),
+                );
+                let points: Vec<(i32, i32)> = changed.iter().map(|(p, _)| *p).collect();
+                sight.update(&dun, &points);
+                assert!(sight == dun.sight_map(6));
+
+                let mut cache = original.sight_cache(6);
+                cache.look(wall);
+                for from in original.paths() {
+                    cache.look(*from);
+                }
+                cache.update(&dun, &points);
+                for from in dun.paths() {
+                    assert_eq!(cache.look(*from), sight.visible_from(*from));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! Generation statistics of a Dungeon
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use crate::{analysis::regions, inst::Dungeon};
+
+///
+/// Statistics describing the layout of a `Dungeon`,
+/// used to compare the output of different generation
+/// parameters.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DungeonStats {
+    /// The number of path tiles
+    pub tiles: usize,
+    /// The number of separate regions the paths form
+    pub regions: usize,
+    /// The number of independent cycles in the paths - how many
+    /// tiles could be walled off without splitting any region. A
+    /// dungeon shaped like a tree has none. Every 2x2 block of open
+    /// floor counts as a small cycle of its own, so open caverns
+    /// have many more cycles than routes around them.
+    pub cycles: usize,
+    /// The number of loops carved into the paths while the dungeon
+    /// was generated - each a second route around, whatever the
+    /// shape of the rest of the dungeon
+    pub loops: usize,
+}
+
+impl Dungeon {
+    ///
+    /// Computes the `DungeonStats` of the `Dungeon`'s paths,
+    /// and the loops recorded as carved into them
+    ///
+    pub fn stats(&self) -> DungeonStats {
+        let paths = self.paths_ref();
+        let edges = paths
+            .iter()
+            .flat_map(|p| [(p.0 + 1, p.1), (p.0, p.1 + 1)])
+            .filter(|adj| paths.contains(adj))
+            .count();
+        let regions = regions(paths).len();
+
+        DungeonStats {
+            tiles: paths.len(),
+            regions,
+            cycles: edges + regions - paths.len(),
+            loops: self.loops() as usize,
+        }
+    }
+}
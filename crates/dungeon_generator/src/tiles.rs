@@ -0,0 +1,209 @@
+//! Tiles of a Dungeon, and the hazards placed on them
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use rand::{prelude::*, rngs::StdRng};
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analysis::{articulation_points, shortest_route},
+    locks::Lock,
+    vaults::PlacedVault,
+};
+
+///
+/// The kind of ground covering a single tile of the dungeon.
+/// Only walls and pits can't be walked over.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tile {
+    /// Open ground
+    Floor,
+    /// Solid rock
+    Wall,
+    /// Shallow water, slow to wade through
+    Water,
+    /// A drop which can't be crossed, but can be seen over
+    Pit,
+    /// A door, which may be locked
+    Door,
+    /// A hidden trap, best walked around
+    Trap,
+    /// Rubble strewn across the floor, slowing the walk
+    Rubble,
+}
+
+simple_serializer::numbered_enum!(Tile {
+    Floor,
+    Wall,
+    Water,
+    Pit,
+    Door,
+    Trap,
+    Rubble,
+});
+
+impl Tile {
+    /// The character the `Tile` is drawn as in dungeon files
+    pub fn symbol(&self) -> char {
+        match self {
+            Tile::Floor => '.',
+            Tile::Wall => '#',
+            Tile::Water => '~',
+            Tile::Pit => '_',
+            Tile::Door => '+',
+            Tile::Trap => '^',
+            Tile::Rubble => '%',
+        }
+    }
+    /// Converts the character a `Tile` is drawn as
+    /// back into a `Tile`, if it is valid.
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|tile| tile.symbol() == symbol)
+            .cloned()
+    }
+    /// Tests if the `Tile` can be walked over
+    pub fn is_walkable(&self) -> bool {
+        !matches!(self, Tile::Wall | Tile::Pit)
+    }
+    /// Tests if the `Tile` blocks the view past it
+    pub fn is_opaque(&self) -> bool {
+        *self == Tile::Wall
+    }
+    ///
+    /// The cost of stepping onto the `Tile`, when finding
+    /// the best way through the dungeon. Returns `None` if
+    /// the `Tile` can't be walked over.
+    ///
+    pub fn cost(&self) -> Option<u32> {
+        match self {
+            Tile::Floor | Tile::Door => Some(1),
+            Tile::Rubble => Some(2),
+            Tile::Water => Some(3),
+            Tile::Trap => Some(5),
+            Tile::Wall | Tile::Pit => None,
+        }
+    }
+}
+
+///
+/// Places up to `count` hazards - water, pits, traps and rubble -
+/// on the `paths`, off the shortest way from the `entrance` to each
+/// of the `exits`, and away from the doors and keys of the `locks` and the
+/// markers of the `vaults`. Pits can't be walked over, so are taken
+/// out of the `paths`, and only placed where they don't cut any part
+/// of the paths off from the rest. Returns the tile of each hazard.
+///
+pub fn place_hazards(
+    rng: &mut StdRng,
+    paths: &mut HashSet<(i32, i32)>,
+    entrance: (i32, i32),
+    exits: &[(i32, i32)],
+    locks: &[Lock],
+    vaults: &[PlacedVault],
+    count: u32,
+) -> HashMap<(i32, i32), Tile> {
+    let mut hazards = HashMap::new();
+    if count == 0 {
+        return hazards;
+    }
+    let keep: HashSet<(i32, i32)> = locks
+        .iter()
+        .flat_map(|lock| [lock.door, lock.key])
+        .chain(
+            vaults
+                .iter()
+                .flat_map(|vault| vault.spawns.iter().chain(vault.treasure.iter()).cloned()),
+        )
+        .collect();
+    let route: HashSet<(i32, i32)> = exits
+        .iter()
+        .flat_map(|exit| shortest_route(paths, entrance, *exit).unwrap_or_default())
+        .collect();
+
+    let mut spots: Vec<(i32, i32)> = paths
+        .iter()
+        .filter(|spot| !route.contains(spot) && !keep.contains(spot))
+        .cloned()
+        .collect();
+    spots.sort_unstable();
+    spots.shuffle(rng);
+
+    for spot in spots {
+        if hazards.len() == count as usize {
+            break;
+        }
+        let tile = *[Tile::Water, Tile::Pit, Tile::Trap, Tile::Rubble]
+            .choose(rng)
+            .unwrap();
+        if tile == Tile::Pit {
+            if articulation_points(paths).contains(&spot) {
+                continue;
+            }
+            paths.remove(&spot);
+        }
+        hazards.insert(spot, tile);
+    }
+    hazards
+}
+
+#[cfg(test)]
+mod tests {
+    use simple_serializer::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{
+        config::DungeonConfig,
+        generators::{bsp::BspGenerator, caves::CaveGenerator, walk::WalkGenerator},
+        inst::Dungeon,
+        traits::Generator,
+    };
+
+    /// Tests that tiles other than floors and walls are read from
+    /// hand-written maps, and survive being saved, and that hazards
+    /// placed by every `Generator` never block the way to the exit.
+    #[test]
+    fn test_tiles() {
+        let dun = Dungeon::from_text("dungeon 1\nmap\n<~_+^%>\n").unwrap();
+        let tiles: Vec<Tile> = (0..7).map(|x| dun.tile_at((x, 0))).collect();
+        assert_eq!(
+            tiles[1..6],
+            [Tile::Water, Tile::Pit, Tile::Door, Tile::Trap, Tile::Rubble]
+        );
+        assert_eq!(dun.tile_at((0, 1)), Tile::Wall);
+        assert!(!dun.paths_ref().contains(&(2, 0)));
+        assert!(!dun.is_connected());
+        assert_eq!(Dungeon::deserialize(&dun.serialize()), Some(dun.clone()));
+        assert_eq!(Dungeon::from_text(&dun.to_text()), Some(dun));
+
+        let generators: [&dyn Generator; 3] = [
+            &WalkGenerator,
+            &BspGenerator::default(),
+            &CaveGenerator::default(),
+        ];
+        for generator in generators.iter() {
+            for seed in 0..5 {
+                let config = DungeonConfig::new(40, 40).with_seed(seed).with_locks(2);
+                let plain = generator.generate(&config);
+                let dun = generator.generate(&config.with_hazards(15));
+
+                assert!(dun.tiles().len() <= 15 + dun.locks().len());
+                assert!(dun.is_solvable());
+                assert_eq!(dun.locks(), plain.locks());
+                assert_eq!(dun.stats().regions, plain.stats().regions);
+                for lock in dun.locks() {
+                    assert_eq!(dun.tile_at(lock.door), Tile::Door);
+                }
+                for (point, tile) in dun.tiles() {
+                    assert_eq!(dun.paths_ref().contains(point), tile.is_walkable());
+                    assert!(plain.paths_ref().contains(point));
+                }
+                assert_eq!(Dungeon::deserialize(&dun.serialize()), Some(dun.clone()));
+                assert_eq!(Dungeon::from_text(&dun.to_text()), Some(dun));
+            }
+        }
+    }
+}
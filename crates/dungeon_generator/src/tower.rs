@@ -0,0 +1,111 @@
+//! Multi-floor Towers of Dungeons
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use rand::prelude::*;
+
+use crate::{
+    config::{DungeonConfig, Placement},
+    gen::seeded_rng,
+    generators::walk::WalkGenerator,
+    inst::Dungeon,
+    traits::Generator,
+};
+
+///
+/// A stack of `Dungeon` floors, which the players descend
+/// through one after another. The exit of each floor lines
+/// up with the entrance of the floor below it, so the
+/// players arrive where they left the floor above.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tower {
+    seed: u64,
+    floors: Vec<Dungeon>,
+}
+
+impl Tower {
+    ///
+    /// Generates a new `Tower` of `floors` floors, each sized and
+    /// placed by `config`, and generated by one of the `generators`
+    /// chosen at random (the random walk, if there are none).
+    /// The first floor's entrance follows `config`, while each
+    /// floor after starts at the exit of the floor above, with the
+    /// exit moved off it if `config` places both at the same spot.
+    /// A `Tower` always has at least one floor, so asking for
+    /// none generates a single floor. Each floor falling outside
+    /// the `config`'s difficulty band is generated again, up to its
+    /// number of attempts in all, keeping the last if none fall in it.
+    /// The same seeded `config` always produces the same `Tower`.
+    ///
+    pub fn generate(
+        config: &DungeonConfig,
+        floors: u32,
+        generators: &[Box<dyn Generator>],
+    ) -> Self {
+        let (band, attempts) = (config.difficulty(), config.attempts());
+        let (seed, mut rng) = seeded_rng(config);
+
+        let mut tower = Vec::<Dungeon>::with_capacity(floors as usize);
+        for _ in 0..floors.max(1) {
+            for attempt in 1..=attempts {
+                let mut floor_config = config.clone().with_seed(rng.gen());
+                if let Some(above) = tower.last() {
+                    floor_config =
+                        floor_config.with_entrance(Placement::At(above.exit.0, above.exit.1));
+                }
+
+                let floor = match generators.choose(&mut rng) {
+                    Some(generator) => generator.generate(&floor_config),
+                    None => WalkGenerator.generate(&floor_config),
+                };
+                if attempt >= attempts || band.accepts(&floor) {
+                    tower.push(floor);
+                    break;
+                }
+            }
+        }
+
+        Self {
+            seed,
+            floors: tower,
+        }
+    }
+    ///
+    /// Creates a `Tower` directly from its `floors`, such as
+    /// hand-authored dungeons loaded from files. The tower takes
+    /// the seed of its top floor. Returns `None` if there are no
+    /// floors, or the exit of a floor doesn't line up with the
+    /// entrance of the floor below it.
+    ///
+    pub fn from_floors(floors: Vec<Dungeon>) -> Option<Self> {
+        let lined_up = floors
+            .windows(2)
+            .all(|pair| pair[0].exit == pair[1].entrance);
+        match floors.first() {
+            Some(top) if lined_up => Some(Self {
+                seed: top.seed(),
+                floors,
+            }),
+            _ => None,
+        }
+    }
+    /// The seed the tower was generated from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    /// The floors of the tower, from the top down
+    pub fn floors(&self) -> &[Dungeon] {
+        &self.floors
+    }
+    /// The floor at the given depth, if there is one.
+    /// The top floor is at a depth of 0.
+    pub fn floor(&self, depth: usize) -> Option<&Dungeon> {
+        self.floors.get(depth)
+    }
+    /// The number of floors in the tower
+    pub fn depth(&self) -> usize {
+        self.floors.len()
+    }
+}
@@ -0,0 +1,19 @@
+//! Traits for Dungeon Generator
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use crate::{config::DungeonConfig, inst::Dungeon};
+
+///
+/// Represents an algorithm which can generate a `Dungeon`.
+/// Each implementation gives its dungeons a different
+/// style, while sharing the same `DungeonConfig`.
+///
+pub trait Generator {
+    /// Generates a new `Dungeon` with the size, seed and
+    /// entrance / exit placement rules in `config`. The same
+    /// seeded `config` must always produce the same `Dungeon`,
+    /// with a path from its entrance to its exit.
+    fn generate(&self, config: &DungeonConfig) -> Dungeon;
+}
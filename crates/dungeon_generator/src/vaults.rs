@@ -0,0 +1,311 @@
+//! Hand-designed vaults stamped into generated Dungeons
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+//!
+//! A vault is a small room drawn as an ASCII pattern:
+//!
+//! ```text
+//! #####+#####
+//! #.........#
+//! #..M...M..#
+//! +....$....+
+//! #..M...M..#
+//! #.........#
+//! #####+#####
+//! ```
+//!
+//! `#` (or a space) is a wall, `.` is floor, `M` is a floor tile
+//! monsters spawn on, `$` is a floor tile holding treasure, and `+`
+//! is a door on the edge of the pattern, which is joined to the rest
+//! of the dungeon once the vault is stamped in.
+
+use rand::{prelude::*, rngs::StdRng};
+use simple_serializer::{Decode, Deserialize, Encode, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{
+    gen::{in_bounds, NEIGHBOURS},
+    rooms::Rect,
+};
+
+///
+/// A hand-designed room, read from an ASCII pattern,
+/// with its tiles relative to its top-left corner.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vault {
+    name: String,
+    width: i32,
+    height: i32,
+    floor: Vec<(i32, i32)>,
+    spawns: Vec<(i32, i32)>,
+    treasure: Vec<(i32, i32)>,
+    doors: Vec<(i32, i32)>,
+}
+
+impl Vault {
+    ///
+    /// Reads a `Vault` called `name` from its ASCII `pattern`.
+    /// Returns `None` if the pattern holds an unknown character,
+    /// has no doors, or has a door which isn't on its edge.
+    ///
+    pub fn parse(name: &str, pattern: &str) -> Option<Vault> {
+        let mut vault = Vault {
+            name: name.to_string(),
+            width: 0,
+            height: 0,
+            floor: Vec::new(),
+            spawns: Vec::new(),
+            treasure: Vec::new(),
+            doors: Vec::new(),
+        };
+
+        let rows: Vec<&str> = pattern
+            .lines()
+            .map(|line| line.trim_end())
+            .skip_while(|line| line.is_empty())
+            .collect();
+        for (row, line) in rows.iter().enumerate() {
+            for (col, tile) in line.chars().enumerate() {
+                let point = (col as i32, row as i32);
+                match tile {
+                    '#' | ' ' => continue,
+                    '.' => {}
+                    'M' => vault.spawns.push(point),
+                    '$' => vault.treasure.push(point),
+                    '+' => vault.doors.push(point),
+                    _ => return None,
+                }
+                vault.floor.push(point);
+            }
+            vault.width = vault.width.max(line.chars().count() as i32);
+        }
+        vault.height = rows.len() as i32;
+
+        let on_edge = |p: &(i32, i32)| {
+            p.0 == 0 || p.1 == 0 || p.0 == vault.width - 1 || p.1 == vault.height - 1
+        };
+        if vault.doors.is_empty() || !vault.doors.iter().all(on_edge) {
+            return None;
+        }
+        Some(vault)
+    }
+    /// The name of the vault
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// The horizontal bounds of the vault
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+    /// The vertical bounds of the vault
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+}
+
+///
+/// A `Vault` stamped into a `Dungeon`, with the
+/// positions of its markers within the dungeon.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, Encode, Decode)]
+pub struct PlacedVault {
+    /// The name of the vault stamped in
+    pub name: String,
+    /// The area the vault covers, including its walls
+    pub bounds: Rect,
+    /// The tiles monsters spawn on
+    pub spawns: Vec<(i32, i32)>,
+    /// The tiles holding treasure
+    pub treasure: Vec<(i32, i32)>,
+}
+
+///
+/// Stamps up to `count` vaults, each chosen at random from the
+/// `library`, into areas of solid wall within the `width` and
+/// `height` of the `paths`. Each door of a vault is joined to the
+/// closest tile of the paths outside it, by the shortest tunnel
+/// which doesn't pass through the vault. Fewer vaults are stamped
+/// if there is no room left for them.
+///
+pub fn stamp_vaults(
+    rng: &mut StdRng,
+    paths: &mut HashSet<(i32, i32)>,
+    width: u32,
+    height: u32,
+    library: &[Vault],
+    count: u32,
+) -> Vec<PlacedVault> {
+    let mut placed = Vec::<PlacedVault>::new();
+    if library.is_empty() {
+        return placed;
+    }
+
+    for _ in 0..count {
+        let vault = library.choose(rng).unwrap();
+        let spots = free_spots(paths, width, height, vault, &placed);
+        let bounds = match spots.choose(rng) {
+            Some(bounds) => *bounds,
+            None => continue,
+        };
+        let offset = |p: &(i32, i32)| (p.0 + bounds.x, p.1 + bounds.y);
+
+        paths.extend(vault.floor.iter().map(offset));
+        for door in vault.doors.iter().map(offset) {
+            if let Some(tunnel) = tunnel(paths, width, height, bounds, door) {
+                paths.extend(tunnel);
+            }
+        }
+
+        placed.push(PlacedVault {
+            name: vault.name.clone(),
+            bounds,
+            spawns: vault.spawns.iter().map(offset).collect(),
+            treasure: vault.treasure.iter().map(offset).collect(),
+        });
+    }
+    placed
+}
+
+/// Finds every area the `vault` could be stamped into, with a wall
+/// of solid rock around it, clear of the `placed` vaults, and within
+/// the `width` and `height`. Returned in a deterministic order.
+fn free_spots(
+    paths: &HashSet<(i32, i32)>,
+    width: u32,
+    height: u32,
+    vault: &Vault,
+    placed: &[PlacedVault],
+) -> Vec<Rect> {
+    let mut spots = Vec::new();
+    for y in 1..height as i32 - vault.height {
+        for x in 1..width as i32 - vault.width {
+            let margin = Rect::new(x - 1, y - 1, vault.width + 2, vault.height + 2);
+            let clear = !margin.points().any(|p| paths.contains(&p))
+                && !placed
+                    .iter()
+                    .any(|other| margin.points().any(|p| other.bounds.contains(p)));
+            if clear {
+                spots.push(Rect::new(x, y, vault.width, vault.height));
+            }
+        }
+    }
+    spots
+}
+
+/// Finds the shortest tunnel from just outside the `door` of the vault
+/// covering `bounds` to the closest tile of the `paths`, without
+/// passing through the vault. Returns the tiles to carve, if
+/// the paths can be reached.
+fn tunnel(
+    paths: &HashSet<(i32, i32)>,
+    width: u32,
+    height: u32,
+    bounds: Rect,
+    door: (i32, i32),
+) -> Option<Vec<(i32, i32)>> {
+    let outside = |p: &(i32, i32)| !bounds.contains(*p) && in_bounds(*p, width, height);
+    let start = NEIGHBOURS
+        .iter()
+        .map(|dir| (door.0 + dir.0, door.1 + dir.1))
+        .find(outside)?;
+
+    let mut parents = HashMap::new();
+    let mut queue = VecDeque::new();
+    parents.insert(start, start);
+    queue.push_back(start);
+
+    while let Some(cur) = queue.pop_front() {
+        if paths.contains(&cur) {
+            // Walk back to the start, leaving out the tile reached
+            let mut tiles = Vec::new();
+            let mut tile = cur;
+            while tile != start {
+                tile = parents[&tile];
+                tiles.push(tile);
+            }
+            return Some(tiles);
+        }
+        for (dx, dy) in NEIGHBOURS.iter() {
+            let next = (cur.0 + dx, cur.1 + dy);
+            if outside(&next) && !parents.contains_key(&next) {
+                parents.insert(next, cur);
+                queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use simple_serializer::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{
+        analysis::distance_field,
+        config::DungeonConfig,
+        generators::{bsp::BspGenerator, caves::CaveGenerator, walk::WalkGenerator},
+        inst::Dungeon,
+        traits::Generator,
+    };
+
+    /// Tests that vault patterns are read correctly, and that vaults
+    /// stamped in by every `Generator` keep clear of each other and are
+    /// joined to the rest of the paths, and survive being saved.
+    #[test]
+    fn test_vaults() {
+        let shrine = Vault::parse("shrine", "#+###\n#.M$#\n#####\n").unwrap();
+        assert_eq!((shrine.width(), shrine.height()), (5, 3));
+        assert!(Vault::parse("bad", "#+#\n#?#\n").is_none());
+        assert!(Vault::parse("sealed", "###\n#.#\n###\n").is_none());
+        assert!(Vault::parse("inner", "#####\n#.+.#\n#####\n").is_none());
+
+        let hall = Vault::parse("hall", "###+###\n#M...M#\n+..$..+\n#M...M#\n###+###\n").unwrap();
+        let generators: [&dyn Generator; 3] = [
+            &WalkGenerator,
+            &BspGenerator::default(),
+            &CaveGenerator::default(),
+        ];
+
+        for generator in generators.iter() {
+            for seed in 0..5 {
+                let config = DungeonConfig::new(60, 60).with_seed(seed);
+                let plain = generator.generate(&config);
+                assert!(plain.vaults().is_empty());
+                assert!(
+                    plain == generator.generate(&config.clone().with_vaults(vec![hall.clone()], 0))
+                );
+
+                let dun =
+                    generator.generate(&config.with_vaults(vec![shrine.clone(), hall.clone()], 3));
+                assert!(dun.is_connected());
+
+                let reached = distance_field(dun.paths_ref(), dun.entrance);
+                for (i, vault) in dun.vaults().iter().enumerate() {
+                    let markers = vault.spawns.iter().chain(vault.treasure.iter());
+                    for marker in markers {
+                        assert!(vault.bounds.contains(*marker));
+                        assert!(reached.contains_key(marker));
+                    }
+                    for other in dun.vaults()[i + 1..].iter() {
+                        assert!(!vault.bounds.points().any(|p| other.bounds.contains(p)));
+                    }
+                }
+
+                assert_eq!(Dungeon::deserialize(&dun.serialize()), Some(dun.clone()));
+                assert_eq!(Dungeon::from_text(&dun.to_text()), Some(dun));
+            }
+        }
+
+        // The walk leaves plenty of solid rock in a large dungeon
+        let dun = WalkGenerator.generate(
+            &DungeonConfig::new(75, 75)
+                .with_seed(1)
+                .with_vaults(vec![hall], 2),
+        );
+        assert_eq!(dun.vaults().len(), 2);
+        assert_eq!(dun.vaults()[0].spawns.len(), 4);
+    }
+}
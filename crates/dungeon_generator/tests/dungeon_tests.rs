@@ -5,7 +5,56 @@
 
 use std::collections::HashSet;
 
-use dungeon_generator::inst::Dungeon;
+use dungeon_generator::{
+    analysis::distance_field,
+    biomes::{Biome, Decoration},
+    config::{DirectionBias, DungeonConfig, Edge, Placement},
+    difficulty::DifficultyBand,
+    generators::{bsp::BspGenerator, caves::CaveGenerator, walk::WalkGenerator, wfc::WfcGenerator},
+    inst::Dungeon,
+    mutate::Connectivity,
+    render::{RenderOptions, ACTOR, ENTRANCE, EXIT, WALL},
+    rooms::RoomKind,
+    tiles::Tile,
+    tower::Tower,
+    traits::Generator,
+    vaults::Vault,
+};
+use simple_serializer::{Deserialize, Serialize, Versioned};
+
+/// Function for determining whether the entrance
+/// eventually traverses to the exit
+fn is_dungeon_valid(dun: &Dungeon) -> bool {
+    // The list of paths already visited.
+    let mut visited = HashSet::new();
+    // The list of next paths to visit
+    let mut next = Vec::new();
+
+    // Push the entrance onto the stack
+    next.push(dun.entrance);
+    visited.insert(dun.entrance);
+
+    // While all paths have not been searched,
+    // take the current one and check its adjacent
+    // neighbors.
+    while let Some(point) = next.pop() {
+        // If the current path is the exit, return true
+        if point == dun.exit {
+            return true;
+        }
+        for p in dun.paths() {
+            if ((point.1 == p.1 && (point.0 - p.0).abs() == 1)
+                || (point.0 == p.0 && (point.1 - p.1).abs() == 1))
+                && !visited.contains(p)
+            {
+                next.push(*p);
+                visited.insert(*p);
+            }
+        }
+    }
+
+    false
+}
 
 /// Generates about 50 dungeons of different sizes, and
 /// tests that each one successfully creates and entrance-
@@ -13,42 +62,663 @@ use dungeon_generator::inst::Dungeon;
 /// the test fails. Performs a breadth-first-search
 #[test]
 fn test_100() {
-    // Function for determining whether the entrance
-    // eventually traverses to the exit
-    fn is_dungeon_valid(dun: Dungeon) -> bool {
-        // The list of paths already visited.
-        let mut visited = HashSet::new();
-        // The list of next paths to visit
-        let mut next = Vec::new();
-
-        // Push the entrance onto the stack
-        next.push(dun.entrance);
-        visited.insert(dun.entrance);
-
-        // While all paths have not been searched,
-        // take the current one and check its adjacent
-        // neighbors.
-        while let Some(point) = next.pop() {
-            // If the current path is the exit, return true
-            if point == dun.exit {
-                return true;
+    for i in (20..75).step_by(2) {
+        assert!(is_dungeon_valid(&Dungeon::new(i, i)));
+    }
+}
+
+/// Generates dungeons from several seeds, and tests
+/// that regenerating from the same seed and size gives
+/// an identical `Dungeon`, down to its serialized form.
+#[test]
+fn test_seeded() {
+    for seed in 0..20 {
+        let dun = Dungeon::from_seed(seed, 40, 40);
+        let other = Dungeon::from_seed(seed, 40, 40);
+
+        assert_eq!(dun.seed(), seed);
+        assert!(dun == other);
+        assert_eq!(dun.serialize(), other.serialize());
+        // The seed is followed by the (empty) rooms, corridors
+        // and locks, the size of the dungeon, the (empty) vaults
+        // and tiles, the exit, without a destination, the (empty)
+        // regions and decorations, and the number of loops
+        assert!(dun.serialize().ends_with(&format!(
+            "::{}::0::0::0::40::40::0::0::1::{}::{}::::0::0::0",
+            seed, dun.exit.0, dun.exit.1
+        )));
+    }
+}
+
+/// Tests that `Dungeon::with_config` respects the size and
+/// placement rules of its `DungeonConfig`, and that the
+/// resulting dungeons are still traversable.
+#[test]
+fn test_config() {
+    let configs = [
+        DungeonConfig::new(30, 50).with_entrance(Placement::Edge(Edge::Bottom)),
+        DungeonConfig::new(40, 40)
+            .with_entrance(Placement::At(5, 20))
+            .with_exit(Placement::At(35, 2)),
+        DungeonConfig::new(40, 40)
+            .with_entrance(Placement::Edge(Edge::Top))
+            .with_exit(Placement::Edge(Edge::Top)),
+        DungeonConfig::new(40, 40)
+            .with_noise_scale(0.1, 0.1)
+            .with_noise_threshold(0.3)
+            .with_direction_bias(DirectionBias {
+                sideways: 5,
+                towards_exit: 0,
+                forward: 0,
+            }),
+    ];
+
+    for (i, config) in configs.iter().enumerate() {
+        let dun = Dungeon::with_config(&config.clone().with_seed(i as u64));
+        assert_eq!(
+            (dun.width(), dun.height()),
+            (config.width(), config.height())
+        );
+        assert!(dun.paths().all(|p| p.0 >= 0
+            && p.1 >= 0
+            && p.0 < dun.width() as i32
+            && p.1 < dun.height() as i32));
+        assert!(is_dungeon_valid(&dun));
+    }
+
+    let dun = Dungeon::with_config(&configs[0]);
+    assert_eq!(dun.entrance.1, 49);
+    assert_eq!(dun.exit.1, 0);
+
+    let dun = Dungeon::with_config(&configs[1]);
+    assert_eq!((dun.entrance, dun.exit), ((5, 20), (35, 2)));
+
+    let dun = Dungeon::with_config(&configs[2]);
+    assert_eq!((dun.entrance.1, dun.exit.1), (0, 0));
+}
+
+/// Tests that configs of tiny or empty sizes, with exact positions
+/// outside the dungeon or interior ones in a dungeon too thin to
+/// have an interior, generate with every `Generator`
+/// rather than panicking, keeping everything within bounds.
+#[test]
+fn test_degenerate_configs() {
+    let generators: [&dyn Generator; 4] = [
+        &WalkGenerator,
+        &BspGenerator::default(),
+        &CaveGenerator::default(),
+        &WfcGenerator::default(),
+    ];
+    let shrine = Vault::parse("shrine", "#+###\n#.M$#\n#####\n").unwrap();
+
+    assert_eq!(DungeonConfig::new(0, 0).width(), 1);
+    assert_eq!(DungeonConfig::new(5, 5).with_size(3, 0).height(), 1);
+
+    for generator in generators.iter() {
+        for (seed, (width, height)) in [(0, 0), (1, 1), (2, 1), (1, 3), (4, 4)].iter().enumerate() {
+            let config = DungeonConfig::new(*width, *height)
+                .with_seed(seed as u64)
+                .with_entrance(Placement::At(100, -5))
+                .with_exit(Placement::At(-7, 200))
+                .with_extra_exit(Placement::At(-3, 7), None)
+                .with_extra_exit(Placement::Interior, None)
+                .with_loops(2)
+                .with_locks(1)
+                .with_vaults(vec![shrine.clone()], 1)
+                .with_hazards(3)
+                .with_biomes(2)
+                .with_decorations(2);
+            let dun = generator.generate(&config);
+
+            let in_bounds = |p: &(i32, i32)| {
+                p.0 >= 0 && p.1 >= 0 && p.0 < dun.width() as i32 && p.1 < dun.height() as i32
+            };
+            assert!(in_bounds(&dun.entrance) && in_bounds(&dun.exit));
+            assert!(dun.exits().iter().all(|e| in_bounds(&e.pos)));
+            assert!(dun.paths().all(in_bounds));
+        }
+    }
+}
+
+/// Tests that a noise threshold above any perlin value leaves
+/// only the winding main path, which is thinner than the
+/// default dungeon built from the same seed.
+#[test]
+fn test_noise_threshold() {
+    let config = DungeonConfig::new(40, 40).with_seed(7);
+    let sparse = Dungeon::with_config(&config.clone().with_noise_threshold(2.0));
+    let dense = Dungeon::with_config(&config);
+
+    assert!(is_dungeon_valid(&sparse));
+    assert_eq!((sparse.entrance, sparse.exit), (dense.entrance, dense.exit));
+    assert!(sparse.paths().len() < dense.paths().len());
+}
+
+/// Generates dungeons of several sizes with each `Generator`,
+/// and tests that each one is traversable, stays within its
+/// bounds, and is reproducible from its seed.
+#[test]
+fn test_generators() {
+    let generators: [&dyn Generator; 3] = [
+        &WalkGenerator,
+        &BspGenerator::default(),
+        &CaveGenerator::default(),
+    ];
+
+    for generator in generators.iter() {
+        for (seed, size) in (20..75).step_by(6).enumerate() {
+            let config = DungeonConfig::new(size, size + 5).with_seed(seed as u64);
+            let dun = generator.generate(&config);
+
+            assert!(is_dungeon_valid(&dun));
+            assert!(dun.paths().all(|p| p.0 >= 0
+                && p.1 >= 0
+                && p.0 < dun.width() as i32
+                && p.1 < dun.height() as i32));
+            assert!(dun == generator.generate(&config));
+        }
+    }
+}
+
+/// Tests that rooms-and-corridors dungeons record their rooms,
+/// with a single start and exit room, and that every room and
+/// corridor lies on the dungeon's paths.
+#[test]
+fn test_rooms() {
+    for seed in 0..10 {
+        let config = DungeonConfig::new(60, 60).with_seed(seed);
+        let dun = BspGenerator::default().generate(&config);
+
+        assert!(dun.rooms().len() > 1);
+        for kind in [RoomKind::Start, RoomKind::Exit].iter() {
+            assert_eq!(dun.rooms().iter().filter(|r| r.kind == *kind).count(), 1);
+        }
+        assert!(dun.room_of_kind(RoomKind::Boss).is_some());
+
+        for room in dun.rooms() {
+            assert!(room.bounds.points().all(|p| dun.paths_ref().contains(&p)));
+        }
+        for corridor in dun.corridors() {
+            assert!(corridor.from < dun.rooms().len() && corridor.to < dun.rooms().len());
+            assert!(corridor.tiles.iter().all(|t| dun.paths_ref().contains(t)));
+        }
+
+        // The start room is the room closest to the entrance
+        let start = dun.room_of_kind(RoomKind::Start).unwrap();
+        assert!(dun
+            .rooms()
+            .iter()
+            .all(|r| start.bounds.distance(dun.entrance) <= r.bounds.distance(dun.entrance)));
+    }
+
+    // The random walk has no notion of rooms
+    assert!(Dungeon::from_seed(0, 40, 40).rooms().is_empty());
+}
+
+/// Tests that each floor of a `Tower` is traversable, that the
+/// exit of each floor lines up with the entrance of the next,
+/// and that towers are reproducible from their seed.
+#[test]
+fn test_tower() {
+    let generators: Vec<Box<dyn Generator>> = vec![
+        Box::new(WalkGenerator),
+        Box::new(BspGenerator::default()),
+        Box::new(CaveGenerator::default()),
+    ];
+
+    for seed in 0..10 {
+        let config = DungeonConfig::new(40, 40).with_seed(seed);
+        let tower = Tower::generate(&config, 4, &generators);
+
+        assert_eq!(tower.depth(), 4);
+        assert!(tower.floors().iter().all(is_dungeon_valid));
+        for pair in tower.floors().windows(2) {
+            assert_eq!(pair[0].exit, pair[1].entrance);
+        }
+        assert!(tower.floor(4).is_none());
+        assert!(tower == Tower::generate(&config, 4, &generators));
+    }
+
+    // An exit at an exact spot is moved off each floor's
+    // entrance, which the floor above left at the same spot
+    let config = DungeonConfig::new(30, 30)
+        .with_seed(3)
+        .with_exit(Placement::At(15, 29));
+    let tower = Tower::generate(&config, 3, &generators);
+    assert_eq!(tower.floors()[0].exit, (15, 29));
+    for floor in tower.floors() {
+        assert_ne!(floor.entrance, floor.exit);
+        assert!(is_dungeon_valid(floor));
+    }
+    for pair in tower.floors().windows(2) {
+        assert_eq!(pair[0].exit, pair[1].entrance);
+    }
+
+    // A tower always has at least one floor
+    assert_eq!(
+        Tower::generate(&DungeonConfig::new(20, 20), 0, &[]).depth(),
+        1
+    );
+}
+
+/// Tests that dungeons read back from both their serialization
+/// and the human-editable file format are identical to the
+/// original, and that malformed input is rejected.
+#[test]
+fn test_load_save() {
+    let generators: [&dyn Generator; 3] = [
+        &WalkGenerator,
+        &BspGenerator::default(),
+        &CaveGenerator::default(),
+    ];
+
+    for generator in generators.iter() {
+        for seed in 0..5 {
+            let config = DungeonConfig::new(30, 25).with_seed(seed).with_locks(2);
+            let dun = generator.generate(&config);
+
+            assert!(Dungeon::deserialize(&dun.serialize()) == Some(dun.clone()));
+            assert!(Dungeon::from_text(&dun.to_text()) == Some(dun.clone()));
+        }
+    }
+
+    // Files can be saved and loaded
+    let dun = BspGenerator::default().generate(&DungeonConfig::new(30, 30).with_seed(7));
+    let path = std::env::temp_dir().join("dungeon_generator_test_load_save.txt");
+    dun.save(&path).unwrap();
+    assert!(Dungeon::load(&path).unwrap() == dun);
+    std::fs::remove_file(&path).unwrap();
+
+    // Names with spaces, backslashes and other whitespace are saved
+    // as single words, and read back as they were
+    let guard = Vault::parse("guard room", "#+###\n#.M$#\n#####\n").unwrap();
+    let config = DungeonConfig::new(30, 30)
+        .with_seed(2)
+        .with_vaults(vec![guard], 1)
+        .with_exit_destination("the\\deep\tend")
+        .with_extra_exit(Placement::Random, Some(""));
+    let named = BspGenerator::default().generate(&config);
+    assert_eq!(named.vaults()[0].name, "guard room");
+    assert!(named.to_text().contains("vault guard\\sroom "));
+    assert!(named.to_text().contains(" the\\\\deep\\tend\n"));
+    assert!(Dungeon::from_text(&named.to_text()) == Some(named.clone()));
+    named.save(&path).unwrap();
+    assert!(Dungeon::load(&path).unwrap() == named);
+    std::fs::remove_file(&path).unwrap();
+
+    // Names holding delimiters, escapes and line breaks are
+    // escaped in the serialization, and read back as they were
+    let hostile = Vault::parse("a::b", "#+###\n#.M$#\n#####\n").unwrap();
+    let config = DungeonConfig::new(30, 30)
+        .with_seed(2)
+        .with_vaults(vec![hostile], 1)
+        .with_exit_destination("\\::\nup");
+    let named = BspGenerator::default().generate(&config);
+    assert_eq!(named.vaults()[0].name, "a::b");
+    let text = named.serialize();
+    assert!(!text.contains('\n') && text.contains("::a\\:\\:b::"));
+    assert!(Dungeon::deserialize(&text) == Some(named.clone()));
+
+    // Strings from before the seed was sent are still read
+    let old = Dungeon::deserialize("3::0::0::1::0::2::0::0::0::2::0").unwrap();
+    assert_eq!(
+        (old.entrance, old.exit, old.width(), old.height()),
+        ((0, 0), (2, 0), 3, 1)
+    );
+    assert_eq!(
+        old.serialize_for(1),
+        Some("3::0::0::1::0::2::0::0::0::2::0".to_string())
+    );
+    assert_eq!(named.serialize_for(2), Some(named.serialize()));
+
+    // A hand-written map
+    let text = "dungeon 1\n// a corridor\nseed 4\nlock 2 1 1 1\nmap\n#####\n#<.>#\n#####\n";
+    let hand = Dungeon::from_text(text).unwrap();
+    assert_eq!((hand.entrance, hand.exit, hand.seed()), ((1, 1), (3, 1), 4));
+    assert_eq!((hand.width(), hand.height(), hand.paths().len()), (5, 3, 3));
+    assert!(hand.is_solvable());
+
+    // Malformed input is rejected
+    assert!(Dungeon::deserialize("2::0::0::1").is_none());
+    assert!(Dungeon::deserialize("99999999999999::0::0::1::0").is_none());
+    assert!(Dungeon::deserialize(&format!("{}::9", dun.serialize())).is_none());
+    assert!(Dungeon::from_text("dungeon 2\nmap\n<>\n").is_none());
+    assert!(Dungeon::from_text("dungeon 1\nexit 1 0 a\\q\nmap\n<>\n").is_none());
+    assert!(Dungeon::from_text("dungeon 1\nexit 1 0 a\\u{20\nmap\n<>\n").is_none());
+    assert!(Dungeon::from_text("dungeon 1\nmap\n<.\n").is_none());
+    assert!(Dungeon::from_text("dungeon 1\nmap\n<?>\n").is_none());
+    assert!(Dungeon::from_text("dungeon 1\nroom a cellar 0 0 1 1\nmap\n<>\n").is_none());
+}
+
+/// Tests the difficulty report of a hand-written map, and that
+/// towers generated within a difficulty band only hold floors
+/// inside it, while an unbounded band changes nothing.
+#[test]
+fn test_difficulty() {
+    let dun = Dungeon::from_text("dungeon 1\nmap\n#.###\n<...>\n#.###\n").unwrap();
+    let report = dun.difficulty_report().unwrap();
+    assert_eq!(report.path_length, 4);
+    assert_eq!(report.path_ratio, 5.0 / 7.0);
+    assert_eq!(report.branching, 2.0 / 5.0);
+    assert_eq!(report.chokepoints, 3);
+
+    assert!(DifficultyBand::new().contains(&report));
+    assert!(!DifficultyBand::new()
+        .with_path_length(5..=10)
+        .contains(&report));
+    assert!(!DifficultyBand::new()
+        .with_branching(0.5..=1.0)
+        .contains(&report));
+
+    // An exit which can't be reached has no report, and is never accepted
+    let split = Dungeon::from_text("dungeon 1\nmap\n<#>\n").unwrap();
+    assert!(split.difficulty_report().is_none());
+    assert!(!DifficultyBand::new().accepts(&split));
+
+    let generators: Vec<Box<dyn Generator>> =
+        vec![Box::new(WalkGenerator), Box::new(BspGenerator::default())];
+    let band = DifficultyBand::new().with_path_length(40..=80);
+    for seed in 0..5 {
+        let config = DungeonConfig::new(40, 40).with_seed(seed);
+        let within = config.clone().with_difficulty(band.clone(), 100);
+        let tower = Tower::generate(&within, 3, &generators);
+        assert!(tower.floors().iter().all(|floor| band.accepts(floor)));
+
+        // An unbounded band generates the same towers as no band
+        let unbounded = config.clone().with_difficulty(DifficultyBand::new(), 100);
+        assert!(
+            Tower::generate(&unbounded, 3, &generators) == Tower::generate(&config, 3, &generators)
+        );
+    }
+}
+
+/// Tests entrances and exits on the left and right edges and
+/// away from the edges, and dungeons with several tagged exits,
+/// which can each be reached and survive being saved.
+#[test]
+fn test_exits() {
+    let generators: [&dyn Generator; 3] = [
+        &WalkGenerator,
+        &BspGenerator::default(),
+        &CaveGenerator::default(),
+    ];
+
+    for generator in generators.iter() {
+        for seed in 0..5 {
+            let sideways = generator.generate(
+                &DungeonConfig::new(50, 30)
+                    .with_seed(seed)
+                    .with_entrance(Placement::Edge(Edge::Left)),
+            );
+            assert_eq!((sideways.entrance.0, sideways.exit.0), (0, 49));
+            assert!(is_dungeon_valid(&sideways));
+
+            let inside = generator.generate(
+                &DungeonConfig::new(40, 40)
+                    .with_seed(seed)
+                    .with_entrance(Placement::Interior)
+                    .with_exit(Placement::Edge(Edge::Right)),
+            );
+            let (x, y) = inside.entrance;
+            assert!(x > 0 && y > 0 && x < 39 && y < 39);
+            assert_eq!(inside.exit.0, 39);
+            assert!(is_dungeon_valid(&inside));
+
+            let dun = generator.generate(
+                &DungeonConfig::new(40, 40)
+                    .with_seed(seed)
+                    .with_locks(1)
+                    .with_hazards(10)
+                    .with_exit_destination("depths")
+                    .with_extra_exit(Placement::Edge(Edge::Left), Some("sewers"))
+                    .with_extra_exit(Placement::Random, None),
+            );
+            let exits = dun.exits();
+            assert_eq!(exits.len(), 3);
+            assert_eq!(exits[0].pos, dun.exit);
+            assert_eq!(exits[0].destination.as_deref(), Some("depths"));
+            assert_eq!(
+                (exits[1].pos.0, exits[1].destination.as_deref()),
+                (0, Some("sewers"))
+            );
+            assert_eq!(exits[2].destination, None);
+
+            let reached = distance_field(dun.paths_ref(), dun.entrance);
+            assert!(exits.iter().all(|exit| reached.contains_key(&exit.pos)));
+            assert_eq!(Dungeon::deserialize(&dun.serialize()), Some(dun.clone()));
+            assert_eq!(Dungeon::from_text(&dun.to_text()), Some(dun));
+        }
+    }
+
+    // Exits never share a tile with the entrance or each other,
+    // even when crowded onto the same edge of a small dungeon
+    for seed in 0..40 {
+        let config = DungeonConfig::new(20, 20)
+            .with_seed(seed)
+            .with_entrance(Placement::Edge(Edge::Top))
+            .with_exit(Placement::Edge(Edge::Top))
+            .with_extra_exit(Placement::Edge(Edge::Top), None)
+            .with_extra_exit(Placement::Random, None);
+        let dun = WalkGenerator.generate(&config);
+        let mut taken: Vec<(i32, i32)> = dun.exits().iter().map(|e| e.pos).collect();
+        taken.push(dun.entrance);
+        taken.sort_unstable();
+        taken.dedup();
+        assert_eq!(taken.len(), 4);
+        assert_eq!(Dungeon::from_text(&dun.to_text()), Some(dun));
+    }
+    // A seed whose extra exit was once placed on the entrance
+    let config = DungeonConfig::new(42, 51)
+        .with_seed(13675643547288814971)
+        .with_extra_exit(Placement::Random, None);
+    let dun = WalkGenerator.generate(&config);
+    assert!(!dun.is_exit(dun.entrance));
+    assert_eq!(Dungeon::from_text(&dun.to_text()), Some(dun));
+
+    // Maps with several exits must say which is the main exit
+    assert!(Dungeon::from_text("dungeon 1\nmap\n>.<.>\n").is_none());
+    let text = "dungeon 1\nexit 4 0\nexit 0 0 cellar\nmap\n>.<.>\n";
+    let dun = Dungeon::from_text(text).unwrap();
+    assert_eq!(dun.exit, (4, 0));
+    assert!(dun.is_exit((0, 0)) && !dun.is_exit((2, 0)));
+    assert!(Dungeon::from_text("dungeon 1\nexit 4 0\nmap\n>.<.>\n").is_none());
+}
+
+/// Renders dungeons from every `Generator` to each kind of
+/// image, and tests that each image is the right size, draws
+/// the entrance, exits and actors in their colors, and is
+/// saved with the format its extension names.
+#[test]
+fn test_render() {
+    let generators: [Box<dyn Generator>; 3] = [
+        Box::new(WalkGenerator),
+        Box::new(BspGenerator::default()),
+        Box::new(CaveGenerator::default()),
+    ];
+    let config = DungeonConfig::new(30, 24)
+        .with_seed(9)
+        .with_locks(1)
+        .with_hazards(5)
+        .with_extra_exit(Placement::Interior, None);
+
+    for generator in generators.iter() {
+        let dun = generator.generate(&config);
+        let actor = *dun
+            .paths()
+            .find(|p| **p != dun.entrance && !dun.is_exit(**p))
+            .unwrap();
+        let options = RenderOptions::new().with_scale(3).with_actors(vec![actor]);
+
+        // Each pixel of the PPM image is the color of its tile
+        let ppm = dun.to_ppm(&options);
+        assert!(ppm.lines().all(|line| line.len() <= 70));
+        let values: Vec<u32> = ppm
+            .split_whitespace()
+            .skip(4)
+            .map(|v| v.parse().unwrap())
+            .collect();
+        assert!(ppm.starts_with("P3\n90 72\n255\n"));
+        assert_eq!(values.len(), 90 * 72 * 3);
+        let pixel = |(x, y): (i32, i32)| {
+            let i = (y as usize * 3 * 90 + x as usize * 3) * 3;
+            (values[i], values[i + 1], values[i + 2])
+        };
+        for row in 0..24 {
+            for col in 0..30 {
+                let color = dun.color_at((col, row), &options);
+                assert_eq!(
+                    pixel((col, row)),
+                    (color.0 as u32, color.1 as u32, color.2 as u32)
+                );
             }
-            for p in dun.paths() {
-                if (point.1 == p.1 && (point.0 as i32 - p.0 as i32).abs() == 1)
-                    || (point.0 == p.0 && (point.1 as i32 - p.1 as i32).abs() == 1)
-                {
-                    if !visited.contains(p) {
-                        next.push(*p);
-                        visited.insert(*p);
-                    }
+        }
+        assert_eq!(dun.color_at(dun.entrance, &options), ENTRANCE);
+        assert_eq!(dun.color_at(actor, &options), ACTOR);
+        assert_eq!(dun.exits().len(), 2);
+        for exit in dun.exits() {
+            assert_eq!(dun.color_at(exit.pos, &options), EXIT);
+        }
+        assert_eq!(dun.color_at((-1, -1), &options), WALL);
+
+        let pgm = dun.to_pgm(&options);
+        assert!(pgm.starts_with("P2\n90 72\n255\n"));
+        assert_eq!(pgm.split_whitespace().count(), 4 + 90 * 72);
+
+        let svg = dun.to_svg(&options);
+        assert!(svg.starts_with("<svg") && svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains("width=\"90\" height=\"72\" viewBox=\"0 0 30 24\""));
+        assert!(svg.contains(&format!(
+            "x=\"{}\" y=\"{}\"",
+            dun.entrance.0, dun.entrance.1
+        )));
+        assert_eq!(svg.matches("<circle").count(), 1);
+
+        for (ext, image) in [("svg", &svg), ("ppm", &ppm), ("pgm", &pgm)].iter() {
+            let path = std::env::temp_dir().join(format!("dungeon_generator_test_render.{}", ext));
+            dun.save_image(&path, &options).unwrap();
+            assert_eq!(&std::fs::read_to_string(&path).unwrap(), *image);
+            std::fs::remove_file(&path).unwrap();
+        }
+        assert!(dun.save_image("dungeon.png", &options).is_err());
+    }
+}
+
+/// Tests the biome regions and decorations of a hand-written map, and
+/// that in dungeons from every `Generator` they are seeded, cover the
+/// whole map, keep off the tiles they must, and survive being saved.
+#[test]
+fn test_biomes() {
+    let map = "dungeon 1
+region crypt 1 1
+region sewer 4 2
+decoration torch 0 2
+decoration puddle 4 2
+map
+#######
+#<...##
+#.....>
+#######
+";
+    let dun = Dungeon::from_text(map).unwrap();
+    assert_eq!(dun.biome_at((2, 1)), Some(Biome::Crypt));
+    assert_eq!(dun.biome_at((5, 2)), Some(Biome::Sewer));
+    // Ties go to the first region
+    assert_eq!(dun.biome_at((3, 0)), Some(Biome::Crypt));
+    assert_eq!(dun.decoration_at((0, 2)), Some(Decoration::Torch));
+    assert_eq!(dun.decoration_at((1, 2)), None);
+    assert_eq!(Dungeon::deserialize(&dun.serialize()), Some(dun.clone()));
+    assert_eq!(Dungeon::from_text(&dun.to_text()), Some(dun.clone()));
+    assert!(Dungeon::from_text(&map.replace("crypt", "swamp")).is_none());
+
+    // Changing a tile clears its decoration
+    let mut collapsed = dun.clone();
+    collapsed
+        .collapse(&[(5, 1)], Connectivity::Override)
+        .unwrap();
+    assert_eq!(collapsed.decoration_at((5, 1)), None);
+    assert_eq!(collapsed.decoration_at((0, 2)), Some(Decoration::Torch));
+
+    let generators: [&dyn Generator; 3] = [
+        &WalkGenerator,
+        &BspGenerator::default(),
+        &CaveGenerator::default(),
+    ];
+    for generator in generators.iter() {
+        for seed in 0..5 {
+            let config = DungeonConfig::new(40, 40)
+                .with_seed(seed)
+                .with_locks(2)
+                .with_hazards(10);
+            let plain = generator.generate(&config);
+            let config = config.with_biomes(4).with_decorations(30);
+            let dun = generator.generate(&config);
+            assert!(plain.regions().is_empty() && plain.biome_at((0, 0)).is_none());
+            assert_eq!(dun, generator.generate(&config));
+            assert_eq!(dun.paths_ref(), plain.paths_ref());
+            assert_eq!(dun.tiles(), plain.tiles());
+
+            assert_eq!(dun.regions().len(), 4);
+            assert!(dun.paths().all(|p| dun.biome_at(*p).is_some()));
+            assert!(!dun.decorations().is_empty() && dun.decorations().len() <= 30);
+            for (point, decoration) in dun.decorations() {
+                let biome = dun.biome_at(*point).unwrap();
+                assert!(biome.decorations().contains(decoration));
+                if decoration.on_wall() {
+                    assert_eq!(dun.tile_at(*point), Tile::Wall);
+                } else {
+                    assert_eq!(dun.tile_at(*point), Tile::Floor);
+                    assert!(*point != dun.entrance && !dun.is_exit(*point));
+                    assert!(!dun.locks().iter().any(|l| l.key == *point));
                 }
             }
+            assert_eq!(Dungeon::deserialize(&dun.serialize()), Some(dun.clone()));
+            assert_eq!(Dungeon::from_text(&dun.to_text()), Some(dun));
         }
-
-        return false;
     }
+}
 
-    for i in (20..75).step_by(2) {
-        assert_eq!(is_dungeon_valid(Dungeon::new(i, i)), true);
+/// Tests that the wave function collapse `Generator` learns patterns
+/// from a sample map, generates solvable dungeons from the same seeds
+/// made mostly of those patterns, and still joins the entrance to the
+/// exit when the sample is too small to learn from.
+#[test]
+fn test_wfc() {
+    let generator = WfcGenerator::default();
+    assert!(generator.pattern_count() > 0);
+    assert!(WfcGenerator::from_text("dungeon 1\nmap\n<?>\n").is_none());
+
+    for seed in 0..3 {
+        let config = DungeonConfig::new(30, 30).with_seed(seed);
+        let dun = generator.generate(&config);
+        assert!(is_dungeon_valid(&dun));
+        assert!(dun == generator.generate(&config));
+
+        // Away from the border, almost every window is drawn from the sample
+        let windows: Vec<(i32, i32)> = (1..26).flat_map(|y| (1..26).map(move |x| (x, y))).collect();
+        let matching = windows
+            .iter()
+            .filter(|p| generator.matches_sample(&dun, **p))
+            .count();
+        assert!(matching * 10 >= windows.len() * 9);
+
+        let config = config
+            .with_extra_exit(Placement::Edge(Edge::Left), Some("cellar"))
+            .with_loops(2)
+            .with_locks(2)
+            .with_hazards(10)
+            .with_biomes(3)
+            .with_decorations(20);
+        let dun = generator.generate(&config);
+        assert!(dun.is_solvable());
+        for exit in dun.exits() {
+            assert!(distance_field(dun.paths_ref(), dun.entrance).contains_key(&exit.pos));
+        }
+        assert_eq!(Dungeon::deserialize(&dun.serialize()), Some(dun.clone()));
     }
+
+    // A sample smaller than a pattern leaves only a corridor
+    let tiny = WfcGenerator::from_text("dungeon 1\nmap\n<>\n").unwrap();
+    assert_eq!(tiny.pattern_count(), 0);
+    let dun = tiny.generate(&DungeonConfig::new(20, 20).with_seed(1));
+    assert!(is_dungeon_valid(&dun));
 }
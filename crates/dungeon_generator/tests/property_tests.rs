@@ -0,0 +1,153 @@
+//! Property-based tests for the DungeonGenerator
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+//!
+//! Each test generates dungeons from random seeds and configs,
+//! and checks an invariant every generated dungeon must hold.
+//! When one fails, proptest shrinks the seed and config down to
+//! the simplest which still fails, and prints them.
+
+use dungeon_generator::{
+    analysis::regions,
+    config::{DungeonConfig, Edge, Placement},
+    generators::{bsp::BspGenerator, caves::CaveGenerator, walk::WalkGenerator, wfc::WfcGenerator},
+    inst::Dungeon,
+    traits::Generator,
+    vaults::Vault,
+};
+use proptest::prelude::*;
+use simple_serializer::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// A small vault stamped into some of the dungeons
+const VAULT: &str = "
+###+###
+#.....#
+#.M.$.#
+#######
+";
+
+/// Generates the `Dungeon` for `config` with the `Generator` called `style`
+fn generate(style: &str, config: &DungeonConfig) -> Dungeon {
+    match style {
+        "bsp" => BspGenerator::default().generate(config),
+        "caves" => CaveGenerator::default().generate(config),
+        "wfc" => {
+            static WFC: OnceLock<WfcGenerator> = OnceLock::new();
+            WFC.get_or_init(WfcGenerator::default).generate(config)
+        }
+        _ => WalkGenerator.generate(config),
+    }
+}
+
+/// Any rule for placing an entrance or exit, other than an exact position
+fn placement() -> impl Strategy<Value = Placement> {
+    prop_oneof![
+        Just(Placement::Random),
+        Just(Placement::Interior),
+        prop::sample::select(vec![Edge::Top, Edge::Bottom, Edge::Left, Edge::Right])
+            .prop_map(Placement::Edge),
+    ]
+}
+
+/// A seeded `DungeonConfig` of any size from 20 to 60 tiles
+/// across, with any placement rules and features
+fn config() -> impl Strategy<Value = DungeonConfig> {
+    (
+        any::<u64>(),
+        (20u32..=60, 20u32..=60),
+        (
+            placement(),
+            placement(),
+            prop::collection::vec(placement(), 0..=2),
+        ),
+        (0u32..=4, 0u32..=2, 0u32..=2, 0u32..=20),
+    )
+        .prop_map(
+            |(seed, (w, h), (entrance, exit, extra), (loops, locks, vaults, hazards))| {
+                let mut config = DungeonConfig::new(w, h)
+                    .with_seed(seed)
+                    .with_entrance(entrance)
+                    .with_exit(exit)
+                    .with_loops(loops)
+                    .with_locks(locks)
+                    .with_vaults(vec![Vault::parse("closet", VAULT).unwrap()], vaults)
+                    .with_hazards(hazards);
+                for placement in extra {
+                    config = config.with_extra_exit(placement, None);
+                }
+                config
+            },
+        )
+}
+
+/// The name of any `Generator`
+fn style() -> impl Strategy<Value = &'static str> {
+    prop::sample::select(vec!["walk", "bsp", "caves", "wfc"])
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(48))]
+
+    /// Tests that every tile of the paths lies within the
+    /// width and height of the dungeon
+    #[test]
+    fn prop_in_bounds(style in style(), config in config()) {
+        let dun = generate(style, &config);
+        prop_assert_eq!((dun.width(), dun.height()), (config.width(), config.height()));
+        for (x, y) in dun.paths() {
+            prop_assert!(
+                (0..dun.width() as i32).contains(x) && (0..dun.height() as i32).contains(y),
+                "seed {}: tile ({}, {}) out of bounds", dun.seed(), x, y
+            );
+        }
+    }
+
+    /// Tests that the entrance and every exit lie on the paths
+    #[test]
+    fn prop_on_paths(style in style(), config in config()) {
+        let dun = generate(style, &config);
+        prop_assert!(dun.paths_ref().contains(&dun.entrance), "seed {}: entrance off the paths", dun.seed());
+        prop_assert_eq!(dun.exits().len(), 1 + config.extra_exits().len());
+        for exit in dun.exits() {
+            prop_assert!(
+                dun.paths_ref().contains(&exit.pos),
+                "seed {}: exit {:?} off the paths", dun.seed(), exit.pos
+            );
+        }
+    }
+
+    /// Tests that the paths form a single connected region
+    #[test]
+    fn prop_connected(style in style(), config in config()) {
+        let dun = generate(style, &config);
+        let sizes: Vec<usize> = regions(dun.paths_ref()).iter().map(|r| r.len()).collect();
+        prop_assert_eq!(sizes.len(), 1, "seed {}: regions of {:?} tiles", dun.seed(), sizes);
+    }
+
+    /// Tests that every dungeon is read back identically from
+    /// both its serialization and the text file format
+    #[test]
+    fn prop_round_trip(style in style(), config in config()) {
+        let dun = generate(style, &config);
+        prop_assert!(
+            Dungeon::deserialize(&dun.serialize()) == Some(dun.clone()),
+            "seed {}: serialization not read back", dun.seed()
+        );
+        prop_assert!(
+            Dungeon::from_text(&dun.to_text()) == Some(dun.clone()),
+            "seed {}: text file not read back", dun.seed()
+        );
+    }
+
+    /// Tests that generating from the same seed and config
+    /// always gives an identical dungeon
+    #[test]
+    fn prop_deterministic(style in style(), config in config()) {
+        let dun = generate(style, &config);
+        let other = generate(style, &config);
+        prop_assert!(dun == other, "seed {}: generated differently", dun.seed());
+        prop_assert_eq!(dun.serialize(), other.serialize());
+    }
+}
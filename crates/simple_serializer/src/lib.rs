@@ -1,3 +1,119 @@
+//! Simple Serializer
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+//!
+//! Values are written as segments of text joined by `::`. Wire types
+//! can write their `Serialize` and `Deserialize` by hand, or derive
+//! both with `#[derive(Serialize, Deserialize)]` - see the
+//! `simple_serializer_derive` crate for the attributes it accepts.
+//! Derived types also implement `TryDeserialize`, which reports
+//! which field of a malformed message failed, and why.
+//!
+//! Alongside the text format is a compact binary one, derived with
+//! `#[derive(Encode, Decode)]`. Integers are written as varints - zigzag
+//! encoded when signed - strings are prefixed by their length, and enums
+//! by the index of their variant as a varint.
+//!
+//! Types whose format changes between versions of a protocol derive
+//! `Versioned`, which writes a value as an older version would have,
+//! leaving out the fields and variants which came after it.
+//!
+//! Lists (`Vec`) and pairs are written by the traits of their items,
+//! so they can be the fields of derived types too.
+
+use std::{convert::TryFrom, fmt::Display};
+
+pub use simple_serializer_derive::{Decode, Deserialize, Encode, Serialize, Versioned};
+
+/// The delimiter between the segments of a serialized value
+pub const DELIMITER: &str = "::";
+
+/// The character which starts an escape sequence in a string field
+pub const ESCAPE: char = '\\';
+
+///
+/// Escapes `value` so it can be written as a single segment,
+/// whatever it contains. Backslashes, colons, newlines and
+/// carriage returns are written as `\\`, `\:`, `\n` and `\r`,
+/// so an escaped value never holds a delimiter or line break.
+///
+pub fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ':' => escaped.push_str("\\:"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+///
+/// Escapes `value` as `escape` does, so that it can also be written
+/// as a single word, between others split apart by whitespace.
+/// Spaces are written as `\s`, tabs as `\t` and any other whitespace
+/// as `\u{..}` with its code point, and an empty value as `\e`, so
+/// `guard room` is written `guard\sroom`.
+///
+pub fn escape_word(value: &str) -> String {
+    if value.is_empty() {
+        return "\\e".to_string();
+    }
+    let mut word = String::with_capacity(value.len());
+    for c in escape(value).chars() {
+        match c {
+            ' ' => word.push_str("\\s"),
+            '\t' => word.push_str("\\t"),
+            c if c.is_whitespace() => word.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => word.push(c),
+        }
+    }
+    word
+}
+
+///
+/// Reverses `escape` and `escape_word`, failing if `value` holds
+/// an escape sequence neither of them writes, or ends partway
+/// through one.
+///
+pub fn unescape(value: &str) -> Result<String, DeserializeError> {
+    let malformed = || DeserializeError::new(ErrorKind::Malformed(value.to_string()));
+    if value == "\\e" {
+        return Ok(String::new());
+    }
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != ESCAPE {
+            unescaped.push(c);
+            continue;
+        }
+        unescaped.push(match chars.next().ok_or_else(malformed)? {
+            '\\' => '\\',
+            ':' => ':',
+            'n' => '\n',
+            'r' => '\r',
+            's' => ' ',
+            't' => '\t',
+            'u' if chars.next() == Some('{') => {
+                let rest = chars.as_str();
+                let end = rest.find('}').ok_or_else(malformed)?;
+                chars = rest[end + 1..].chars();
+                u32::from_str_radix(&rest[..end], 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(malformed)?
+            }
+            _ => return Err(malformed()),
+        });
+    }
+    Ok(unescaped)
+}
+
 ///
 /// A trait that requires that a particular struct
 /// serializes to a generic type.
@@ -15,3 +131,671 @@ pub trait Deserialize {
     type DeserializeTo;
     fn deserialize(from: &str) -> Self::DeserializeTo;
 }
+
+///
+/// A trait for types which can be deserialized from an &str,
+/// reporting why when they can't be.
+///
+pub trait TryDeserialize: Sized {
+    fn try_deserialize(from: &str) -> Result<Self, DeserializeError>;
+}
+
+///
+/// Why a field couldn't be deserialized
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The message ended before the field
+    Missing,
+    /// The field's segment couldn't be parsed
+    Malformed(String),
+    /// The segment didn't name any variant
+    UnknownTag(String),
+}
+
+///
+/// The error returned when a message can't be deserialized,
+/// holding the field that failed and why it failed.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeserializeError {
+    /// The path to the field, outermost first - each the
+    /// name or index of a field, or the tag of a variant
+    pub path: Vec<String>,
+    pub kind: ErrorKind,
+}
+
+impl DeserializeError {
+    /// Creates a new `DeserializeError` for the current field
+    pub fn new(kind: ErrorKind) -> Self {
+        Self {
+            path: Vec::new(),
+            kind,
+        }
+    }
+    /// Places the failed field within the field or variant `name`
+    pub fn within(mut self, name: &str) -> Self {
+        self.path.insert(0, name.to_string());
+        self
+    }
+    /// The path to the failed field, joined by `.`
+    pub fn field(&self) -> String {
+        self.path.join(".")
+    }
+}
+
+impl Display for DeserializeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let field = if self.path.is_empty() {
+            "message".to_string()
+        } else {
+            format!("field `{}`", self.field())
+        };
+        match &self.kind {
+            ErrorKind::Missing => write!(f, "{} is missing", field),
+            ErrorKind::Malformed(seg) => write!(f, "{} is malformed: `{}`", field, seg),
+            ErrorKind::UnknownTag(tag) => write!(f, "{} has unknown tag `{}`", field, tag),
+        }
+    }
+}
+
+impl std::error::Error for DeserializeError {}
+
+///
+/// Writes a value as one or more segments of a larger
+/// serialized value. Derived alongside `Serialize`, so
+/// that derived types can be the fields of others.
+///
+pub trait SerializeSegments {
+    fn serialize_segments(&self, segs: &mut Vec<String>);
+}
+
+///
+/// Reads a value back from the segments `SerializeSegments`
+/// wrote it as, failing if they are missing or malformed.
+/// Derived alongside `Deserialize`.
+///
+pub trait DeserializeSegments: Sized {
+    fn deserialize_segments(segs: &mut Segments) -> Result<Self, DeserializeError>;
+}
+
+///
+/// The segments of a serialized value, read one at a time
+///
+#[derive(Debug, Clone)]
+pub struct Segments<'a> {
+    rest: Option<&'a str>,
+}
+
+impl<'a> Segments<'a> {
+    /// Splits `from` into its segments
+    pub fn new(from: &'a str) -> Self {
+        Self { rest: Some(from) }
+    }
+    /// Takes the next segment, failing if there are none left
+    pub fn field(&mut self) -> Result<&'a str, DeserializeError> {
+        self.next()
+            .ok_or_else(|| DeserializeError::new(ErrorKind::Missing))
+    }
+    /// Takes everything left unread, delimiters and all, as one segment
+    pub fn rest(&mut self) -> &'a str {
+        self.rest.take().unwrap_or("")
+    }
+    /// Tests if every segment has been read
+    pub fn is_empty(&self) -> bool {
+        self.rest.is_none()
+    }
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let rest = self.rest?;
+        // Find the first delimiter, skipping over escaped characters
+        let bytes = rest.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == ESCAPE as u8 {
+                i += 2;
+            } else if bytes[i..].starts_with(DELIMITER.as_bytes()) {
+                self.rest = Some(&rest[i + DELIMITER.len()..]);
+                return Some(&rest[..i]);
+            } else {
+                i += 1;
+            }
+        }
+        self.rest.take()
+    }
+}
+
+///
+/// Implements the segment traits for types which
+/// are written as a single segment by `Display`, and
+/// read back by `FromStr`, ignoring surrounding whitespace.
+///
+macro_rules! impl_segments {
+    ($($t:ty),*) => {
+        $(
+            impl SerializeSegments for $t {
+                fn serialize_segments(&self, segs: &mut Vec<String>) {
+                    segs.push(self.to_string());
+                }
+            }
+            impl DeserializeSegments for $t {
+                fn deserialize_segments(segs: &mut Segments) -> Result<Self, DeserializeError> {
+                    let seg = segs.field()?;
+                    seg.trim()
+                        .parse()
+                        .map_err(|_| DeserializeError::new(ErrorKind::Malformed(seg.to_string())))
+                }
+            }
+        )*
+    };
+}
+
+impl_segments!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize, f32, f64, bool);
+
+///
+/// Strings are escaped, so they can hold anything -
+/// delimiters included - and still be a single segment.
+///
+impl SerializeSegments for String {
+    fn serialize_segments(&self, segs: &mut Vec<String>) {
+        segs.push(escape(self));
+    }
+}
+
+impl DeserializeSegments for String {
+    fn deserialize_segments(segs: &mut Segments) -> Result<Self, DeserializeError> {
+        unescape(segs.field()?)
+    }
+}
+
+///
+/// Lists are written as their length, followed by each item.
+/// Errors name the index of the item which failed.
+///
+impl<T: SerializeSegments> SerializeSegments for Vec<T> {
+    fn serialize_segments(&self, segs: &mut Vec<String>) {
+        self.len().serialize_segments(segs);
+        for item in self.iter() {
+            item.serialize_segments(segs);
+        }
+    }
+}
+
+impl<T: DeserializeSegments> DeserializeSegments for Vec<T> {
+    fn deserialize_segments(segs: &mut Segments) -> Result<Self, DeserializeError> {
+        let len = usize::deserialize_segments(segs)?;
+        (0..len)
+            .map(|i| T::deserialize_segments(segs).map_err(|e| e.within(&i.to_string())))
+            .collect()
+    }
+}
+
+///
+/// Pairs, such as points, are written as their first
+/// value followed by their second.
+///
+impl<A: SerializeSegments, B: SerializeSegments> SerializeSegments for (A, B) {
+    fn serialize_segments(&self, segs: &mut Vec<String>) {
+        self.0.serialize_segments(segs);
+        self.1.serialize_segments(segs);
+    }
+}
+
+impl<A: DeserializeSegments, B: DeserializeSegments> DeserializeSegments for (A, B) {
+    fn deserialize_segments(segs: &mut Segments) -> Result<Self, DeserializeError> {
+        let first = A::deserialize_segments(segs).map_err(|e| e.within("0"))?;
+        let second = B::deserialize_segments(segs).map_err(|e| e.within("1"))?;
+        Ok((first, second))
+    }
+}
+
+///
+/// Boxes are written as the value they hold, so large
+/// fields can be boxed without changing the format.
+///
+impl<T: SerializeSegments> SerializeSegments for Box<T> {
+    fn serialize_segments(&self, segs: &mut Vec<String>) {
+        (**self).serialize_segments(segs);
+    }
+}
+
+impl<T: DeserializeSegments> DeserializeSegments for Box<T> {
+    fn deserialize_segments(segs: &mut Segments) -> Result<Self, DeserializeError> {
+        T::deserialize_segments(segs).map(Box::new)
+    }
+}
+
+///
+/// A field which takes up the rest of a message - usually a
+/// message of its own - and so is written as it is, with no
+/// delimiter, length or escaping, in either format.
+///
+pub trait Rest: Sized {
+    fn rest_text(&self) -> String;
+    fn from_rest_text(rest: &str) -> Self;
+    fn rest_bytes(&self) -> &[u8];
+    fn from_rest_bytes(rest: &[u8]) -> Result<Self, DeserializeError>;
+}
+
+impl Rest for String {
+    fn rest_text(&self) -> String {
+        self.clone()
+    }
+    fn from_rest_text(rest: &str) -> Self {
+        rest.to_string()
+    }
+    fn rest_bytes(&self) -> &[u8] {
+        self.as_bytes()
+    }
+    fn from_rest_bytes(rest: &[u8]) -> Result<Self, DeserializeError> {
+        String::from_utf8(rest.to_vec()).map_err(|_| malformed_bytes(rest))
+    }
+}
+
+///
+/// Writes a value in the binary format
+///
+pub trait Encode {
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Encodes the value on its own
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+        out
+    }
+}
+
+///
+/// Reads a value back from the binary format, failing if
+/// the bytes run out or are malformed
+///
+pub trait Decode: Sized {
+    fn decode(bytes: &mut Bytes) -> Result<Self, DeserializeError>;
+
+    /// Decodes a value from the start of `bytes`,
+    /// ignoring anything left over
+    fn from_bytes(bytes: &[u8]) -> Result<Self, DeserializeError> {
+        Self::decode(&mut Bytes::new(bytes))
+    }
+}
+
+///
+/// The bytes of an encoded value, read from the front
+///
+#[derive(Debug, Clone)]
+pub struct Bytes<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Bytes<'a> {
+    /// Reads from the start of `bytes`
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+    /// Takes the next `n` bytes, failing if there aren't that many left
+    pub fn take(&mut self, n: usize) -> Result<&'a [u8], DeserializeError> {
+        if n > self.bytes.len() {
+            return Err(DeserializeError::new(ErrorKind::Missing));
+        }
+        let (taken, bytes) = self.bytes.split_at(n);
+        self.bytes = bytes;
+        Ok(taken)
+    }
+    /// Takes the next varint - seven bits to a byte, least
+    /// significant first, with the top bit set on all but the last
+    pub fn varint(&mut self) -> Result<u64, DeserializeError> {
+        let mut value = 0u64;
+        for i in 0..10 {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7f) as u64) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(DeserializeError::new(ErrorKind::Malformed(
+            "varint longer than 10 bytes".to_string(),
+        )))
+    }
+    /// Takes everything left unread
+    pub fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.bytes)
+    }
+    /// Tests if every byte has been read
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+/// Writes `value` to `out` as a varint
+pub fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// The error for `bytes` which couldn't be decoded
+fn malformed_bytes(bytes: &[u8]) -> DeserializeError {
+    DeserializeError::new(ErrorKind::Malformed(format!("{:02x?}", bytes)))
+}
+
+///
+/// Implements the binary traits for unsigned integers,
+/// which are written as varints.
+///
+macro_rules! impl_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl Encode for $t {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    write_varint(*self as u64, out);
+                }
+            }
+            impl Decode for $t {
+                fn decode(bytes: &mut Bytes) -> Result<Self, DeserializeError> {
+                    let value = bytes.varint()?;
+                    <$t>::try_from(value)
+                        .map_err(|_| DeserializeError::new(ErrorKind::Malformed(value.to_string())))
+                }
+            }
+        )*
+    };
+}
+
+///
+/// Implements the binary traits for signed integers, which
+/// are zigzag encoded - 0, -1, 1, -2, ... as 0, 1, 2, 3, ... -
+/// so that small negative numbers are written as small varints.
+///
+macro_rules! impl_signed {
+    ($($t:ty),*) => {
+        $(
+            impl Encode for $t {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    let value = *self as i64;
+                    write_varint(((value << 1) ^ (value >> 63)) as u64, out);
+                }
+            }
+            impl Decode for $t {
+                fn decode(bytes: &mut Bytes) -> Result<Self, DeserializeError> {
+                    let zigzag = bytes.varint()?;
+                    let value = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+                    <$t>::try_from(value)
+                        .map_err(|_| DeserializeError::new(ErrorKind::Malformed(value.to_string())))
+                }
+            }
+        )*
+    };
+}
+
+impl_unsigned!(u8, u16, u32, u64, usize);
+impl_signed!(i8, i16, i32, i64, isize);
+
+impl Encode for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.push(*self as u8);
+    }
+}
+
+impl Decode for bool {
+    fn decode(bytes: &mut Bytes) -> Result<Self, DeserializeError> {
+        match bytes.take(1)? {
+            [0] => Ok(false),
+            [1] => Ok(true),
+            other => Err(malformed_bytes(other)),
+        }
+    }
+}
+
+impl Encode for f32 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decode for f32 {
+    fn decode(bytes: &mut Bytes) -> Result<Self, DeserializeError> {
+        let mut le = [0; 4];
+        le.copy_from_slice(bytes.take(4)?);
+        Ok(f32::from_le_bytes(le))
+    }
+}
+
+impl Encode for f64 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.to_le_bytes());
+    }
+}
+
+impl Decode for f64 {
+    fn decode(bytes: &mut Bytes) -> Result<Self, DeserializeError> {
+        let mut le = [0; 8];
+        le.copy_from_slice(bytes.take(8)?);
+        Ok(f64::from_le_bytes(le))
+    }
+}
+
+///
+/// Strings are prefixed by their length in bytes, so
+/// need no escaping in the binary format.
+///
+impl Encode for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(self.len() as u64, out);
+        out.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl Decode for String {
+    fn decode(bytes: &mut Bytes) -> Result<Self, DeserializeError> {
+        let len = usize::decode(bytes)?;
+        let utf8 = bytes.take(len)?;
+        String::from_utf8(utf8.to_vec()).map_err(|_| malformed_bytes(utf8))
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        write_varint(self.len() as u64, out);
+        for item in self.iter() {
+            item.encode(out);
+        }
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(bytes: &mut Bytes) -> Result<Self, DeserializeError> {
+        let len = usize::decode(bytes)?;
+        (0..len)
+            .map(|i| T::decode(bytes).map_err(|e| e.within(&i.to_string())))
+            .collect()
+    }
+}
+
+impl<T: Encode> Encode for Box<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (**self).encode(out);
+    }
+}
+
+impl<T: Decode> Decode for Box<T> {
+    fn decode(bytes: &mut Bytes) -> Result<Self, DeserializeError> {
+        T::decode(bytes).map(Box::new)
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(bytes: &mut Bytes) -> Result<Self, DeserializeError> {
+        let first = A::decode(bytes).map_err(|e| e.within("0"))?;
+        let second = B::decode(bytes).map_err(|e| e.within("1"))?;
+        Ok((first, second))
+    }
+}
+
+///
+/// Makes a fieldless enum a wire type, written as the number of
+/// its variant - its place in the enum - in either format. Every
+/// variant must be listed, in the order they are declared:
+///
+/// ```
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Tile { Floor, Wall }
+/// simple_serializer::numbered_enum!(Tile { Floor, Wall });
+///
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// enum Kind { Start, Exit }
+/// simple_serializer::numbered_enum!(Kind { Start => "start", Exit => "exit" });
+///
+/// assert_eq!(Tile::Wall.to_string(), "1");
+/// assert_eq!(Tile::from_u32(0), Some(Tile::Floor));
+/// assert_eq!(Kind::from_name("exit"), Some(Kind::Exit));
+/// ```
+///
+/// This implements `Display` as the number, the segment and binary
+/// traits, `ALL` - every variant, in order - and `from_u32`. Variants
+/// given names also get `name` and `from_name`.
+///
+#[macro_export]
+macro_rules! numbered_enum {
+    ($t:ident { $($v:ident),* $(,)? }) => {
+        impl $t {
+            /// Every variant, in the order of their serialized numbers
+            pub const ALL: [$t; [$($t::$v),*].len()] = [$($t::$v),*];
+
+            /// Converts a serialized number back into
+            /// a variant, if it is valid.
+            pub fn from_u32(n: u32) -> Option<Self> {
+                Self::ALL.get(n as usize).cloned()
+            }
+        }
+
+        // Fails to compile if a variant is left out of the list,
+        // or listed out of the order it was declared in
+        const _: () = {
+            const fn listed(value: $t) {
+                match value {
+                    $($t::$v)|* => {}
+                }
+            }
+            let mut i = 0;
+            while i < $t::ALL.len() {
+                listed($t::ALL[i]);
+                assert!($t::ALL[i] as usize == i);
+                i += 1;
+            }
+        };
+
+        impl ::std::fmt::Display for $t {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                write!(f, "{}", *self as u32)
+            }
+        }
+
+        impl $crate::SerializeSegments for $t {
+            fn serialize_segments(&self, segs: &mut Vec<String>) {
+                segs.push(self.to_string());
+            }
+        }
+
+        impl $crate::DeserializeSegments for $t {
+            fn deserialize_segments(
+                segs: &mut $crate::Segments,
+            ) -> Result<Self, $crate::DeserializeError> {
+                let n = <u32 as $crate::DeserializeSegments>::deserialize_segments(segs)?;
+                $t::from_u32(n).ok_or_else(|| {
+                    $crate::DeserializeError::new($crate::ErrorKind::Malformed(n.to_string()))
+                })
+            }
+        }
+
+        impl $crate::Encode for $t {
+            fn encode(&self, out: &mut Vec<u8>) {
+                $crate::Encode::encode(&(*self as u32), out);
+            }
+        }
+
+        impl $crate::Decode for $t {
+            fn decode(bytes: &mut $crate::Bytes) -> Result<Self, $crate::DeserializeError> {
+                let n = <u32 as $crate::Decode>::decode(bytes)?;
+                $t::from_u32(n).ok_or_else(|| {
+                    $crate::DeserializeError::new($crate::ErrorKind::Malformed(n.to_string()))
+                })
+            }
+        }
+    };
+    ($t:ident { $($v:ident => $name:literal),* $(,)? }) => {
+        $crate::numbered_enum!($t { $($v),* });
+
+        impl $t {
+            /// The lowercase name of the variant
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $($t::$v => $name,)*
+                }
+            }
+            /// Converts the lowercase `name` of a variant
+            /// back into the variant, if it is valid.
+            pub fn from_name(name: &str) -> Option<Self> {
+                Self::ALL.iter().find(|v| v.name() == name).cloned()
+            }
+        }
+    };
+}
+
+///
+/// A type whose format depends on the version of the protocol it is
+/// written for. Fields and variants marked `#[serialize(since = N)]`
+/// are only written for version `N` onwards. Versions start at 1.
+///
+pub trait Versioned {
+    /// The first version the value can be written in
+    fn since(&self) -> u32;
+    fn serialize_segments_for(&self, version: u32, segs: &mut Vec<String>);
+    fn encode_for(&self, version: u32, out: &mut Vec<u8>);
+
+    /// Serializes the value as `version` would, or `None` if it
+    /// didn't exist yet
+    fn serialize_for(&self, version: u32) -> Option<String> {
+        if self.since() > version {
+            return None;
+        }
+        let mut segs = Vec::new();
+        self.serialize_segments_for(version, &mut segs);
+        Some(segs.join(DELIMITER))
+    }
+    /// Encodes the value as `version` would, or `None` if it
+    /// didn't exist yet
+    fn to_bytes_for(&self, version: u32) -> Option<Vec<u8>> {
+        if self.since() > version {
+            return None;
+        }
+        let mut out = Vec::new();
+        self.encode_for(version, &mut out);
+        Some(out)
+    }
+}
+
+impl<T: Versioned> Versioned for Box<T> {
+    fn since(&self) -> u32 {
+        (**self).since()
+    }
+    fn serialize_segments_for(&self, version: u32, segs: &mut Vec<String>) {
+        (**self).serialize_segments_for(version, segs);
+    }
+    fn encode_for(&self, version: u32, out: &mut Vec<u8>) {
+        (**self).encode_for(version, out);
+    }
+}
@@ -0,0 +1,366 @@
+use simple_serializer::{
+    escape, escape_word, unescape, Decode, Deserialize, DeserializeError, Encode, ErrorKind,
+    Segments, Serialize, TryDeserialize, Versioned, DELIMITER,
+};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Encode, Decode)]
+struct Point(i32, i32);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Encode, Decode)]
+struct Named {
+    id: u32,
+    pos: Point,
+    name: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Encode, Decode)]
+struct Route {
+    points: Vec<(i32, u32)>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Encode, Decode)]
+enum Message {
+    Hello(String),
+    #[serialize(tag = "MV")]
+    Moved(u32, Point),
+    Data(u32, #[serialize(rest)] String),
+    Quit,
+    #[serialize(trailing)]
+    Done,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Encode, Decode)]
+#[serialize(bytes)]
+enum Packet {
+    Ping,
+    Ack(u64),
+    #[serialize(other)]
+    Bad,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Encode, Decode, Versioned)]
+enum Versions {
+    Join(
+        String,
+        #[serialize(since = 2)] u32,
+        #[serialize(since = 3)] Vec<String>,
+    ),
+    #[serialize(since = 2)]
+    Leave(u32),
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Encode, Decode, Versioned)]
+struct Area {
+    width: u32,
+    #[serialize(since = 2)]
+    height: u32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Encode, Decode, Versioned)]
+enum Nested {
+    Map(u32, #[serialize(versioned)] Box<Area>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shade {
+    Light,
+    Dark,
+}
+
+simple_serializer::numbered_enum!(Shade {
+    Light => "light",
+    Dark => "dark",
+});
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, Encode, Decode)]
+struct Shaded(Point, Shade);
+
+#[test]
+fn test_segments() {
+    let mut segs = Segments::new("a::b::::c::d");
+    assert_eq!(segs.next(), Some("a"));
+    assert_eq!(segs.next(), Some("b"));
+    assert_eq!(segs.next(), Some(""));
+    assert_eq!(segs.rest(), "c::d");
+    assert_eq!(segs.next(), None);
+    assert_eq!(segs.rest(), "");
+}
+
+#[test]
+fn test_derive_struct() {
+    let named = Named {
+        id: 7,
+        pos: Point(-1, 2),
+        name: "Sam".to_string(),
+    };
+    assert_eq!(named.serialize(), "7::-1::2::Sam");
+    assert_eq!(Named::deserialize(&named.serialize()), Some(named));
+    assert_eq!(Point::deserialize(" 3 :: 4 ::extra"), Some(Point(3, 4)));
+    assert_eq!(Point::deserialize("3"), None);
+    assert_eq!(Point::deserialize("3::x"), None);
+
+    // Pairs are written first value, then second
+    let route = Route {
+        points: vec![(-1, 2), (3, 4)],
+    };
+    assert_eq!(route.serialize(), "2::-1::2::3::4");
+    assert_eq!(Route::deserialize(&route.serialize()), Some(route));
+    assert_eq!(
+        Route::try_deserialize("1::-1::x").unwrap_err().field(),
+        "points.0.1"
+    );
+}
+
+#[test]
+fn test_derive_enum() {
+    let messages = [
+        (Message::Hello("Sam".to_string()), "Hello::Sam"),
+        (Message::Moved(2, Point(3, 4)), "MV::2::3::4"),
+        (Message::Data(1, "a::b".to_string()), "Data::1::a::b"),
+        (Message::Data(1, "".to_string()), "Data::1::"),
+        (Message::Quit, "Quit"),
+        (Message::Done, "Done::"),
+    ];
+    for (message, text) in messages {
+        assert_eq!(message.serialize(), text);
+        assert_eq!(Message::deserialize(text), Some(message));
+    }
+    // Tags replace variant names, rather than adding to them
+    assert_eq!(Message::deserialize("Moved::2::3::4"), None);
+    assert_eq!(Message::deserialize("Hello"), None);
+    assert_eq!(Message::deserialize(""), None);
+    // Trailing empty segments are optional when reading
+    assert_eq!(Message::deserialize("Done"), Some(Message::Done));
+    assert_eq!(Message::deserialize("Quit::"), Some(Message::Quit));
+
+    assert_eq!(Packet::Ack(5).serialize(), b"Ack::5".to_vec());
+    assert_eq!(Packet::deserialize("Ack::5"), Packet::Ack(5));
+    assert_eq!(Packet::deserialize("Ack::five"), Packet::Bad);
+    assert_eq!(Packet::deserialize("Pong"), Packet::Bad);
+    assert_eq!(Packet::deserialize("Bad"), Packet::Bad);
+}
+
+#[test]
+fn test_try_deserialize() {
+    let error = |path: &[&str], kind| DeserializeError {
+        path: path.iter().map(|p| p.to_string()).collect(),
+        kind,
+    };
+    assert_eq!(
+        Message::try_deserialize("MV::2::3"),
+        Err(error(&["MV", "1", "1"], ErrorKind::Missing))
+    );
+    assert_eq!(
+        Named::try_deserialize("7::x::2::Sam"),
+        Err(error(&["pos", "0"], ErrorKind::Malformed("x".to_string())))
+    );
+    assert_eq!(
+        Message::try_deserialize("Moved::2"),
+        Err(error(&[], ErrorKind::UnknownTag("Moved".to_string())))
+    );
+    assert_eq!(Packet::try_deserialize("Ack::5"), Ok(Packet::Ack(5)));
+    // Even types with an `other` variant report why they failed
+    assert_eq!(
+        Packet::try_deserialize("Ack").unwrap_err().to_string(),
+        "field `Ack.0` is missing"
+    );
+    assert_eq!(
+        Message::try_deserialize("Hi").unwrap_err().to_string(),
+        "message has unknown tag `Hi`"
+    );
+}
+
+#[test]
+fn test_escaping() {
+    let hostile = [
+        "a::b", "a:", ":", "::", "\\", "a\\", "\\::", "\\:", "a\nb\r", "", "名前",
+    ];
+    for name in hostile.iter() {
+        assert_eq!(unescape(&escape(name)), Ok(name.to_string()));
+        assert!(!escape(name).contains(DELIMITER) && !escape(name).contains('\n'));
+
+        let message = Message::Hello(name.to_string());
+        assert_eq!(Message::deserialize(&message.serialize()), Some(message));
+        let named = Named {
+            id: 1,
+            pos: Point(2, 3),
+            name: name.to_string(),
+        };
+        assert_eq!(Named::try_deserialize(&named.serialize()), Ok(named));
+    }
+    // Escaped delimiters don't split segments, and the rest is left escaped
+    let mut segs = Segments::new("a\\:\\:b::c\\::d");
+    assert_eq!(segs.next(), Some("a\\:\\:b"));
+    assert_eq!(segs.rest(), "c\\::d");
+
+    // Words hold no whitespace at all, and are never empty
+    for name in hostile.iter().chain(["guard room", "a\tb\u{3000}c"].iter()) {
+        let word = escape_word(name);
+        assert!(!word.is_empty() && !word.contains(char::is_whitespace));
+        assert_eq!(unescape(&word), Ok(name.to_string()));
+    }
+    assert_eq!(escape_word("guard room"), "guard\\sroom");
+    assert_eq!(escape_word("a:\tb\u{3000}"), "a\\:\\tb\\u{3000}");
+    assert_eq!(escape_word(""), "\\e");
+
+    for bad in ["\\", "a\\q", "\\\\\\", "a\\e", "\\u{20", "\\u{d800}"].iter() {
+        assert_eq!(
+            unescape(bad),
+            Err(DeserializeError::new(ErrorKind::Malformed(bad.to_string())))
+        );
+    }
+}
+
+#[test]
+fn test_binary() {
+    // Varints, zigzagged when signed
+    assert_eq!(0u32.to_bytes(), [0]);
+    assert_eq!(300u32.to_bytes(), [0xac, 0x02]);
+    assert_eq!((-1i32).to_bytes(), [1]);
+    assert_eq!(2i32.to_bytes(), [4]);
+    for n in [0, 1, -1, 63, -64, 64, i64::MAX, i64::MIN].iter() {
+        assert_eq!(i64::from_bytes(&n.to_bytes()), Ok(*n));
+    }
+    assert_eq!(u64::from_bytes(&u64::MAX.to_bytes()), Ok(u64::MAX));
+    assert!(u8::from_bytes(&300u32.to_bytes()).is_err());
+    assert!(u64::from_bytes(&[0xff; 11]).is_err());
+
+    let named = Named {
+        id: 7,
+        pos: Point(-1, 2),
+        name: "a::b".to_string(),
+    };
+    assert_eq!(named.to_bytes(), [7, 1, 4, 4, b'a', b':', b':', b'b']);
+    let route = Route {
+        points: vec![(-1, 2)],
+    };
+    assert_eq!(route.to_bytes(), [1, 1, 2]);
+    assert_eq!(Route::from_bytes(&route.to_bytes()), Ok(route));
+    assert_eq!(Named::from_bytes(&named.to_bytes()), Ok(named));
+
+    let messages = [
+        Message::Hello("Sam".to_string()),
+        Message::Moved(2, Point(3, -4)),
+        Message::Data(1, "a::b".to_string()),
+        Message::Quit,
+        Message::Done,
+    ];
+    for message in messages.iter() {
+        assert_eq!(
+            Message::from_bytes(&message.to_bytes()).as_ref(),
+            Ok(message)
+        );
+    }
+    // Variants are tagged by index, and rest fields take the rest of the bytes
+    assert_eq!(Message::Moved(2, Point(3, -4)).to_bytes(), [1, 2, 6, 7]);
+    assert_eq!(
+        Message::Data(1, "xy".to_string()).to_bytes(),
+        [2, 1, b'x', b'y']
+    );
+
+    let error = |path: &[&str], kind| DeserializeError {
+        path: path.iter().map(|p| p.to_string()).collect(),
+        kind,
+    };
+    assert_eq!(
+        Message::from_bytes(&[1, 2, 6]),
+        Err(error(&["MV", "1", "1"], ErrorKind::Missing))
+    );
+    assert_eq!(
+        Message::from_bytes(&[9]),
+        Err(error(&[], ErrorKind::UnknownTag("9".to_string())))
+    );
+    assert_eq!(
+        Message::from_bytes(&[0, 5, b'a']),
+        Err(error(&["Hello", "0"], ErrorKind::Missing))
+    );
+    assert!(Message::from_bytes(&[0, 1, 0xff]).is_err());
+}
+
+#[test]
+fn test_versioned() {
+    let join = Versions::Join("Sam".to_string(), 5, vec!["a::b".to_string()]);
+    assert_eq!(join.serialize_for(1), Some("Join::Sam".to_string()));
+    assert_eq!(join.serialize_for(2), Some("Join::Sam::5".to_string()));
+    assert_eq!(join.serialize_for(3), Some(join.serialize()));
+    assert_eq!(join.serialize(), "Join::Sam::5::1::a\\:\\:b");
+    assert_eq!(join.to_bytes_for(9), Some(join.to_bytes()));
+    assert_eq!(Versions::try_deserialize(&join.serialize()), Ok(join));
+
+    // Fields from later versions are read back as their defaults
+    let old = Versions::Join("Sam".to_string(), 0, Vec::new());
+    for version in 1..=2 {
+        let text = old.serialize_for(version).unwrap();
+        let bytes = old.to_bytes_for(version).unwrap();
+        assert_eq!(Versions::try_deserialize(&text).as_ref(), Ok(&old));
+        assert_eq!(Versions::from_bytes(&bytes).as_ref(), Ok(&old));
+    }
+    assert_eq!(old.to_bytes_for(1), Some(vec![0, 3, b'S', b'a', b'm']));
+
+    // Variants from later versions aren't written at all
+    assert_eq!(Versions::Leave(2).since(), 2);
+    assert_eq!(Versions::Leave(2).serialize_for(1), None);
+    assert_eq!(Versions::Leave(2).to_bytes_for(1), None);
+    assert_eq!(
+        Versions::Leave(2).serialize_for(2),
+        Some("Leave::2".to_string())
+    );
+
+    assert_eq!(
+        Versions::try_deserialize("Join::Sam::5::2::x")
+            .unwrap_err()
+            .field(),
+        "Join.2.1"
+    );
+    assert_eq!(
+        Versions::from_bytes(&[0, 0, 5, 1, 9]).unwrap_err().field(),
+        "Join.2.0"
+    );
+
+    // Nested `versioned` fields are written for the same version
+    let map = Nested::Map(
+        1,
+        Box::new(Area {
+            width: 4,
+            height: 3,
+        }),
+    );
+    assert_eq!(map.serialize_for(1), Some("Map::1::4".to_string()));
+    assert_eq!(map.serialize_for(2), Some(map.serialize()));
+    assert_eq!(map.to_bytes_for(1), Some(vec![0, 1, 4]));
+    assert_eq!(map.to_bytes_for(2), Some(map.to_bytes()));
+    let old = Nested::Map(
+        1,
+        Box::new(Area {
+            width: 4,
+            height: 0,
+        }),
+    );
+    assert_eq!(Nested::try_deserialize("Map::1::4"), Ok(old));
+}
+
+#[test]
+fn test_numbered_enum() {
+    assert_eq!(Shade::ALL, [Shade::Light, Shade::Dark]);
+    assert_eq!(Shade::Dark.to_string(), "1");
+    assert_eq!(Shade::from_u32(0), Some(Shade::Light));
+    assert_eq!(Shade::from_u32(2), None);
+    assert_eq!(Shade::Dark.name(), "dark");
+    assert_eq!(Shade::from_name("light"), Some(Shade::Light));
+    assert_eq!(Shade::from_name("Light"), None);
+
+    // Numbered enums can be the fields of derived types, in either format
+    let shaded = Shaded(Point(1, -2), Shade::Dark);
+    assert_eq!(shaded.serialize(), "1::-2::1");
+    assert_eq!(Shaded::deserialize("1::-2::1"), Some(shaded));
+    assert_eq!(Shaded(Point(1, -2), Shade::Dark).to_bytes(), [2, 3, 1]);
+    assert_eq!(
+        Shaded::from_bytes(&[2, 3, 1]),
+        Ok(Shaded(Point(1, -2), Shade::Dark))
+    );
+    assert_eq!(
+        Shaded::try_deserialize("1::-2::2").unwrap_err().kind,
+        ErrorKind::Malformed("2".to_string())
+    );
+    assert!(Shaded::from_bytes(&[2, 3, 2]).is_err());
+}
@@ -0,0 +1,701 @@
+//! Derive Macros for Simple Serializer
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+//!
+//! `#[derive(Serialize)]` writes each field of a struct in order,
+//! joined by `::`. Enums write the tag of their variant first, then
+//! its fields - so `Moved(3, Vec2(1, 2))` is written `Moved::3::1::2`.
+//! `#[derive(Deserialize)]` reads the same segments back, ignoring any
+//! left over at the end. It also implements `TryDeserialize`, whose
+//! errors give the path to the field which failed, such as `Moved.1.pos.0`.
+//! String fields are escaped, so they can't break the message apart.
+//! Every field must implement the segment traits of `simple_serializer`,
+//! which the derives also implement, so derived types can be nested in
+//! each other.
+//!
+//! `#[derive(Encode, Decode)]` writes and reads the same fields in the
+//! binary format instead, with each variant tagged by its index in the
+//! enum - so new variants must be added at the end.
+//!
+//! The `#[serialize(...)]` attribute adjusts the format:
+//! - `tag = "..."` on a variant replaces its name as its text tag
+//! - `other` on a unit variant deserializes anything that doesn't
+//!   parse as that variant, rather than `None`
+//! - `trailing` on a unit variant writes an empty segment after its
+//!   tag, so the text ends in `::` - for clients which expect one.
+//!   It is read back with or without it
+//! - `rest` on the last field reads everything left, delimiters
+//!   and all, into it - for fields which are already serialized,
+//!   so are written and read as they are, through the `Rest` trait
+//! - `versioned` on a field writes it through its own `Versioned` impl,
+//!   for the version the message is written for, so the fields of a
+//!   nested type can change between versions too
+//! - `bytes` on the type serializes it into bytes, rather than a `String`
+//! - `since = N` on a variant or field marks the version of the protocol
+//!   it was added in. `#[derive(Versioned)]` leaves it out when writing
+//!   for an earlier version, and a `since` field is read back as its
+//!   `Default` if the message ends before it - so `since` fields must
+//!   come last, and belong on the top-level message rather than nested types
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Error, Fields, LitInt,
+    LitStr, Result,
+};
+
+///
+/// The options set by `#[serialize(...)]` attributes
+///
+#[derive(Default)]
+struct Options {
+    tag: Option<String>,
+    other: bool,
+    trailing: bool,
+    rest: bool,
+    versioned: bool,
+    bytes: bool,
+    since: Option<u32>,
+}
+
+impl Options {
+    /// Reads the options from the `#[serialize(...)]` attributes in `attrs`
+    fn from_attrs(attrs: &[Attribute]) -> Result<Self> {
+        let mut options = Options::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("serialize")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("tag") {
+                    options.tag = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("other") {
+                    options.other = true;
+                } else if meta.path.is_ident("trailing") {
+                    options.trailing = true;
+                } else if meta.path.is_ident("rest") {
+                    options.rest = true;
+                } else if meta.path.is_ident("versioned") {
+                    options.versioned = true;
+                } else if meta.path.is_ident("bytes") {
+                    options.bytes = true;
+                } else if meta.path.is_ident("since") {
+                    let since = meta.value()?.parse::<LitInt>()?.base10_parse()?;
+                    if since == 0 {
+                        return Err(meta.error("versions start at 1"));
+                    }
+                    options.since = Some(since);
+                } else {
+                    return Err(meta
+                        .error("expected `tag`, `other`, `trailing`, `rest`, `versioned`, `bytes` or `since`"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(options)
+    }
+    /// Fails if any option is set which only belongs somewhere else
+    fn only(
+        &self,
+        variant: bool,
+        field: bool,
+        bytes: bool,
+        since: bool,
+        span: &dyn Spanned,
+    ) -> Result<()> {
+        let on_variant = self.tag.is_some() || self.other || self.trailing;
+        if (on_variant && !variant)
+            || ((self.rest || self.versioned) && !field)
+            || (self.bytes && !bytes)
+            || (self.since.is_some() && !since)
+        {
+            return Err(Error::new(
+                span.span(),
+                "`tag`, `other` and `trailing` belong on variants, `rest` and `versioned` \
+                 on fields, `since` on either, and `bytes` on the type",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Derives `Serialize` and `SerializeSegments`
+#[proc_macro_derive(Serialize, attributes(serialize))]
+pub fn derive_serialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    serialize(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives `Deserialize`, `TryDeserialize` and `DeserializeSegments`
+#[proc_macro_derive(Deserialize, attributes(serialize))]
+pub fn derive_deserialize(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    deserialize(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives `Encode`
+#[proc_macro_derive(Encode, attributes(serialize))]
+pub fn derive_encode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    encode(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives `Decode`
+#[proc_macro_derive(Decode, attributes(serialize))]
+pub fn derive_decode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    decode(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Derives `Versioned`
+#[proc_macro_derive(Versioned, attributes(serialize))]
+pub fn derive_versioned(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    versioned(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+///
+/// The names the fields of `fields` are bound to when
+/// destructuring, and the pattern binding them.
+///
+fn bindings(fields: &Fields) -> (Vec<syn::Ident>, TokenStream2) {
+    match fields {
+        Fields::Named(named) => {
+            let names: Vec<syn::Ident> = named
+                .named
+                .iter()
+                .map(|f| f.ident.clone().unwrap())
+                .collect();
+            (names.clone(), quote! { { #(#names),* } })
+        }
+        Fields::Unnamed(unnamed) => {
+            let names: Vec<syn::Ident> = (0..unnamed.unnamed.len())
+                .map(|i| format_ident!("field_{}", i))
+                .collect();
+            (names.clone(), quote! { ( #(#names),* ) })
+        }
+        Fields::Unit => (Vec::new(), quote! {}),
+    }
+}
+
+///
+/// The two formats a type can be derived for
+///
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// `::` separated text, through the segment traits
+    Text,
+    /// The binary format, through `Encode` and `Decode`
+    Binary,
+}
+
+///
+/// The pattern binding `fields`, and the statements writing each
+/// of them in turn in the `format`. `rest` fields are written as
+/// they are, as they are read back, rather than escaped. If
+/// `versioned`, `since` fields are only written from their version,
+/// and `versioned` fields are written for it.
+///
+fn write_fields(
+    fields: &Fields,
+    format: Format,
+    versioned: bool,
+) -> Result<(TokenStream2, TokenStream2)> {
+    let (names, pattern) = bindings(fields);
+    let mut writes = Vec::new();
+    for (field, name) in fields.iter().zip(names.iter()) {
+        let options = Options::from_attrs(&field.attrs)?;
+        let write = match (format, options.rest) {
+            (Format::Text, _) if versioned && options.versioned => quote! {
+                ::simple_serializer::Versioned::serialize_segments_for(#name, version, segs);
+            },
+            (Format::Binary, _) if versioned && options.versioned => quote! {
+                ::simple_serializer::Versioned::encode_for(#name, version, out);
+            },
+            (Format::Text, true) => {
+                quote! { segs.push(::simple_serializer::Rest::rest_text(#name)); }
+            }
+            (Format::Text, false) => {
+                quote! { ::simple_serializer::SerializeSegments::serialize_segments(#name, segs); }
+            }
+            (Format::Binary, true) => {
+                quote! { out.extend_from_slice(::simple_serializer::Rest::rest_bytes(#name)); }
+            }
+            (Format::Binary, false) => {
+                quote! { ::simple_serializer::Encode::encode(#name, out); }
+            }
+        };
+        writes.push(match options.since {
+            Some(since) if versioned => quote! {
+                if version >= #since {
+                    #write
+                }
+            },
+            _ => write,
+        });
+    }
+    Ok((pattern, quote! { #(#writes)* }))
+}
+
+///
+/// Checks the options of each of `fields`, which may only set
+/// `rest`, on the last field, `versioned`, but not with `rest`,
+/// and `since`, on fields which follow
+/// all the others in the order of their versions.
+///
+fn check_fields(fields: &Fields) -> Result<()> {
+    let mut since = None;
+    for (i, field) in fields.iter().enumerate() {
+        let options = Options::from_attrs(&field.attrs)?;
+        options.only(false, true, false, true, field)?;
+        if options.rest && i + 1 != fields.len() {
+            return Err(Error::new(field.span(), "`rest` must be on the last field"));
+        }
+        if options.rest && options.versioned {
+            return Err(Error::new(
+                field.span(),
+                "`rest` fields are written as they are, so can't be `versioned`",
+            ));
+        }
+        if since > options.since {
+            return Err(Error::new(
+                field.span(),
+                "`since` fields must come last, from the earliest version",
+            ));
+        }
+        since = options.since;
+    }
+    Ok(())
+}
+
+///
+/// The tag of each variant, checking that no two are the same,
+/// and the single variant marked `other`, if there is one.
+///
+fn tags(data: &syn::DataEnum) -> Result<(Vec<String>, Option<syn::Ident>)> {
+    let mut tags: Vec<String> = Vec::new();
+    let mut other = None;
+    for variant in data.variants.iter() {
+        let options = Options::from_attrs(&variant.attrs)?;
+        options.only(true, false, false, true, variant)?;
+        check_fields(&variant.fields)?;
+        let tag = options.tag.unwrap_or_else(|| variant.ident.to_string());
+        if tags.contains(&tag) {
+            return Err(Error::new(
+                variant.span(),
+                format!("duplicate tag `{}`", tag),
+            ));
+        }
+        tags.push(tag);
+        if options.trailing && !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new(
+                variant.span(),
+                "`trailing` must be on a unit variant",
+            ));
+        }
+        if options.other {
+            if other.is_some() || !matches!(variant.fields, Fields::Unit) {
+                return Err(Error::new(
+                    variant.span(),
+                    "`other` must be on a single unit variant",
+                ));
+            }
+            other = Some(variant.ident.clone());
+        }
+    }
+    Ok((tags, other))
+}
+
+///
+/// The statements writing the text tag of `variant`, followed by
+/// an empty segment if it is marked `trailing`.
+///
+fn write_tag(variant: &syn::Variant, tag: &str) -> Result<TokenStream2> {
+    let trailing = if Options::from_attrs(&variant.attrs)?.trailing {
+        quote! { segs.push(::std::string::String::new()); }
+    } else {
+        quote! {}
+    };
+    Ok(quote! {
+        segs.push(::std::string::String::from(#tag));
+        #trailing
+    })
+}
+
+fn serialize(input: &DeriveInput) -> Result<TokenStream2> {
+    let options = Options::from_attrs(&input.attrs)?;
+    options.only(false, false, true, false, &input.ident)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            check_fields(&data.fields)?;
+            let (pattern, writes) = write_fields(&data.fields, Format::Text, false)?;
+            quote! {
+                let Self #pattern = self;
+                #writes
+            }
+        }
+        Data::Enum(data) => {
+            let (tags, _) = tags(data)?;
+            let mut arms = Vec::new();
+            for (variant, tag) in data.variants.iter().zip(tags.iter()) {
+                let ident = &variant.ident;
+                let (pattern, writes) = write_fields(&variant.fields, Format::Text, false)?;
+                let write_tag = write_tag(variant, tag)?;
+                arms.push(quote! {
+                    Self::#ident #pattern => {
+                        #write_tag
+                        #writes
+                    }
+                });
+            }
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => return Err(Error::new(name.span(), "unions can't be serialized")),
+    };
+
+    let (serialize_to, convert) = if options.bytes {
+        (quote! { ::std::vec::Vec<u8> }, quote! { .into_bytes() })
+    } else {
+        (quote! { ::std::string::String }, quote! {})
+    };
+    Ok(quote! {
+        impl #impl_generics ::simple_serializer::SerializeSegments for #name #ty_generics #where_clause {
+            fn serialize_segments(&self, segs: &mut ::std::vec::Vec<::std::string::String>) {
+                #body
+            }
+        }
+        impl #impl_generics ::simple_serializer::Serialize for #name #ty_generics #where_clause {
+            type SerializeTo = #serialize_to;
+            fn serialize(&self) -> Self::SerializeTo {
+                let mut segs = ::std::vec::Vec::new();
+                ::simple_serializer::SerializeSegments::serialize_segments(self, &mut segs);
+                segs.join(::simple_serializer::DELIMITER) #convert
+            }
+        }
+    })
+}
+
+///
+/// The expression building `path` from the segments or bytes
+/// its `fields` were written as in the `format`. Errors name the
+/// field which failed, within the variant `tag` if there is one.
+///
+fn construct(
+    path: TokenStream2,
+    fields: &Fields,
+    tag: Option<&str>,
+    format: Format,
+) -> Result<TokenStream2> {
+    let within_tag = tag.map(|tag| quote! { .within(#tag) });
+    let mut values = Vec::new();
+    for (i, field) in fields.iter().enumerate() {
+        let ty = &field.ty;
+        let label = match &field.ident {
+            Some(ident) => ident.to_string(),
+            None => i.to_string(),
+        };
+        let options = Options::from_attrs(&field.attrs)?;
+        let value = match (format, options.rest) {
+            (Format::Text, true) => {
+                quote! { <#ty as ::simple_serializer::Rest>::from_rest_text(segs.rest()) }
+            }
+            (Format::Text, false) => quote! {
+                <#ty as ::simple_serializer::DeserializeSegments>::deserialize_segments(segs)
+                    .map_err(|e| e.within(#label) #within_tag)?
+            },
+            (Format::Binary, true) => quote! {
+                <#ty as ::simple_serializer::Rest>::from_rest_bytes(bytes.rest())
+                    .map_err(|e| e.within(#label) #within_tag)?
+            },
+            (Format::Binary, false) => quote! {
+                <#ty as ::simple_serializer::Decode>::decode(bytes)
+                    .map_err(|e| e.within(#label) #within_tag)?
+            },
+        };
+        // Messages from before a `since` field was added end before it
+        let ended = match format {
+            Format::Text => quote! { segs.is_empty() },
+            Format::Binary => quote! { bytes.is_empty() },
+        };
+        values.push(match options.since {
+            Some(_) => quote! {
+                if #ended {
+                    ::std::default::Default::default()
+                } else {
+                    #value
+                }
+            },
+            None => value,
+        });
+    }
+    Ok(match fields {
+        Fields::Named(_) => {
+            let names = fields.iter().map(|f| f.ident.clone().unwrap());
+            quote! { #path { #(#names: #values),* } }
+        }
+        Fields::Unnamed(_) => quote! { #path ( #(#values),* ) },
+        Fields::Unit => path,
+    })
+}
+
+fn deserialize(input: &DeriveInput) -> Result<TokenStream2> {
+    let options = Options::from_attrs(&input.attrs)?;
+    options.only(false, false, true, false, &input.ident)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let (body, other) = match &input.data {
+        Data::Struct(data) => {
+            check_fields(&data.fields)?;
+            let value = construct(quote! { Self }, &data.fields, None, Format::Text)?;
+            (quote! { ::std::result::Result::Ok(#value) }, None)
+        }
+        Data::Enum(data) => {
+            let (tags, other) = tags(data)?;
+            let mut arms = Vec::new();
+            for (variant, tag) in data.variants.iter().zip(tags.iter()) {
+                let ident = &variant.ident;
+                let value = construct(
+                    quote! { Self::#ident },
+                    &variant.fields,
+                    Some(tag),
+                    Format::Text,
+                )?;
+                arms.push(quote! { #tag => ::std::result::Result::Ok(#value), });
+            }
+            (
+                quote! {
+                    let tag = segs.field()?;
+                    match tag.trim() {
+                        #(#arms)*
+                        _ => ::std::result::Result::Err(::simple_serializer::DeserializeError::new(
+                            ::simple_serializer::ErrorKind::UnknownTag(tag.to_string()),
+                        )),
+                    }
+                },
+                other,
+            )
+        }
+        Data::Union(_) => return Err(Error::new(name.span(), "unions can't be deserialized")),
+    };
+
+    // Types with an `other` variant always deserialize into something
+    let (deserialize_to, finish) = match other {
+        Some(other) => (quote! { Self }, quote! { .unwrap_or(Self::#other) }),
+        None => (quote! { ::std::option::Option<Self> }, quote! { .ok() }),
+    };
+    Ok(quote! {
+        impl #impl_generics ::simple_serializer::DeserializeSegments for #name #ty_generics #where_clause {
+            fn deserialize_segments(
+                segs: &mut ::simple_serializer::Segments,
+            ) -> ::std::result::Result<Self, ::simple_serializer::DeserializeError> {
+                #body
+            }
+        }
+        impl #impl_generics ::simple_serializer::TryDeserialize for #name #ty_generics #where_clause {
+            fn try_deserialize(
+                from: &str,
+            ) -> ::std::result::Result<Self, ::simple_serializer::DeserializeError> {
+                let mut segs = ::simple_serializer::Segments::new(from);
+                <Self as ::simple_serializer::DeserializeSegments>::deserialize_segments(&mut segs)
+            }
+        }
+        impl #impl_generics ::simple_serializer::Deserialize for #name #ty_generics #where_clause {
+            type DeserializeTo = #deserialize_to;
+            fn deserialize(from: &str) -> Self::DeserializeTo {
+                <Self as ::simple_serializer::TryDeserialize>::try_deserialize(from) #finish
+            }
+        }
+    })
+}
+
+fn encode(input: &DeriveInput) -> Result<TokenStream2> {
+    let options = Options::from_attrs(&input.attrs)?;
+    options.only(false, false, true, false, &input.ident)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            check_fields(&data.fields)?;
+            let (pattern, writes) = write_fields(&data.fields, Format::Binary, false)?;
+            quote! {
+                let Self #pattern = self;
+                #writes
+            }
+        }
+        Data::Enum(data) => {
+            tags(data)?;
+            let mut arms = Vec::new();
+            for (index, variant) in data.variants.iter().enumerate() {
+                let ident = &variant.ident;
+                let index = index as u64;
+                let (pattern, writes) = write_fields(&variant.fields, Format::Binary, false)?;
+                arms.push(quote! {
+                    Self::#ident #pattern => {
+                        ::simple_serializer::write_varint(#index, out);
+                        #writes
+                    }
+                });
+            }
+            quote! {
+                match self {
+                    #(#arms)*
+                }
+            }
+        }
+        Data::Union(_) => return Err(Error::new(name.span(), "unions can't be encoded")),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::simple_serializer::Encode for #name #ty_generics #where_clause {
+            fn encode(&self, out: &mut ::std::vec::Vec<u8>) {
+                #body
+            }
+        }
+    })
+}
+
+fn decode(input: &DeriveInput) -> Result<TokenStream2> {
+    let options = Options::from_attrs(&input.attrs)?;
+    options.only(false, false, true, false, &input.ident)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => {
+            check_fields(&data.fields)?;
+            let value = construct(quote! { Self }, &data.fields, None, Format::Binary)?;
+            quote! { ::std::result::Result::Ok(#value) }
+        }
+        Data::Enum(data) => {
+            let (tags, _) = tags(data)?;
+            let mut arms = Vec::new();
+            for (index, (variant, tag)) in data.variants.iter().zip(tags.iter()).enumerate() {
+                let ident = &variant.ident;
+                let index = index as u64;
+                let value = construct(
+                    quote! { Self::#ident },
+                    &variant.fields,
+                    Some(tag),
+                    Format::Binary,
+                )?;
+                arms.push(quote! { #index => ::std::result::Result::Ok(#value), });
+            }
+            quote! {
+                let tag = bytes.varint()?;
+                match tag {
+                    #(#arms)*
+                    _ => ::std::result::Result::Err(::simple_serializer::DeserializeError::new(
+                        ::simple_serializer::ErrorKind::UnknownTag(tag.to_string()),
+                    )),
+                }
+            }
+        }
+        Data::Union(_) => return Err(Error::new(name.span(), "unions can't be decoded")),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::simple_serializer::Decode for #name #ty_generics #where_clause {
+            fn decode(
+                bytes: &mut ::simple_serializer::Bytes,
+            ) -> ::std::result::Result<Self, ::simple_serializer::DeserializeError> {
+                #body
+            }
+        }
+    })
+}
+
+fn versioned(input: &DeriveInput) -> Result<TokenStream2> {
+    let options = Options::from_attrs(&input.attrs)?;
+    options.only(false, false, true, false, &input.ident)?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let (since, text, binary) = match &input.data {
+        Data::Struct(data) => {
+            check_fields(&data.fields)?;
+            let (pattern, text) = write_fields(&data.fields, Format::Text, true)?;
+            let (_, binary) = write_fields(&data.fields, Format::Binary, true)?;
+            (
+                quote! { 1 },
+                quote! {
+                    let Self #pattern = self;
+                    #text
+                },
+                quote! {
+                    let Self #pattern = self;
+                    #binary
+                },
+            )
+        }
+        Data::Enum(data) => {
+            let (tags, _) = tags(data)?;
+            let (mut since_arms, mut text_arms, mut binary_arms) =
+                (Vec::new(), Vec::new(), Vec::new());
+            for (index, (variant, tag)) in data.variants.iter().zip(tags.iter()).enumerate() {
+                let ident = &variant.ident;
+                let index = index as u64;
+                let since = Options::from_attrs(&variant.attrs)?.since.unwrap_or(1);
+                let (pattern, text) = write_fields(&variant.fields, Format::Text, true)?;
+                let (_, binary) = write_fields(&variant.fields, Format::Binary, true)?;
+                let write_tag = write_tag(variant, tag)?;
+                since_arms.push(quote! { Self::#ident { .. } => #since, });
+                text_arms.push(quote! {
+                    Self::#ident #pattern => {
+                        #write_tag
+                        #text
+                    }
+                });
+                binary_arms.push(quote! {
+                    Self::#ident #pattern => {
+                        ::simple_serializer::write_varint(#index, out);
+                        #binary
+                    }
+                });
+            }
+            (
+                quote! { match self { #(#since_arms)* } },
+                quote! { match self { #(#text_arms)* } },
+                quote! { match self { #(#binary_arms)* } },
+            )
+        }
+        Data::Union(_) => return Err(Error::new(name.span(), "unions can't be versioned")),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::simple_serializer::Versioned for #name #ty_generics #where_clause {
+            fn since(&self) -> u32 {
+                #since
+            }
+            fn serialize_segments_for(
+                &self,
+                version: u32,
+                segs: &mut ::std::vec::Vec<::std::string::String>,
+            ) {
+                let _ = version;
+                #text
+            }
+            fn encode_for(&self, version: u32, out: &mut ::std::vec::Vec<u8>) {
+                let _ = version;
+                #binary
+            }
+        }
+    })
+}
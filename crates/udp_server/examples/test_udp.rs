@@ -39,7 +39,7 @@ fn main() -> std::io::Result<()> {
         s1.send(SendPacket {
             addrs: addrs.clone(),
             is_rel: false,
-            msg,
+            msg: msg.into(),
         })
         .unwrap();
 
@@ -48,7 +48,7 @@ fn main() -> std::io::Result<()> {
         s2.send(SendPacket {
             addrs: addrs.clone(),
             is_rel: false,
-            msg,
+            msg: msg.into(),
         })
         .unwrap();
     });
@@ -59,7 +59,7 @@ fn main() -> std::io::Result<()> {
             count += 1;
 
             if PRINT_UUIDS {
-                println!("{} >> {}", msg, count);
+                println!("{:?} >> {}", msg, count);
             } else if count % 1000 == 0 {
                 println!("{}", count);
             }
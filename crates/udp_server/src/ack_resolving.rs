@@ -9,12 +9,12 @@ use std::{
     time::{Duration, Instant},
 };
 
-use super::enums::RelResult;
+use super::{enums::RelResult, packets::Payload};
 
 pub struct AckResolver {
     pub addr: SocketAddr,
     pub index: u64,
-    pub msg: String,
+    pub msg: Payload,
 
     start_time: Instant,
     last_update_time: Instant,
@@ -84,7 +84,7 @@ impl AckResolverManager {
     /// Returns a `u64` representing the ack index of the reliable datagram
     /// being delivered.
     ///
-    pub fn create_rel_resolver(&mut self, addr: SocketAddr, msg: String) -> u64 {
+    pub fn create_rel_resolver(&mut self, addr: SocketAddr, msg: Payload) -> u64 {
         // Check if a reliable datagram has already been sent to this client,
         // and if so, grab the next index. Otherwise, add the client to next_to and
         // next_from, and create a new resolver list
@@ -99,10 +99,7 @@ impl AckResolverManager {
             last_update_time: Instant::now(),
         };
 
-        self.resolvers
-            .entry(addr)
-            .or_insert_with(VecDeque::new)
-            .insert(0, resolver);
+        self.resolvers.entry(addr).or_default().insert(0, resolver);
         self.timeouts
             .entry(addr)
             .or_insert_with(|| Duration::from_millis(500));
@@ -2,4 +2,4 @@ mod ack_resolving;
 mod enums;
 pub mod manager;
 pub mod packets;
-mod types;
+pub mod types;
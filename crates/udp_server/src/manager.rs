@@ -8,7 +8,7 @@ use super::{
     ack_resolving::AckResolverManager,
     enums::{HandlerState, RelResult},
     packets::{
-        PacketReceiver, PacketSender, ReceivePacket,
+        Encoding, PacketReceiver, PacketSender, ReceivePacket,
         ReceivePacket::{ClientMessage, DroppedClient},
         SendPacket,
     },
@@ -16,7 +16,6 @@ use super::{
 };
 
 use crossbeam::channel::{unbounded, Receiver, Sender};
-use simple_serializer::{Deserialize, Serialize};
 
 use std::{
     collections::{HashMap, HashSet},
@@ -28,6 +27,10 @@ use std::{
 
 const DEFAULT_DROP_TIME: Duration = Duration::from_secs(5);
 
+// The largest datagram UDP can carry - a Welcome, holding a whole
+// Dungeon, is far larger than any other message
+const MAX_DATAGRAM_SIZE: usize = 65_507;
+
 ///
 /// A udp datagram manager, which recieves
 /// the lowest-level byte data from incoming
@@ -131,7 +134,7 @@ impl DatagramManager {
         // datagrams received by the socket
         std::thread::spawn(move || loop {
             state = r_handler_state.try_recv().unwrap_or(state);
-            let mut buf = [0; 100];
+            let mut buf = [0; MAX_DATAGRAM_SIZE];
 
             match state {
                 HandlerState::Dropped => break,
@@ -171,7 +174,7 @@ impl DatagramManager {
                 for res in ack_resolver.retrieve_timeouts().iter() {
                     socket
                         .send_to(
-                            &Type::Rel(res.index, res.msg.to_string()).serialize(),
+                            &Type::Rel(res.index, res.msg.clone()).frame(res.msg.encoding()),
                             res.addr,
                         )
                         .unwrap();
@@ -179,19 +182,23 @@ impl DatagramManager {
 
                 // If a datagram has been received be socket
                 if let Ok((amt, addr)) = socket.recv_from(&mut buf) {
+                    // Replies are written in the encoding the client wrote in
+                    let buf = &buf[..amt];
+                    let encoding = Encoding::of(buf);
                     if dropped_clients.contains(&addr) {
-                        socket.send_to(&Type::Drop.serialize(), addr).unwrap();
+                        socket.send_to(&Type::Drop.frame(encoding), addr).unwrap();
                         continue;
                     }
 
-                    // Convert the buffer into a string, and parse the
-                    // string as a DatagramType
-                    let buf = &buf[..amt];
-                    let msg = String::from_utf8(buf.to_vec()).unwrap();
-                    let datagram = Type::deserialize(&msg);
-
                     client_ping_times.insert(addr, Instant::now());
 
+                    // Parse the buffer as a DatagramType. Malformed datagrams
+                    // are ignored, rather than bringing the server down.
+                    let datagram = match Type::unframe(buf) {
+                        Ok(datagram) => datagram,
+                        Err(_) => continue,
+                    };
+
                     match datagram {
                         // Have the Transmitter send the relevant data
                         // to the Receiver
@@ -212,7 +219,7 @@ impl DatagramManager {
                                         RelResult::ClientDropped => Type::Drop,
                                         _ => Type::Ack(ack_index),
                                     }
-                                    .serialize(),
+                                    .frame(encoding),
                                     addr,
                                 )
                                 .unwrap();
@@ -230,7 +237,8 @@ impl DatagramManager {
                             for res in resolvers {
                                 socket
                                     .send_to(
-                                        &Type::Rel(res.index, res.msg.to_string()).serialize(),
+                                        &Type::Rel(res.index, res.msg.clone())
+                                            .frame(res.msg.encoding()),
                                         res.addr,
                                     )
                                     .unwrap();
@@ -239,10 +247,8 @@ impl DatagramManager {
                         // Ping messages update the DatagramManager's
                         // client map, to ensure that the manager doesn't
                         // drop the client
-                        Type::Ping => {
-                            if client_ping_times.contains_key(&addr) {
-                                client_ping_times.insert(addr, Instant::now());
-                            }
+                        Type::Ping if client_ping_times.contains_key(&addr) => {
+                            client_ping_times.insert(addr, Instant::now());
                         }
                         _ => {}
                     }
@@ -304,7 +310,7 @@ impl DatagramManager {
                                     ack_resolver.create_rel_resolver(client, msg.clone()),
                                     msg.clone(),
                                 )
-                                .serialize(),
+                                .frame(msg.encoding()),
                                 client,
                             )
                             .unwrap();
@@ -313,7 +319,7 @@ impl DatagramManager {
                 } else {
                     for client in clients {
                         socket
-                            .send_to(&Type::Unrel(msg.clone()).serialize(), client)
+                            .send_to(&Type::Unrel(msg.clone()).frame(msg.encoding()), client)
                             .unwrap();
                     }
                 }
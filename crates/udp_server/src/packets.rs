@@ -4,9 +4,10 @@
 //! Christian Schmid - June 2021
 //! CS510 - Rust Programming
 
-use std::net::SocketAddr;
+use std::{net::SocketAddr, time::Duration};
 
-use crossbeam::channel::{Receiver, RecvError, SendError, Sender, TryRecvError};
+use crossbeam::channel::{Receiver, RecvError, RecvTimeoutError, SendError, Sender, TryRecvError};
+use simple_serializer::{DeserializeError, Rest};
 
 ///
 /// A wrapper for a channel Sender
@@ -55,6 +56,82 @@ impl PacketReceiver {
     pub fn try_recv(&self) -> Result<ReceivePacket, TryRecvError> {
         self.r_from_clients.try_recv()
     }
+    /// Attempts to receive a package, blocking the
+    /// current thread for at most `timeout`
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<ReceivePacket, RecvTimeoutError> {
+        self.r_from_clients.recv_timeout(timeout)
+    }
+}
+
+///
+/// The encodings a client can speak
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// `::` separated text
+    Text,
+    /// The compact binary format
+    Binary,
+}
+
+///
+/// The content of a message, in the encoding it is sent or
+/// received in. Each is carried in a datagram of the same
+/// encoding, so a client which sends binary messages is
+/// sent binary datagrams back.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Payload {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl Payload {
+    /// The encoding the `Payload` is in
+    pub fn encoding(&self) -> Encoding {
+        match self {
+            Payload::Text(_) => Encoding::Text,
+            Payload::Binary(_) => Encoding::Binary,
+        }
+    }
+}
+
+impl From<String> for Payload {
+    fn from(text: String) -> Self {
+        Payload::Text(text)
+    }
+}
+
+impl From<&str> for Payload {
+    fn from(text: &str) -> Self {
+        Payload::Text(text.to_string())
+    }
+}
+
+///
+/// A `Payload` takes up the rest of its datagram. Text
+/// datagrams only ever carry `Text` payloads, and binary
+/// datagrams `Binary` ones.
+///
+impl Rest for Payload {
+    fn rest_text(&self) -> String {
+        match self {
+            Payload::Text(text) => text.clone(),
+            Payload::Binary(bytes) => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+    fn from_rest_text(rest: &str) -> Self {
+        Payload::Text(rest.to_string())
+    }
+    fn rest_bytes(&self) -> &[u8] {
+        match self {
+            Payload::Text(text) => text.as_bytes(),
+            Payload::Binary(bytes) => bytes,
+        }
+    }
+    fn from_rest_bytes(rest: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Payload::Binary(rest.to_vec()))
+    }
 }
 
 ///
@@ -64,7 +141,7 @@ impl PacketReceiver {
 pub struct SendPacket {
     pub addrs: Vec<SocketAddr>,
     pub is_rel: bool,
-    pub msg: String,
+    pub msg: Payload,
 }
 
 ///
@@ -73,6 +150,6 @@ pub struct SendPacket {
 ///
 #[derive(Debug, PartialEq, Eq)]
 pub enum ReceivePacket {
-    ClientMessage(SocketAddr, String),
+    ClientMessage(SocketAddr, Payload),
     DroppedClient(SocketAddr),
 }
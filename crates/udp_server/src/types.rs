@@ -3,71 +3,78 @@
 //! Christian Schmid - June 2021
 //! CS510 - Rust Programming
 
-use std::str::FromStr;
+use simple_serializer::{
+    Decode, Deserialize, DeserializeError, Encode, ErrorKind, Serialize, TryDeserialize,
+};
+
+use crate::packets::{Encoding, Payload};
+
+/// The first byte of every binary datagram. It can never begin
+/// valid UTF-8, so no text datagram is mistaken for a binary one.
+pub const BINARY_MARKER: u8 = 0xb1;
 
-use simple_serializer::{Deserialize, Serialize};
 ///
 /// All datagram types that can be sent
 /// and/or recieved from the server
 ///
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, Encode, Decode)]
+#[serialize(bytes)]
 pub enum Type {
     /// unreliable datagram (does not need ack)
-    Unrel(String),
+    #[serialize(tag = "UNR")]
+    Unrel(#[serialize(rest)] Payload),
     /// reliable datagram (requires sending an ack)
-    Rel(u64, String),
+    #[serialize(tag = "REL")]
+    Rel(u64, #[serialize(rest)] Payload),
     /// ack datagram (acknowledges a rel datagram has been recieved)
     /// with it's associated index
+    #[serialize(tag = "ACK")]
     Ack(u64),
     /// resend datagram (the recipient needs all rel datagrams resent)
+    #[serialize(tag = "RES")]
     Res,
     /// a datagram representing to the server that a client is still connected
     /// If enough time passes where the client doesn't send this, or any other,
     /// datagram, the server will drop it.
+    #[serialize(tag = "PNG")]
     Ping,
-    /// a datagram that had some kind of parsing error, or the server informing a
-    /// client that it has been dropped
+    /// the server informing a client that it has been dropped
+    #[serialize(tag = "DRP")]
     Drop,
 }
 
-impl Serialize for Type {
-    type SerializeTo = Vec<u8>;
-    fn serialize(&self) -> Vec<u8> {
-        match self {
-            Self::Unrel(data) => format!("UNR::{}", data.to_string()),
-            Self::Rel(ack_index, data) => format!("REL::{}::{}", ack_index, data),
-            Self::Ack(ack_index) => format!("ACK::{}", ack_index),
-            Self::Res => "RES".to_string(),
-            Self::Ping => "PNG".to_string(),
-            Self::Drop => "DRP".to_string(),
+impl Type {
+    /// Writes the datagram in the `encoding`
+    pub fn frame(&self, encoding: Encoding) -> Vec<u8> {
+        match encoding {
+            Encoding::Text => self.serialize(),
+            Encoding::Binary => {
+                let mut datagram = vec![BINARY_MARKER];
+                self.encode(&mut datagram);
+                datagram
+            }
+        }
+    }
+    /// Reads a `datagram` written in either encoding
+    pub fn unframe(datagram: &[u8]) -> Result<Self, DeserializeError> {
+        match Encoding::of(datagram) {
+            Encoding::Text => match std::str::from_utf8(datagram) {
+                Ok(text) => Type::try_deserialize(text),
+                Err(_) => Err(DeserializeError::new(ErrorKind::Malformed(
+                    String::from_utf8_lossy(datagram).into_owned(),
+                ))),
+            },
+            Encoding::Binary => Type::from_bytes(&datagram[1..]),
         }
-        .into_bytes() // Convert the resulting string into bytes
     }
 }
 
-impl Deserialize for Type {
-    type DeserializeTo = Type;
-    fn deserialize(from: &str) -> Type {
-        let segs = from.split("::").collect::<Vec<&str>>();
-        match segs[0].trim() {
-            "UNR" => Type::Unrel(segs[1..].join("::")),
-            "REL" => {
-                return if let Ok(index) = u64::from_str(segs[1].trim()) {
-                    Type::Rel(index, segs[2..].join("::"))
-                } else {
-                    Type::Drop
-                }
-            }
-            "ACK" => {
-                return if let Ok(index) = u64::from_str(segs[1].trim()) {
-                    Type::Ack(index)
-                } else {
-                    Type::Drop
-                }
-            }
-            "RES" => Type::Res,
-            "PNG" => Type::Ping,
-            _ => Type::Drop,
+impl Encoding {
+    /// The encoding the `datagram` is written in
+    pub fn of(datagram: &[u8]) -> Self {
+        match datagram.first() {
+            Some(&BINARY_MARKER) => Encoding::Binary,
+            _ => Encoding::Text,
         }
     }
 }
@@ -6,18 +6,20 @@
 #[cfg(test)]
 mod datagram_handler_tests {
 
+    use simple_serializer::{ErrorKind, Serialize, TryDeserialize};
     use std::{net::SocketAddr, str::FromStr, thread, time::Duration};
     use udp_server::{
         manager::DatagramManager,
-        packets::{ReceivePacket, SendPacket},
+        packets::{Encoding, Payload, ReceivePacket, SendPacket},
+        types::{Type, BINARY_MARKER},
     };
 
     // Generates two DatagramManagers to test with
     fn gen_handlers(port1: u32, port2: u32) -> (DatagramManager, DatagramManager) {
-        return (
+        (
             DatagramManager::new(port1).unwrap(),
             DatagramManager::new(port2).unwrap(),
-        );
+        )
     }
 
     ///
@@ -36,14 +38,14 @@ mod datagram_handler_tests {
         s1.send(SendPacket {
             addrs: vec![SocketAddr::from_str("127.0.0.1:2001").unwrap()],
             is_rel: true,
-            msg: "Hello!".to_string(),
+            msg: "Hello!".into(),
         })
         .unwrap();
 
         // Ensure it was received by the other manager
         if let ReceivePacket::ClientMessage(addr, msg) = r2.recv().unwrap() {
             assert_eq!(addr, SocketAddr::from_str("127.0.0.1:2000").unwrap());
-            assert_eq!(msg, "Hello!");
+            assert_eq!(msg, "Hello!".into());
         } else {
             panic!("Recieved ClientDropped message");
         }
@@ -52,14 +54,14 @@ mod datagram_handler_tests {
         s2.send(SendPacket {
             addrs: vec![SocketAddr::from_str("127.0.0.1:2000").unwrap()],
             is_rel: true,
-            msg: "Hi there!".to_string(),
+            msg: "Hi there!".into(),
         })
         .unwrap();
 
         // Ensure it was received by the other manager
         if let ReceivePacket::ClientMessage(addr, msg) = r1.recv().unwrap() {
             assert_eq!(addr, SocketAddr::from_str("127.0.0.1:2001").unwrap());
-            assert_eq!(msg, "Hi there!");
+            assert_eq!(msg, "Hi there!".into());
         } else {
             panic!("Recieved ClientDropped message");
         }
@@ -81,7 +83,7 @@ mod datagram_handler_tests {
                 s1.send(SendPacket {
                     addrs: vec![SocketAddr::from_str("127.0.0.1:2005").unwrap()],
                     is_rel: true,
-                    msg: "Hello!".to_string(),
+                    msg: "Hello!".into(),
                 })
                 .unwrap();
             }
@@ -95,7 +97,7 @@ mod datagram_handler_tests {
                 s2.send(SendPacket {
                     addrs: vec![SocketAddr::from_str("127.0.0.1:2004").unwrap()],
                     is_rel: true,
-                    msg: "Hello!".to_string(),
+                    msg: "Hello!".into(),
                 })
                 .unwrap();
             }
@@ -143,7 +145,7 @@ mod datagram_handler_tests {
                 s2.send(SendPacket {
                     addrs: vec![SocketAddr::from_str("127.0.0.1:2002").unwrap()],
                     is_rel: true,
-                    msg: i.to_string(),
+                    msg: i.to_string().into(),
                 })
                 .unwrap();
                 std::thread::sleep(Duration::from_millis(100));
@@ -160,7 +162,7 @@ mod datagram_handler_tests {
                 r1.recv().unwrap(),
                 ReceivePacket::ClientMessage(
                     SocketAddr::from_str("127.0.0.1:2003").unwrap(),
-                    i.to_string()
+                    i.to_string().into()
                 )
             );
         }
@@ -177,7 +179,7 @@ mod datagram_handler_tests {
         s1.send(SendPacket {
             addrs: vec![SocketAddr::from_str("127.0.0.1:2007").unwrap()],
             is_rel: true,
-            msg: "hello!".to_string(),
+            msg: "hello!".into(),
         })
         .unwrap();
         r2.recv().unwrap();
@@ -192,4 +194,68 @@ mod datagram_handler_tests {
             ReceivePacket::DroppedClient(SocketAddr::from_str("127.0.0.1:2006").unwrap())
         );
     }
+
+    /// Tests the wire format of each datagram type, and that malformed
+    /// datagrams are rejected with the field that failed
+    #[test]
+    fn test_datagram_types() {
+        assert_eq!(Type::Unrel("a::b".into()).serialize(), b"UNR::a::b");
+        assert_eq!(Type::Rel(4, "hi".into()).serialize(), b"REL::4::hi");
+        assert_eq!(Type::Ack(4).serialize(), b"ACK::4");
+        assert_eq!(Type::Res.serialize(), b"RES");
+        assert_eq!(Type::Ping.serialize(), b"PNG");
+        assert_eq!(Type::Drop.serialize(), b"DRP");
+
+        assert!(
+            matches!(Type::try_deserialize("REL::4::a::b"), Ok(Type::Rel(4, d)) if d == "a::b".into())
+        );
+        assert!(matches!(Type::try_deserialize("UNR"), Ok(Type::Unrel(d)) if d == "".into()));
+        assert!(matches!(Type::try_deserialize("ACK:: 4"), Ok(Type::Ack(4))));
+        assert!(matches!(Type::try_deserialize("DRP"), Ok(Type::Drop)));
+
+        let error = |msg| Type::try_deserialize(msg).unwrap_err();
+        assert_eq!(error("ACK::x").field(), "ACK.0");
+        assert_eq!(error("ACK::x").kind, ErrorKind::Malformed("x".to_string()));
+        assert_eq!(error("REL").kind, ErrorKind::Missing);
+        assert_eq!(
+            error("what").kind,
+            ErrorKind::UnknownTag("what".to_string())
+        );
+    }
+
+    /// Tests that binary datagrams are framed compactly, read back in
+    /// either encoding, and carried between managers with their payloads
+    #[test]
+    fn test_binary_datagrams() {
+        let payload = Payload::Binary(vec![0, 1, 0xff]);
+        let datagram = Type::Rel(300, payload.clone()).frame(Encoding::Binary);
+        assert_eq!(datagram, [BINARY_MARKER, 1, 0xac, 0x02, 0, 1, 0xff]);
+        assert!(matches!(Type::unframe(&datagram), Ok(Type::Rel(300, p)) if p == payload));
+        assert_eq!(Type::Ping.frame(Encoding::Binary), [BINARY_MARKER, 4]);
+        assert!(matches!(
+            Type::unframe(b"UNR::hi"),
+            Ok(Type::Unrel(Payload::Text(t))) if t == "hi"
+        ));
+        assert!(Type::unframe(&[BINARY_MARKER, 9]).is_err());
+        assert!(Type::unframe(&[0xff, 0xfe]).is_err());
+
+        let (h1, h2) = gen_handlers(2008, 2009);
+        let (s1, _) = h1.get_sender_receiver();
+        let (_, r2) = h2.get_sender_receiver();
+        for is_rel in [true, false].iter() {
+            s1.send(SendPacket {
+                addrs: vec![SocketAddr::from_str("127.0.0.1:2009").unwrap()],
+                is_rel: *is_rel,
+                msg: payload.clone(),
+            })
+            .unwrap();
+            assert_eq!(
+                r2.recv().unwrap(),
+                ReceivePacket::ClientMessage(
+                    SocketAddr::from_str("127.0.0.1:2008").unwrap(),
+                    payload.clone()
+                )
+            );
+        }
+    }
 }
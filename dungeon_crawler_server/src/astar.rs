@@ -43,7 +43,7 @@ impl Ord for Path {
 }
 impl PartialOrd for Path {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(&other))
+        Some(self.cmp(other))
     }
 }
 
@@ -51,6 +51,8 @@ impl PartialOrd for Path {
 /// Finds the shortest path from `start` to `end`.
 /// Requires a collection of `paths`, and a collection
 /// of the currently `filled_spots` on the `paths`.
+/// Slow tiles, such as water, cost more to step onto,
+/// so paths around them are preferred.
 ///
 pub fn find_shortest_path(world_stage: &WorldStage, start: Vec2, end: Vec2) -> Vec<Vec2> {
     if !world_stage.is_on_path(start) || !world_stage.is_on_path(end) {
@@ -89,7 +91,7 @@ pub fn find_shortest_path(world_stage: &WorldStage, start: Vec2, end: Vec2) -> V
         for path in POS_TO_CONSIDER.iter() {
             let new_pos = u.pos + *path;
             if world_stage.is_spot_open(new_pos) || new_pos == end {
-                let new_cost = dist_map[&u.pos] + 1;
+                let new_cost = dist_map[&u.pos] + world_stage.move_cost(new_pos).unwrap_or(1);
 
                 if let Some(cost) = dist_map.get(&new_pos) {
                     if *cost <= new_cost {
@@ -132,7 +134,8 @@ pub fn find_shortest_path(world_stage: &WorldStage, start: Vec2, end: Vec2) -> V
 /// Determines which Actors (via `actor_ids`) are
 /// visible in the entity's current field of view,
 /// given their `Transform` `tr`, by specified `world_stage`
-/// position and `sight_range`
+/// position and `sight_range`. The entity sees furthest
+/// straight ahead, and not at all behind it.
 pub fn visible_actors(
     world_stage: &mut WorldStage,
     tr: Transform,
@@ -142,36 +145,25 @@ pub fn visible_actors(
     let mut ids = HashSet::new();
 
     // If the entity is facing left, set their center of view
-    // as PI (-1, 0). Otherwise, set it as 0 (1, 0)
-    let (begin, end, center) = if tr.dir == Direction::Left {
-        (PI / 2.0, 3.0 * PI / 2.0, PI)
-    } else {
-        (-PI / 2.0, PI / 2.0, 0.0)
-    };
-
-    let mut f = begin;
-    let mut i;
-    while f <= end {
-        i = 1.0;
-        // Calculate the enemy's potential to see in the given direction, using
-        // their center of view.
-        let angle_factor = (MAX_RAD - (center - f).abs()) / MAX_RAD;
-        // If the enemy can see the given spot, determine if there are any
-        // actor_ids in that spot
-        while i <= (sight_range as f32 * angle_factor) + 1.0 {
-            let spot = tr.pos + Vec2((f.cos() * i).round() as i32, (f.sin() * i).round() as i32);
-            if !world_stage.is_on_path(spot) {
-                break;
-            }
-            for id in actor_ids {
-                if let Some(actor) = world_stage.is_actor_id_on_spot(*id, spot) {
-                    ids.insert(actor.id);
-                }
-            }
+    // as (-1, 0). Otherwise, set it as (1, 0)
+    let facing = if tr.dir == Direction::Left { -1 } else { 1 };
 
-            i += 1.0;
+    for (id, spot) in world_stage.active_of(actor_ids) {
+        let offset = Vec2(spot.0 - tr.pos.0, spot.1 - tr.pos.1);
+        if spot == tr.pos {
+            continue;
+        }
+        // Calculate the enemy's potential to see in the direction of the
+        // actor, using their center of view. If the enemy can see that
+        // far, determine if the actor is in its line of sight
+        let turn = (offset.1.abs() as f32).atan2((offset.0 * facing) as f32);
+        let angle_factor = (MAX_RAD - turn) / MAX_RAD;
+        if offset.0 * facing >= 0
+            && tr.pos.distance(spot) <= sight_range as f32 * angle_factor + 1.0
+            && world_stage.can_see(tr.pos, spot)
+        {
+            ids.insert(id);
         }
-        f += PI / (8.0 * sight_range as f32);
     }
 
     ids
@@ -1,12 +1,27 @@
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
 
 use crossbeam::channel::{Receiver, Sender};
-use dungeon_generator::inst::Dungeon;
-use simple_serializer::{Deserialize, Serialize};
-use udp_server::packets::{PacketReceiver, PacketSender, ReceivePacket, SendPacket};
+use dungeon_generator::{
+    config::DungeonConfig,
+    difficulty::DifficultyBand,
+    generators::{bsp::BspGenerator, caves::CaveGenerator, walk::WalkGenerator},
+    tower::Tower,
+    traits::Generator,
+    vaults::Vault,
+};
+use udp_server::packets::{
+    Encoding, PacketReceiver, PacketSender, Payload, ReceivePacket, SendPacket,
+};
 
 use crate::{
-    events::types::Type,
+    events::{
+        protocol::{Handshake, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION},
+        types::Type,
+    },
     state::{
         manager::StateManager,
         snapshot::StateSnapshot,
@@ -14,6 +29,28 @@ use crate::{
     },
 };
 
+/// How often a passage caves in on each floor with Players on it
+const CAVE_IN_INTERVAL: Duration = Duration::from_secs(90);
+
+/// The names and patterns of the vaults stamped into each floor
+const VAULTS: [(&str, &str); 2] = [
+    (
+        "guardroom",
+        "###+###
+#M...M#
++..$..+
+#M...M#
+###+###",
+    ),
+    (
+        "shrine",
+        "##+##
+#...#
+#M$M#
+#####",
+    ),
+];
+
 ///
 /// Handles receiving data from the DatagramManager, parsing the data,
 /// converting the game state based on said data, and passing on the relevant
@@ -39,24 +76,144 @@ pub struct EventManager {
     // and removes when the DatagramManager times out a client.
     addrs: HashMap<SocketAddr, u32>,
 
+    // The encoding each client speaks - the one its `Hello`
+    // was written in. Each client is sent events in its own.
+    encodings: HashMap<SocketAddr, Encoding>,
+    // The protocol version and capabilities agreed with each
+    // client. Each client is sent events as its version would.
+    handshakes: HashMap<SocketAddr, Handshake>,
+
+    // The floor of the tower each actor is on, keyed by id.
+    // Messages about an actor are only sent to clients on its floor.
+    actor_floors: HashMap<u32, usize>,
+
     // A global instance ID counter. Incremented
     // whenever a new StateManager entity is created.
     id_next: u32,
+
+    // The config each floor of a new Tower is generated from
+    dungeon_config: DungeonConfig,
+    // The number of floors in each new Tower
+    floors: u32,
+    // The Generators each floor can be generated with.
+    // One is chosen at random for each floor.
+    generators: Vec<Box<dyn Generator>>,
+    // A hand-authored Tower, replayed each time the dungeon
+    // is completed in place of generating a new one
+    authored: Option<Tower>,
+
+    // How often a passage caves in, if ever, and when the next does
+    cave_ins: Option<Duration>,
+    cave_in_at: Instant,
 }
 
 impl EventManager {
     /// Creates a new EventHandler, and receives a DatagramHandler's
     /// client Receiver `r_from_client` and Sender `s_to_clients`.
+    /// Towers are generated 3 floors deep, each floor 75x75 with
+    /// 4 loops, 2 locked doors, 20 hazards, 5 biome regions with 60
+    /// decorations and default parameters, in a random style, with one
+    /// of the `VAULTS` stamped into each floor. Floors are generated
+    /// again, up to 10 times, unless the walk from the entrance to the
+    /// exit is 100 to 160 steps long, and passes through 2 to 40
+    /// chokepoints.
     pub fn new(r_from_client: PacketReceiver, s_to_clients: PacketSender) -> Self {
-        let dun = Dungeon::new(75, 75);
-        let state_manager = StateManager::new(dun);
-        let (s_to_state, r_from_state) = state_manager.get_sender_receiver();
+        // The entrance and exit lie on opposite edges, so every walk
+        // between them is at least 74 steps. Walks under 100 steps run
+        // almost straight across, and walks over 160 take too long to
+        // play through. At least 2 chokepoints leave the 2 locked doors
+        // somewhere to go, while more than 40 make the walk one long
+        // corridor with no way around. Most floors fall in the band
+        // within a few attempts, so 10 attempts keep a new Tower to a
+        // few hundred milliseconds in a release build - though a debug
+        // build can take a few seconds.
+        let difficulty = DifficultyBand::new()
+            .with_path_length(100..=160)
+            .with_chokepoints(2..=40);
+        Self::with_config(
+            r_from_client,
+            s_to_clients,
+            DungeonConfig::new(75, 75)
+                .with_loops(4)
+                .with_locks(2)
+                .with_hazards(20)
+                .with_biomes(5)
+                .with_decorations(60)
+                .with_vaults(
+                    VAULTS
+                        .iter()
+                        .filter_map(|(name, pattern)| Vault::parse(name, pattern))
+                        .collect(),
+                    1,
+                )
+                .with_difficulty(difficulty, 10),
+            3,
+            vec![
+                Box::new(WalkGenerator),
+                Box::new(BspGenerator::default()),
+                Box::new(CaveGenerator::default()),
+            ],
+        )
+    }
 
-        for i in 0..10 {
-            s_to_state.send(RequestType::SpawnMonster(i)).unwrap();
-        }
+    /// Creates a new EventHandler, and receives a DatagramHandler's
+    /// client Receiver `r_from_client` and Sender `s_to_clients`.
+    /// Each Tower is `floors` floors deep, each floor generated from
+    /// `dungeon_config` by one of the `generators` chosen at random,
+    /// and generated again until it falls in the config's difficulty
+    /// band, up to the config's number of attempts.
+    pub fn with_config(
+        r_from_client: PacketReceiver,
+        s_to_clients: PacketSender,
+        dungeon_config: DungeonConfig,
+        floors: u32,
+        generators: Vec<Box<dyn Generator>>,
+    ) -> Self {
+        Self::build(
+            r_from_client,
+            s_to_clients,
+            dungeon_config,
+            floors,
+            generators,
+            None,
+        )
+    }
+
+    /// Creates a new EventHandler, and receives a DatagramHandler's
+    /// client Receiver `r_from_client` and Sender `s_to_clients`.
+    /// The hand-authored `tower` is played, and played again each
+    /// time the dungeon is completed.
+    pub fn with_tower(
+        r_from_client: PacketReceiver,
+        s_to_clients: PacketSender,
+        tower: Tower,
+    ) -> Self {
+        let (width, height) = (tower.floors()[0].width(), tower.floors()[0].height());
+        Self::build(
+            r_from_client,
+            s_to_clients,
+            DungeonConfig::new(width, height),
+            tower.depth() as u32,
+            Vec::new(),
+            Some(tower),
+        )
+    }
+
+    /// Creates a new EventHandler with all of its parameters,
+    /// starting the StateManager on its first Tower.
+    fn build(
+        r_from_client: PacketReceiver,
+        s_to_clients: PacketSender,
+        dungeon_config: DungeonConfig,
+        floors: u32,
+        generators: Vec<Box<dyn Generator>>,
+        authored: Option<Tower>,
+    ) -> Self {
+        let tower = next_tower(&authored, &dungeon_config, floors, &generators);
+        let state_manager = StateManager::new(tower);
+        let (s_to_state, r_from_state) = state_manager.get_sender_receiver();
 
-        EventManager {
+        let mut event_manager = EventManager {
             state_manager,
 
             r_from_client,
@@ -66,7 +223,47 @@ impl EventManager {
             r_from_state,
 
             addrs: HashMap::new(),
-            id_next: 10,
+            encodings: HashMap::new(),
+            handshakes: HashMap::new(),
+            actor_floors: HashMap::new(),
+            // Monsters take their ids from the counter as Players do,
+            // so no block of ids needs to be set aside for them
+            id_next: 0,
+
+            dungeon_config,
+            floors,
+            generators,
+            authored,
+
+            cave_ins: Some(CAVE_IN_INTERVAL),
+            cave_in_at: Instant::now() + CAVE_IN_INTERVAL,
+        };
+        event_manager.spawn_monsters();
+        event_manager
+    }
+
+    /// Sets how often a passage caves in on each floor with
+    /// Players on it, or turns cave-ins off if `every` is `None`.
+    /// By default, a passage caves in every 90 seconds.
+    pub fn with_cave_ins(mut self, every: Option<Duration>) -> Self {
+        self.cave_ins = every;
+        if let Some(every) = every {
+            self.cave_in_at = Instant::now() + every;
+        }
+        self
+    }
+
+    /// Requests the StateManager spawn 10 monsters
+    /// on each floor of the current Tower.
+    fn spawn_monsters(&mut self) {
+        for floor in 0..self.floors.max(1) as usize {
+            for _ in 0..10 {
+                self.s_to_state
+                    .send(RequestType::SpawnMonster(self.id_next, floor))
+                    .unwrap();
+                self.actor_floors.insert(self.id_next, floor);
+                self.id_next += 1;
+            }
         }
     }
 
@@ -83,6 +280,43 @@ impl EventManager {
             if let Ok(response) = self.r_from_state.try_recv() {
                 self.parse_state_response(response);
             }
+            if let Some(every) = self.cave_ins {
+                if Instant::now() >= self.cave_in_at {
+                    self.cave_in();
+                    self.cave_in_at = Instant::now() + every;
+                }
+            }
+        }
+    }
+
+    ///
+    /// Caves in a passage on each floor with Players on it, asking the
+    /// StateManager to collapse a floor tile, chosen at random from the
+    /// floor as it is now, into wall. The StateManager refuses any
+    /// cave-in which would bury an actor or cut an exit off from the
+    /// entrance, and otherwise sends back the changed tile, to be
+    /// passed on to the clients. Floors with a Player whose client
+    /// can't be sent changed tiles never cave in, so the floor never
+    /// changes under it.
+    ///
+    fn cave_in(&mut self) {
+        let legacy = Handshake::default();
+        let mut floors = Vec::new();
+        let mut steady = Vec::new();
+        for (addr, id) in self.addrs.iter() {
+            if let Some(floor) = self.actor_floors.get(id) {
+                floors.push(*floor);
+                if !self.handshakes.get(addr).unwrap_or(&legacy).has("tiles") {
+                    steady.push(*floor);
+                }
+            }
+        }
+        floors.retain(|floor| !steady.contains(floor));
+        floors.sort_unstable();
+        floors.dedup();
+
+        for floor in floors {
+            self.s_to_state.send(RequestType::CaveIn(floor)).unwrap();
         }
     }
 
@@ -102,11 +336,10 @@ impl EventManager {
     fn drop_client(&mut self, addr: SocketAddr) -> Vec<SendPacket> {
         let mut snd_packets = Vec::new();
         if let Some(id) = self.addrs.remove(&addr) {
-            snd_packets.push(SendPacket {
-                addrs: self.all_addrs(),
-                is_rel: true,
-                msg: Type::PlayerLeft(id).serialize(),
-            });
+            self.actor_floors.remove(&id);
+            self.encodings.remove(&addr);
+            self.handshakes.remove(&addr);
+            snd_packets.extend(self.packets(self.all_addrs(), true, &Type::PlayerLeft(id)));
 
             self.s_to_state.send(RequestType::DropPlayer(id)).unwrap();
         }
@@ -117,36 +350,61 @@ impl EventManager {
     /// Parses the `msg` received from the DatagramHandler from client `addr`,
     /// determing the appropriate course of action, and performing it.
     ///
-    fn parse_client_msg(&mut self, (addr, msg): (SocketAddr, String)) -> Vec<SendPacket> {
+    fn parse_client_msg(&mut self, (addr, msg): (SocketAddr, Payload)) -> Vec<SendPacket> {
         let mut snd_packets = Vec::new();
 
-        // Parse the msg into an appropriate event
-        let event = Type::deserialize(&msg);
+        // Parse the msg into an appropriate event,
+        // ignoring any which are malformed
+        let event = match Type::from_payload(&msg) {
+            Ok(event) => event,
+            Err(_) => return snd_packets,
+        };
 
         match event {
-            // If a client has just joined and requesting a sync, inform the StateManager
-            // to add the Player and send a Welcome Packet
-            Type::Hello(name) => {
+            // If a client has just joined and requesting a sync, agree on the
+            // protocol to speak, rejecting it if there is none, then inform
+            // the StateManager to add the Player and send a Welcome Packet
+            Type::Hello(name, handshake) => {
+                let agreed = match handshake.agree() {
+                    Some(agreed) => agreed,
+                    None => {
+                        snd_packets.push(SendPacket {
+                            addrs: vec![addr],
+                            is_rel: true,
+                            msg: Type::Rejected(MIN_PROTOCOL_VERSION, PROTOCOL_VERSION)
+                                .payload(msg.encoding()),
+                        });
+                        return snd_packets;
+                    }
+                };
+                // Clients which can't show doors are never told of
+                // them, so their Players walk through closed doors
+                let sees_doors = agreed.has("doors");
+                self.encodings.insert(addr, msg.encoding());
+                self.handshakes.insert(addr, agreed.clone());
+                snd_packets.extend(self.packets(vec![addr], true, &Type::Agreed(agreed)));
+
                 self.s_to_state
-                    .send(RequestType::NewPlayer(addr, self.id_next, name))
+                    .send(RequestType::NewPlayer(addr, self.id_next, name, sees_doors))
                     .unwrap();
                 self.addrs.insert(addr, self.id_next);
+                self.actor_floors.insert(self.id_next, 0);
                 self.id_next += 1;
             }
             // If a client's position has moved, update the StateManager,
-            // and
-            Type::Moved(id, transform) => {
-                // If Moved, update in state and send to other clients
-                if self.addrs.contains_key(&addr) {
-                    self.s_to_state
-                        .send(RequestType::PlayerMoved(id, transform))
-                        .unwrap();
-                    snd_packets.push(SendPacket {
-                        addrs: self.all_addrs_but(addr),
-                        is_rel: false,
-                        msg: Type::Moved(id, transform).serialize(),
-                    });
-                }
+            // and send the new position to the other clients on its floor
+            Type::Moved(id, transform) if self.addrs.contains_key(&addr) => {
+                self.s_to_state
+                    .send(RequestType::PlayerMoved(id, transform))
+                    .unwrap();
+                snd_packets.extend(self.packets(
+                    match self.actor_floors.get(&id) {
+                        Some(floor) => self.addrs_on_but(*floor, addr),
+                        None => Vec::new(),
+                    },
+                    false,
+                    &Type::Moved(id, transform),
+                ));
             }
             _ => {}
         };
@@ -157,112 +415,166 @@ impl EventManager {
     /// information to the appropriate clients.
     fn parse_state_response(&mut self, response: ResponseType) {
         match response {
-            // If a monster has moved, inform all clients
+            // If a monster has moved, inform all clients on its floor
             ResponseType::MonsterMoved(id, transform) => {
-                self.s_to_clients
-                    .send(SendPacket {
-                        addrs: self.all_addrs(),
-                        is_rel: false,
-                        msg: Type::Moved(id, transform).serialize(),
-                    })
-                    .unwrap();
+                self.send(self.floor_addrs(id), false, &Type::Moved(id, transform));
             }
             // If a StateSnapshot was sent, create a welcome packet for the
             // client that sent `Hello`, and inform all connected clients
             // of this new Player.
             ResponseType::StateSnapshot(snapshot) => {
-                let snd_msg_packets = self.prepare_welcome_packet(snapshot);
+                let snd_msg_packets = self.prepare_welcome_packet(*snapshot);
                 for packet in snd_msg_packets.into_iter() {
                     self.s_to_clients.send(packet).unwrap();
                 }
             }
-            // If the state registered a hit, send to all clients
+            // If a Player has descended to the next floor, remove them
+            // from the clients on the floor above, and remove the other
+            // Players on that floor from the Player's own client, before
+            // welcoming them to the floor below. Monsters are left to
+            // the Welcome, which replaces the whole floor.
+            ResponseType::Descended(snapshot) => {
+                let id = snapshot.new_player.0;
+                if let Some(above) = self.actor_floors.insert(id, snapshot.floor) {
+                    for other in self.player_ids_on(above) {
+                        self.send(vec![snapshot.addr_for], true, &Type::PlayerLeft(other));
+                    }
+                    self.send(self.addrs_on(above), true, &Type::PlayerLeft(id));
+                }
+
+                let snd_msg_packets = self.prepare_welcome_packet(*snapshot);
+                for packet in snd_msg_packets.into_iter() {
+                    self.s_to_clients.send(packet).unwrap();
+                }
+            }
+            // If the state registered a hit, send to all clients on the floor
             ResponseType::Hit(att_id, def_id, cur_health) => {
-                self.s_to_clients
-                    .send(SendPacket {
-                        addrs: self.all_addrs(),
-                        is_rel: false,
-                        msg: Type::Hit(att_id, def_id, cur_health).serialize(),
-                    })
-                    .unwrap();
+                self.send(
+                    self.floor_addrs(def_id),
+                    false,
+                    &Type::Hit(att_id, def_id, cur_health),
+                );
             }
-            // If the state registered a miss, send to all clients
+            // If the state registered a miss, send to all clients on the floor
             ResponseType::Miss(att_id, def_id) => {
-                self.s_to_clients
-                    .send(SendPacket {
-                        addrs: self.all_addrs(),
-                        is_rel: false,
-                        msg: Type::Miss(att_id, def_id).serialize(),
-                    })
-                    .unwrap();
+                self.send(self.floor_addrs(def_id), false, &Type::Miss(att_id, def_id));
             }
             // If the state registered a Player has died, send to all clients
             ResponseType::Dead(id) => {
-                self.s_to_clients
-                    .send(SendPacket {
-                        addrs: self.all_addrs(),
-                        is_rel: true,
-                        msg: Type::Dead(id).serialize(),
-                    })
-                    .unwrap();
+                self.send(self.all_addrs(), true, &Type::Dead(id));
             }
             // If the state registered a Player has escaped, send to all clients
             ResponseType::Escaped(id) => {
-                self.s_to_clients
-                    .send(SendPacket {
-                        addrs: self.all_addrs(),
-                        is_rel: true,
-                        msg: Type::Escaped(id).serialize(),
-                    })
-                    .unwrap();
+                self.send(self.all_addrs(), true, &Type::Escaped(id));
+            }
+            // If a Player has picked up a key, inform all clients on
+            // their floor that its door has opened
+            ResponseType::DoorOpened(id, door) => {
+                self.send(self.floor_addrs(id), true, &Type::DoorOpened(door));
+            }
+            // If tiles of a floor have changed, inform all clients on that floor
+            ResponseType::TilesChanged(floor, changed) => {
+                for (pos, tile) in changed {
+                    self.send(self.addrs_on(floor), true, &Type::TileChanged(pos, tile));
+                }
             }
             // If the state registered that all Players are either dead or escaped,
-            // reset the StateManager, creating a new dungeon.
+            // reset the StateManager, creating a new tower.
             ResponseType::DungeonComplete => {
-                self.s_to_clients
-                    .send(SendPacket {
-                        addrs: self.all_addrs(),
-                        is_rel: true,
-                        msg: Type::DungeonComplete.serialize(),
-                    })
-                    .unwrap();
+                self.send(self.all_addrs(), true, &Type::DungeonComplete);
 
                 std::thread::sleep(Duration::from_secs(5));
 
-                self.state_manager = StateManager::new(Dungeon::new(75, 75));
+                self.state_manager = StateManager::new(next_tower(
+                    &self.authored,
+                    &self.dungeon_config,
+                    self.floors,
+                    &self.generators,
+                ));
                 let (s, r) = self.state_manager.get_sender_receiver();
                 self.s_to_state = s;
                 self.r_from_state = r;
-                self.s_to_clients
-                    .send(SendPacket {
-                        addrs: self.all_addrs(),
-                        is_rel: true,
-                        msg: Type::Reconnect.serialize(),
-                    })
-                    .unwrap();
-
-                for i in self.id_next..self.id_next + 10 {
-                    self.s_to_state.send(RequestType::SpawnMonster(i)).unwrap();
-                }
+                self.actor_floors.clear();
+                self.send(self.all_addrs(), true, &Type::Reconnect);
 
-                self.id_next += 10;
+                self.spawn_monsters();
             }
             _ => {}
         }
     }
 
+    ///
+    /// Packets carrying `event` to each of the `addrs`, written in the
+    /// encoding and version of the protocol each client speaks. Clients
+    /// whose version predates the event, or who lack the capability it
+    /// needs, aren't sent it.
+    ///
+    fn packets(&self, addrs: Vec<SocketAddr>, is_rel: bool, event: &Type) -> Vec<SendPacket> {
+        let legacy = Handshake::default();
+        let mut groups: HashMap<(Encoding, u32), Vec<SocketAddr>> = HashMap::new();
+        for addr in addrs {
+            let encoding = self.encodings.get(&addr).cloned().unwrap_or(Encoding::Text);
+            let handshake = self.handshakes.get(&addr).unwrap_or(&legacy);
+            if event.capability().is_none_or(|c| handshake.has(c)) {
+                groups
+                    .entry((encoding, handshake.version))
+                    .or_default()
+                    .push(addr);
+            }
+        }
+        groups
+            .into_iter()
+            .filter_map(|((encoding, version), addrs)| {
+                Some(SendPacket {
+                    addrs,
+                    is_rel,
+                    msg: event.payload_for(encoding, version)?,
+                })
+            })
+            .collect()
+    }
+    /// Sends `event` to each of the `addrs`
+    fn send(&self, addrs: Vec<SocketAddr>, is_rel: bool, event: &Type) {
+        for packet in self.packets(addrs, is_rel, event) {
+            self.s_to_clients.send(packet).unwrap();
+        }
+    }
+
     /// Retrieve all `SocketAddr`s attached to the EventHandler
     fn all_addrs(&self) -> Vec<SocketAddr> {
-        self.addrs.clone().into_iter().map(|(k, _)| k).collect()
+        self.addrs.keys().cloned().collect()
     }
-    /// Retrieve all `SocketAddr`'s attach to the EventHandler,
-    /// exepct the `addr` provided.
-    fn all_addrs_but(&self, addr: SocketAddr) -> Vec<SocketAddr> {
+    /// Retrieve the `SocketAddr`s of all clients whose
+    /// Player is on the given `floor`
+    fn addrs_on(&self, floor: usize) -> Vec<SocketAddr> {
         self.addrs
-            .clone()
+            .iter()
+            .filter(|(_, id)| self.actor_floors.get(id) == Some(&floor))
+            .map(|(addr, _)| *addr)
+            .collect()
+    }
+    /// Retrieve the `SocketAddr`s of all clients on the
+    /// same floor as the actor with the given `id`
+    fn floor_addrs(&self, id: u32) -> Vec<SocketAddr> {
+        match self.actor_floors.get(&id) {
+            Some(floor) => self.addrs_on(*floor),
+            None => Vec::new(),
+        }
+    }
+    /// Retrieve the ids of all Players on the given `floor`
+    fn player_ids_on(&self, floor: usize) -> Vec<u32> {
+        self.addrs
+            .values()
+            .filter(|id| self.actor_floors.get(id) == Some(&floor))
+            .cloned()
+            .collect()
+    }
+    /// Retrieve the `SocketAddr`s of all clients whose Player
+    /// is on the given `floor`, except the `addr` provided.
+    fn addrs_on_but(&self, floor: usize, addr: SocketAddr) -> Vec<SocketAddr> {
+        self.addrs_on(floor)
             .into_iter()
-            .filter(|(a, _)| *a != addr)
-            .map(|(k, _)| k)
+            .filter(|a| *a != addr)
             .collect()
     }
 
@@ -275,49 +587,73 @@ impl EventManager {
 
         // Send all MonsterInstance information to the client
         for monster in snapshot.monsters {
-            snd_packets.push(SendPacket {
-                addrs: vec![snapshot.addr_for],
-                is_rel: true,
-                msg: Type::NewMonster(monster.0, monster.1, monster.2).serialize(),
-            });
+            snd_packets.extend(self.packets(
+                vec![snapshot.addr_for],
+                true,
+                &Type::NewMonster(monster.0, monster.1, monster.2),
+            ));
         }
 
         for player in snapshot.other_players {
-            snd_packets.push(SendPacket {
-                addrs: vec![snapshot.addr_for],
-                is_rel: true,
-                msg: Type::NewPlayer(player.0, player.1, player.2).serialize(),
-            });
+            snd_packets.extend(self.packets(
+                vec![snapshot.addr_for],
+                true,
+                &Type::NewPlayer(player.0, player.1, player.2),
+            ));
+        }
+
+        for door in snapshot.opened_doors {
+            snd_packets.extend(self.packets(
+                vec![snapshot.addr_for],
+                true,
+                &Type::DoorOpened(door),
+            ));
         }
 
         for player_ts in snapshot.all_player_ts {
-            snd_packets.push(SendPacket {
-                addrs: vec![snapshot.addr_for],
-                is_rel: true,
-                msg: Type::Moved(player_ts.0, player_ts.1).serialize(),
-            });
+            snd_packets.extend(self.packets(
+                vec![snapshot.addr_for],
+                true,
+                &Type::Moved(player_ts.0, player_ts.1),
+            ));
         }
 
-        // Send to all connected clients the
-        // new player info
-        snd_packets.push(SendPacket {
-            addrs: self.all_addrs_but(snapshot.addr_for),
-            is_rel: true,
-            msg: Type::NewPlayer(
+        // Send to all connected clients on the
+        // same floor the new player info
+        snd_packets.extend(self.packets(
+            self.addrs_on_but(snapshot.floor, snapshot.addr_for),
+            true,
+            &Type::NewPlayer(
                 snapshot.new_player.0,
                 snapshot.new_player.1,
                 snapshot.new_player.2,
-            )
-            .serialize(),
-        });
-        // Send the Welcome packet to the incoming client,
-        // which contains the dungeon information
-        snd_packets.push(SendPacket {
-            addrs: vec![snapshot.addr_for],
-            is_rel: true,
-            msg: Type::Welcome(snapshot.new_player.0, snapshot.dungeon.serialize()).serialize(),
-        });
+            ),
+        ));
+        // Send the Welcome packet to the incoming client, which
+        // contains the dungeon information, written in the client's
+        // encoding and version of the protocol - clients from before
+        // the handshake get only the paths, entrance and exit
+        snd_packets.extend(self.packets(
+            vec![snapshot.addr_for],
+            true,
+            &Type::Welcome(snapshot.new_player.0, Box::new(snapshot.dungeon)),
+        ));
 
         snd_packets
     }
 }
+
+/// The next Tower to be played - a copy of the `authored` Tower if
+/// there is one, or else a new Tower of `floors` floors, generated
+/// from `config` by the `generators`, within its difficulty band.
+fn next_tower(
+    authored: &Option<Tower>,
+    config: &DungeonConfig,
+    floors: u32,
+    generators: &[Box<dyn Generator>],
+) -> Tower {
+    match authored {
+        Some(tower) => tower.clone(),
+        None => Tower::generate(config, floors, generators),
+    }
+}
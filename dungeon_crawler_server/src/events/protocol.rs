@@ -0,0 +1,81 @@
+//! Protocol Versions and Capabilities
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+//!
+//! Clients say which version of the protocol they speak, and which
+//! optional events they understand, in their `Hello`. The server
+//! agrees on the newest version both sides speak, and the capabilities
+//! both sides support, and writes every event to the client as that
+//! version would. Clients from before versions were introduced send no
+//! handshake, and speak version 1 with no capabilities.
+
+use simple_serializer::{Decode, Deserialize, Encode, Serialize};
+
+/// The newest version of the protocol the server speaks.
+/// Version 2 added the handshake itself.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The oldest version of the protocol the server still speaks
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// The capabilities the server supports - each one the
+/// name of an optional event a client can ask to be sent
+pub const CAPABILITIES: [&str; 2] = ["doors", "tiles"];
+
+///
+/// The version of the protocol, and the capabilities, a client
+/// speaks - or which the server has agreed to speak with it.
+///
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode)]
+pub struct Handshake {
+    pub version: u32,
+    pub capabilities: Vec<String>,
+}
+
+impl Handshake {
+    /// The handshake of a client speaking the newest protocol,
+    /// with every capability the server supports
+    pub fn current() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+    ///
+    /// Agrees on the newest version both the client and the server
+    /// speak, and the capabilities both support. Returns `None` if
+    /// the client only speaks versions older than the server does.
+    ///
+    pub fn agree(&self) -> Option<Self> {
+        let version = self.version.min(PROTOCOL_VERSION);
+        if version < MIN_PROTOCOL_VERSION {
+            return None;
+        }
+        Some(Self {
+            version,
+            capabilities: CAPABILITIES
+                .iter()
+                .filter(|c| self.has(c))
+                .map(|c| c.to_string())
+                .collect(),
+        })
+    }
+    /// Tests if the handshake includes the `capability`
+    pub fn has(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
+
+///
+/// Clients which send no handshake came before it, so
+/// speak the first version, with no capabilities.
+///
+impl Default for Handshake {
+    fn default() -> Self {
+        Self {
+            version: 1,
+            capabilities: Vec::new(),
+        }
+    }
+}
@@ -1,19 +1,22 @@
-use std::str::FromStr;
+use dungeon_generator::{inst::Dungeon, tiles::Tile};
 
-use crate::state::transforms::{
-    transform::{Direction, Transform},
-    vec2::Vec2,
+use crate::{
+    events::protocol::Handshake,
+    state::transforms::{transform::Transform, vec2::Vec2},
 };
-use simple_serializer::{Deserialize, Serialize};
+use simple_serializer::{
+    Decode, Deserialize, DeserializeError, Encode, Serialize, TryDeserialize, Versioned,
+};
+use udp_server::packets::{Encoding, Payload};
 
 ///
 /// Enum for all possible Event Types the EventManager
 /// can parse.
 ///
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize, Encode, Decode, Versioned)]
 pub enum Type {
-    Hello(String),        // a client joining the game                         (name)
-    Welcome(u32, String), // info. the server relays to the client for syncing (id, dun_data)
+    Hello(String, #[serialize(since = 2)] Handshake), // a client joining the game (name, handshake)
+    Welcome(u32, #[serialize(versioned)] Box<Dungeon>), // info. the server relays to the client for syncing (id, dungeon)
     NewPlayer(u32, String, Vec2), // informs clients of a new Player,                  (id, name, pos)
     NewMonster(u32, u32, Vec2), // informs clients of a new Monster                  (temp_id, inst_id, pos)
     Moved(u32, Transform),      // informs server / clients of moved entity          (id, transform)
@@ -22,68 +25,46 @@ pub enum Type {
     Miss(u32, u32),     // informs clients that a Player has been missed     (attId, defId)
     Dead(u32),          // informs clients that a Player has died            (id)
     Escaped(u32),       // informs clients that a Player has escaped         (id)
-    DungeonComplete,    // informs clients that the Dungeon has been completed
-    Reconnect,          // requests that the clients reconnect - new StateManager
-    Dropped,            // a dropped packet
+    DoorOpened(Vec2),   // informs clients that a locked door has opened     (pos)
+    TileChanged(Vec2, Tile), // informs clients that a tile of their floor has changed (pos, tile)
+    #[serialize(trailing)]
+    DungeonComplete, // informs clients that the Dungeon has been completed
+    #[serialize(trailing)]
+    Reconnect, // requests that the clients reconnect - new StateManager
+    #[serialize(since = 2)]
+    Agreed(Handshake), // informs a client of the protocol agreed with it  (handshake)
+    Rejected(u32, u32), // rejects a client speaking no version the server does (min, max version)
 }
 
-impl Serialize for Type {
-    type SerializeTo = String;
-    fn serialize(&self) -> String {
+impl Type {
+    /// Writes the event as a `Payload` in the `encoding`
+    pub fn payload(&self, encoding: Encoding) -> Payload {
+        match encoding {
+            Encoding::Text => Payload::Text(self.serialize()),
+            Encoding::Binary => Payload::Binary(self.to_bytes()),
+        }
+    }
+    /// Writes the event as a `Payload` in the `encoding`, as the
+    /// `version` of the protocol would, or `None` if it didn't exist yet
+    pub fn payload_for(&self, encoding: Encoding, version: u32) -> Option<Payload> {
+        match encoding {
+            Encoding::Text => self.serialize_for(version).map(Payload::Text),
+            Encoding::Binary => self.to_bytes_for(version).map(Payload::Binary),
+        }
+    }
+    /// The capability a client must have to be sent the event, if any
+    pub fn capability(&self) -> Option<&'static str> {
         match self {
-            Type::Hello(name) => format!("Hello::{}", name),
-            Type::Welcome(id, dun) => format!("Welcome::{}::{}", id, dun),
-            Type::NewPlayer(id, name, pos) => {
-                format!("NewPlayer::{}::{}::{}", id, name, pos.serialize())
-            }
-            Type::NewMonster(t_id, i_id, pos) => {
-                format!("NewMonster::{}::{}::{}", t_id, i_id, pos.serialize())
-            }
-            Type::Moved(id, transform) => format!("Moved::{}::{}", id, transform.serialize()),
-            Type::PlayerLeft(id) => format!("PlayerLeft::{}", id),
-            Type::Hit(att_id, def_id, cur_health) => {
-                format!("Hit::{}::{}::{}", att_id, def_id, cur_health)
-            }
-            Type::Miss(att_id, def_id) => format!("Miss::{}::{}", att_id, def_id),
-            Type::Dead(id) => format!("Dead::{}", id),
-            Type::Escaped(id) => format!("Escaped::{}", id),
-            Type::DungeonComplete => "DungeonComplete::".to_string(),
-            Type::Reconnect => "Reconnect::".to_string(),
-            Type::Dropped => "Drop".to_string(),
+            Type::DoorOpened(_) => Some("doors"),
+            Type::TileChanged(..) => Some("tiles"),
+            _ => None,
         }
     }
-}
-
-impl Deserialize for Type {
-    type DeserializeTo = Type;
-
-    fn deserialize(from: &str) -> Self::DeserializeTo {
-        let segs: Vec<&str> = from.split("::").collect();
-
-        match segs[0].trim() {
-            "Hello" => Type::Hello(segs[1].to_string()),
-            "Left" => {
-                if let Ok(id) = u32::from_str(segs[1].trim()) {
-                    Type::PlayerLeft(id)
-                } else {
-                    Type::Dropped
-                }
-            }
-            "Moved" => {
-                match (
-                    u32::from_str(segs[1]),
-                    i32::from_str(segs[2]),
-                    i32::from_str(segs[3]),
-                    u32::from_str(segs[4]),
-                ) {
-                    (Ok(id), Ok(x), Ok(y), Ok(d)) => Type::Moved(
-                        id,
-                        Transform::with_values(Vec2(x, y), Direction::from_u32(d)),
-                    ),
-                    _ => Type::Dropped,
-                }
-            }
-            _ => Type::Dropped,
+    /// Reads an event from a `payload` in either encoding
+    pub fn from_payload(payload: &Payload) -> Result<Self, DeserializeError> {
+        match payload {
+            Payload::Text(text) => Type::try_deserialize(text),
+            Payload::Binary(bytes) => Type::from_bytes(bytes),
         }
     }
 }
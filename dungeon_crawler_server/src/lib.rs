@@ -1,5 +1,6 @@
 pub mod events {
     pub mod manager;
+    pub mod protocol;
     pub mod types;
 }
 
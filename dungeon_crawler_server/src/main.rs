@@ -3,13 +3,33 @@
 //! Christian Schmid - June 2021
 //! CS510 - Rust Programming
 
+use std::io::{Error, ErrorKind};
+
 use dungeon_crawler_server::events::manager::EventManager;
+use dungeon_generator::{inst::Dungeon, tower::Tower};
 use udp_server::manager::DatagramManager;
 
 fn main() -> Result<(), std::io::Error> {
+    // Any arguments are the paths of hand-authored dungeon
+    // files, played as the floors of a tower from the top down
+    let floors = std::env::args()
+        .skip(1)
+        .map(Dungeon::load)
+        .collect::<Result<Vec<_>, _>>()?;
+
     let dgm_h = DatagramManager::new(2000)?;
     let (s, r) = dgm_h.get_sender_receiver();
 
-    let mut evt_h = EventManager::new(r, s);
+    let mut evt_h = if floors.is_empty() {
+        EventManager::new(r, s)
+    } else {
+        let tower = Tower::from_floors(floors).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "the exit of each floor must line up with the entrance of the next",
+            )
+        })?;
+        EventManager::with_tower(r, s, tower)
+    };
     evt_h.start();
 }
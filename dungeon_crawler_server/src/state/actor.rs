@@ -4,7 +4,7 @@
 //! Christian Schmid - June 2021
 //! CS510 - Rust Programming
 
-use simple_serializer::Serialize;
+use simple_serializer::{Deserialize, Serialize};
 
 use crate::state::transforms::transform::Transform;
 
@@ -18,27 +18,16 @@ use super::{
 /// they are `Active`, `Dead`, or `Escaped` (from the
 /// `Dungeon`)
 ///
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Status {
+    #[serialize(tag = "0")]
     Active,
+    #[serialize(tag = "1")]
     Dead,
+    #[serialize(tag = "2")]
     Escaped,
 }
 
-impl Serialize for Status {
-    type SerializeTo = String;
-    fn serialize(&self) -> Self::SerializeTo {
-        format!(
-            "{}",
-            match self {
-                Status::Active => 0,
-                Status::Dead => 1,
-                Status::Escaped => 2,
-            }
-        )
-    }
-}
-
 ///
 /// The definitions for an entity in a
 /// `WorldStage`. Contains all information
@@ -53,6 +42,9 @@ pub struct Actor {
     pub tr: Transform,
     pub actor_id: ActorId,
     pub status: Status,
+    // Whether the Actor walks through closed doors - true of
+    // Players whose clients were never told of the doors
+    pub through_doors: bool,
 }
 
 impl Actor {
@@ -64,6 +56,7 @@ impl Actor {
             tr,
             actor_id,
             status: Status::Active,
+            through_doors: false,
         }
     }
 }
@@ -64,10 +64,14 @@ pub static MELEE_COMBAT: IndependentPackage<dyn AI> = IndependentPackage {
         // Get the entity and its target transforms
         entity.reset_last_sighting();
         let entity_tr = world_stage.actor(entity.id()).unwrap().tr;
-        let target_tr = world_stage
-            .actor(entity.follow_target().unwrap())
-            .unwrap()
-            .tr;
+        // The target may have left the floor since it was spotted
+        let target_tr = match world_stage.actor(entity.follow_target().unwrap()) {
+            Some(target) => target.tr,
+            None => {
+                entity.stop_following();
+                return;
+            }
+        };
 
         // Establish the entity's first path as directly towards
         // the target.
@@ -83,8 +87,18 @@ pub static MELEE_COMBAT: IndependentPackage<dyn AI> = IndependentPackage {
         // Get the entity transform, and check if there are any visible players
         // in its view.
         let ent_tr = world_stage.actor(entity.id()).unwrap().tr;
-        let target_id = entity.follow_target().unwrap();
-        let target_tr = world_stage.actor(target_id).unwrap().tr;
+        // If the target has left the floor, or the game, stop combat
+        let target_id = match entity.follow_target() {
+            Some(target_id) => target_id,
+            None => return AIPackageResult::Abort,
+        };
+        let target_tr = match world_stage.actor(target_id) {
+            Some(target) => target.tr,
+            None => {
+                entity.stop_following();
+                return AIPackageResult::Abort;
+            }
+        };
         // Retrieve the targets currently in sight of the Entity
         let targets_in_sight = visible_actors(
             world_stage,
@@ -90,8 +90,8 @@ impl<'a, Entity: ?Sized> IndependentManager<'a, Entity> {
                 // If count becomes <= 0, choose the current package to run
                 count -= package.pick_count as i32;
                 if count <= 0 {
-                    let sel_pkg = self.packages[index as usize];
-                    self.selected = Some(index as usize);
+                    let sel_pkg = self.packages[index];
+                    self.selected = Some(index);
 
                     // Run the on start method for the package
                     // And establish the start time
@@ -4,14 +4,14 @@
 //! Christian Schmid - June, 2021
 //! CS510 - Programming Rust
 
-use std::{collections::HashMap, time::Duration};
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
 
 use crate::state::{
     ai::ai_package_collections::{IDLE, MELEE_COMBAT},
     types::ResponseType,
 };
 use crossbeam::channel::{Receiver, Sender};
-use dungeon_generator::inst::Dungeon;
+use dungeon_generator::{biomes::Biome, rooms::RoomKind, tower::Tower};
 use rand::prelude::*;
 use simple_serializer::Serialize;
 
@@ -52,6 +52,7 @@ static MONSTERS: [Monster; 1] = [Monster {
     name: "Goblin",
     spawn_chance: 10,
     sight_range: 3,
+    biomes: &[Biome::Cave, Biome::Sewer],
 }];
 
 ///
@@ -66,11 +67,11 @@ pub struct StateManager {
 
 impl StateManager {
     ///
-    /// Create a new `StateHandler` with the supplied `dungeon`,
-    /// starting a new state event loop
+    /// Create a new `StateHandler` with the supplied `tower`
+    /// of dungeon floors, starting a new state event loop
     ///
-    pub fn new(dungeon: Dungeon) -> Self {
-        let (s_to_state, r_at_event) = state_loop(dungeon);
+    pub fn new(tower: Tower) -> Self {
+        let (s_to_state, r_at_event) = state_loop(tower);
 
         Self {
             s_to_state,
@@ -89,32 +90,39 @@ impl StateManager {
 /// The state loop, ran on a separate thread.
 /// Receives updates from the `EventManager` and adjusts states
 /// accordingly. Runs game logic.
-fn state_loop(dungeon: Dungeon) -> (Sender<RequestType>, Receiver<ResponseType>) {
+fn state_loop(tower: Tower) -> (Sender<RequestType>, Receiver<ResponseType>) {
     // Create the mpsc channels connecting the EventHandler to the StateHandler,
     // and vice-versa
     let (s_to_state, r_at_state) = crossbeam::channel::unbounded();
     let (s_to_event, r_at_event) = crossbeam::channel::unbounded();
 
     std::thread::spawn(move || {
-        // The collection of monsters, keyed by position
-        let mut monsters = HashMap::<Vec2, MonsterInstance>::new();
+        // The collection of monsters on each floor, keyed by position
+        let mut monsters = vec![HashMap::<Vec2, MonsterInstance>::new(); tower.depth()];
         // The collection of players, keyed by id
         let mut players = HashMap::<u32, Player>::new();
+        // The address of each player's client, keyed by id
+        let mut addrs = HashMap::<u32, SocketAddr>::new();
+        // The floor each actor is on, keyed by id
+        let mut floors = HashMap::<u32, usize>::new();
         // The collection of AI Managers
         let mut ai_managers = HashMap::<u32, IndependentManager<dyn AI>>::new();
 
-        // Create the new WorldStage, using the supplied dungeon
-        let mut world_stage = WorldStage::new(
-            dungeon
-                .paths_ref()
-                .iter()
-                .cloned()
-                .map(|s| Vec2(s.0, s.1))
-                .collect(),
-            Vec2::from_tuple(dungeon.entrance),
-            Vec2::from_tuple(dungeon.exit),
-            s_to_event.clone(),
-        );
+        // Create a WorldStage for each floor of the tower. Every
+        // floor but the last has stairs down to the next.
+        let mut stages: Vec<WorldStage> = tower
+            .floors()
+            .iter()
+            .enumerate()
+            .map(|(depth, floor)| {
+                let stage = WorldStage::new(floor, s_to_event.clone());
+                if depth + 1 < tower.depth() {
+                    stage.with_stairs()
+                } else {
+                    stage
+                }
+            })
+            .collect();
 
         // Begin the stateloop, which will not cease until the program ends
         loop {
@@ -122,66 +130,78 @@ fn state_loop(dungeon: Dungeon) -> (Sender<RequestType>, Receiver<ResponseType>)
             if let Ok(request) = r_at_state.try_recv() {
                 match request {
                     // If a new player has been added, insert them into the
-                    // WorldState and send a StateSnapshot to the EventManager,
-                    // to be forwarded to the player
-                    RequestType::NewPlayer(addr, id, name) => {
-                        players.insert(id, Player::new(id, name.clone()));
-                        world_stage.add(
+                    // top floor's WorldStage and send a StateSnapshot to the
+                    // EventManager, to be forwarded to the player. Players
+                    // whose clients don't see doors walk through them.
+                    RequestType::NewPlayer(addr, id, name, sees_doors) => {
+                        players.insert(id, Player::new(id, name));
+                        addrs.insert(id, addr);
+                        floors.insert(id, 0);
+                        let mut actor = Actor::new(
                             id,
-                            Actor::new(
-                                id,
-                                Stats::new(10, 10, 10),
-                                Attributes::new(5, 5, 5),
-                                Transform::with_values(
-                                    Vec2::from_tuple(dungeon.entrance),
-                                    Direction::Left,
-                                ),
-                                ActorId::Player,
+                            Stats::new(10, 10, 10),
+                            Attributes::new(5, 5, 5),
+                            Transform::with_values(
+                                Vec2::from_tuple(tower.floor(0).unwrap().entrance),
+                                Direction::Left,
                             ),
+                            ActorId::Player,
                         );
+                        actor.through_doors = !sees_doors;
+                        stages[0].add(id, actor);
+                        let snapshot =
+                            snapshot(addr, id, 0, &mut stages, &players, &floors, &monsters);
                         s_to_event
-                            .send(ResponseType::StateSnapshot(StateSnapshot {
-                                addr_for: addr,
-                                new_player: (id, name, world_stage.pos(id).unwrap()),
-                                other_players: players
-                                    .values()
-                                    .filter(|p| p.id != id)
-                                    .cloned()
-                                    .map(|p| {
-                                        (
-                                            p.id,
-                                            p.name.clone(),
-                                            world_stage.pos(p.id).unwrap(),
-                                            world_stage.actor(p.id).unwrap().status.serialize(),
-                                        )
-                                    })
-                                    .collect(),
-                                monsters: monsters
-                                    .values()
-                                    .map(|m| {
-                                        (m.template.id, m.id(), world_stage.pos(m.id()).unwrap())
-                                    })
-                                    .collect(),
-                                dungeon: dungeon.clone(),
-                                all_player_ts: world_stage.clone_transforms(),
-                            }))
+                            .send(ResponseType::StateSnapshot(Box::new(snapshot)))
                             .unwrap();
                     }
                     // If a Player has been dropped, remove them from the
-                    // WorldStage and players collection
+                    // WorldStage of their floor and players collection
                     RequestType::DropPlayer(id) => {
-                        world_stage.remove(id);
+                        if let Some(floor) = floors.remove(&id) {
+                            stages[floor].remove(id);
+                        }
                         players.remove(&id);
+                        addrs.remove(&id);
                     }
-                    // If a Player has move, update their
-                    // Transform in the WorldStage
+                    // If a Player has move, update their Transform in the
+                    // WorldStage. If they have reached the stairs, move them
+                    // down to the next floor and send them a StateSnapshot of it.
                     RequestType::PlayerMoved(id, new_t) => {
-                        world_stage.update_pl_tr(id, new_t);
+                        let floor = match floors.get(&id) {
+                            Some(floor) => *floor,
+                            None => continue,
+                        };
+                        stages[floor].update_pl_tr(id, new_t);
+
+                        if stages[floor].is_on_stairs(id) {
+                            let mut actor = stages[floor].take(id).unwrap();
+                            actor.tr.pos =
+                                Vec2::from_tuple(tower.floor(floor + 1).unwrap().entrance);
+                            stages[floor + 1].add(id, actor);
+                            floors.insert(id, floor + 1);
+
+                            let snapshot = snapshot(
+                                addrs[&id],
+                                id,
+                                floor + 1,
+                                &mut stages,
+                                &players,
+                                &floors,
+                                &monsters,
+                            );
+                            s_to_event
+                                .send(ResponseType::Descended(Box::new(snapshot)))
+                                .unwrap();
+                        }
                     }
                     // If its been requested to spawn a new monster
-                    // generate a monster and send its information back
-                    RequestType::SpawnMonster(id) => {
-                        let monster = spawn_monster(id, &mut world_stage);
+                    // generate a monster on the given floor and send
+                    // its information back
+                    RequestType::SpawnMonster(id, floor) => {
+                        let floor = floor.min(stages.len() - 1);
+                        let world_stage = &mut stages[floor];
+                        let monster = spawn_monster(id, world_stage);
                         s_to_event
                             .send(ResponseType::NewMonster(
                                 monster.template.id,
@@ -195,7 +215,28 @@ fn state_loop(dungeon: Dungeon) -> (Sender<RequestType>, Receiver<ResponseType>)
                             monster.instance_id,
                             IndependentManager::new(vec![&IDLE, &MELEE_COMBAT]),
                         );
-                        monsters.insert(world_stage.pos(monster.id()).unwrap(), monster);
+                        floors.insert(id, floor);
+                        monsters[floor].insert(world_stage.pos(monster.id()).unwrap(), monster);
+                    }
+                    // If tiles of a floor are to be changed, change them
+                    // on its WorldStage, and send back the tiles which did
+                    RequestType::ChangeTiles(floor, changes, connectivity) => {
+                        let changed = stages
+                            .get_mut(floor)
+                            .and_then(|stage| stage.set_tiles(&changes, connectivity));
+                        if let Some(changed) = changed.filter(|c| !c.is_empty()) {
+                            s_to_event
+                                .send(ResponseType::TilesChanged(floor, changed))
+                                .unwrap();
+                        }
+                    }
+                    RequestType::CaveIn(floor) => {
+                        let changed = stages.get_mut(floor).and_then(|stage| stage.cave_in());
+                        if let Some(changed) = changed {
+                            s_to_event
+                                .send(ResponseType::TilesChanged(floor, changed))
+                                .unwrap();
+                        }
                     }
                     // If the program is ending, break from the loop
                     RequestType::Abort => break,
@@ -207,20 +248,22 @@ fn state_loop(dungeon: Dungeon) -> (Sender<RequestType>, Receiver<ResponseType>)
             if players.is_empty() {
                 continue;
             }
-            // Run each monster's AI
-            for monster in monsters.values_mut() {
-                let index = monster.instance_id;
-                ai_managers
-                    .get_mut(&index)
-                    .unwrap()
-                    .run(&mut world_stage, monster);
+            // Run each monster's AI, on the WorldStage of its floor
+            for (world_stage, monsters) in stages.iter_mut().zip(monsters.iter_mut()) {
+                for monster in monsters.values_mut() {
+                    let index = monster.instance_id;
+                    ai_managers
+                        .get_mut(&index)
+                        .unwrap()
+                        .run(world_stage, monster);
+                }
             }
             // Check if the dungeon is complete. If so,
             // update the EventManager to inform the connected clients
-            if is_dungeon_complete(&mut world_stage, &players) {
+            if is_dungeon_complete(&mut stages, &players, &floors) {
                 s_to_event.send(ResponseType::DungeonComplete).unwrap();
                 for pl in players.values() {
-                    world_stage.actor(pl.id).unwrap().status = Status::Active;
+                    stages[floors[&pl.id]].actor(pl.id).unwrap().status = Status::Active;
                 }
             }
 
@@ -234,28 +277,89 @@ fn state_loop(dungeon: Dungeon) -> (Sender<RequestType>, Receiver<ResponseType>)
     (s_to_state, r_at_event)
 }
 
+/// Takes a `StateSnapshot` of the given `floor` for the player
+/// with the given `id`, to be sent to their client at `addr`.
+#[allow(clippy::too_many_arguments)]
+fn snapshot(
+    addr: SocketAddr,
+    id: u32,
+    floor: usize,
+    stages: &mut [WorldStage],
+    players: &HashMap<u32, Player>,
+    floors: &HashMap<u32, usize>,
+    monsters: &[HashMap<Vec2, MonsterInstance>],
+) -> StateSnapshot {
+    let world_stage = &mut stages[floor];
+    StateSnapshot {
+        addr_for: addr,
+        new_player: (id, players[&id].name.clone(), world_stage.pos(id).unwrap()),
+        other_players: players
+            .values()
+            .filter(|p| p.id != id && floors.get(&p.id) == Some(&floor))
+            .map(|p| {
+                (
+                    p.id,
+                    p.name.clone(),
+                    world_stage.pos(p.id).unwrap(),
+                    world_stage.actor(p.id).unwrap().status.serialize(),
+                )
+            })
+            .collect(),
+        monsters: monsters[floor]
+            .values()
+            .map(|m| (m.template.id, m.id(), world_stage.pos(m.id()).unwrap()))
+            .collect(),
+        opened_doors: world_stage.opened_doors().to_vec(),
+        floor,
+        dungeon: world_stage.dungeon().clone(),
+        all_player_ts: world_stage.clone_transforms(),
+    }
+}
+
 /// Checks if the dungeon is complete by determining if all `players`
-/// are either `Escaped` or `Dead`
-fn is_dungeon_complete(world_stage: &mut WorldStage, players: &HashMap<u32, Player>) -> bool {
+/// are either `Escaped` or `Dead`, on whichever of the `stages` they
+/// are on.
+fn is_dungeon_complete(
+    stages: &mut [WorldStage],
+    players: &HashMap<u32, Player>,
+    floors: &HashMap<u32, usize>,
+) -> bool {
     !players.is_empty()
         && players
             .values()
-            .all(|pl| world_stage.actor(pl.id()).unwrap().status != Status::Active)
+            .all(|pl| stages[floors[&pl.id()]].actor(pl.id()).unwrap().status != Status::Active)
 }
 
-/// Generates a single monster at random with the given `id`
+/// Generates a single monster at random with the given `id`,
+/// favouring the monsters at home in the biome it spawns in
 fn spawn_monster(id: u32, world_stage: &mut WorldStage) -> MonsterInstance {
-    // Find the sum of all monster's spawn chances
-    let rand_count: u32 = MONSTERS.iter().map(|m| m.spawn_chance).sum();
+    // Find a spot for the MonsterInstance. Vault spawns are
+    // filled first, then if the dungeon has rooms, the monster is
+    // spawned in any room but the start room.
+    let open_spot = world_stage
+        .open_vault_spawn()
+        .or_else(|| {
+            world_stage.open_spot_in(&[
+                RoomKind::Normal,
+                RoomKind::Treasure,
+                RoomKind::Boss,
+                RoomKind::Exit,
+            ])
+        })
+        .unwrap_or_else(|| world_stage.open_spot());
+    let biome = world_stage.dungeon().biome_at((open_spot.0, open_spot.1));
+
+    // Find the sum of all monster's spawn weights in the biome
+    let rand_count: u32 = MONSTERS.iter().map(|m| m.spawn_weight(biome)).sum();
 
     // Choose a value in the range of rand_count.
     let mut choice = ((thread_rng().next_u32() % rand_count) + 1) as i32;
     let mut index = 0;
 
     // Choose the monster based on the value of choice. Subtract each monster's
-    // spawn_chance from choice. When choice hits 0, that particular monster is chosen
+    // spawn weight from choice. When choice hits 0, that particular monster is chosen
     for monster in MONSTERS.iter() {
-        choice -= monster.spawn_chance as i32;
+        choice -= monster.spawn_weight(biome) as i32;
         if choice <= 0 {
             break;
         }
@@ -263,7 +367,6 @@ fn spawn_monster(id: u32, world_stage: &mut WorldStage) -> MonsterInstance {
     }
 
     // Add the MonsterIntance to the WorldStage
-    let open_spot = world_stage.open_spot();
     world_stage
         .add(
             id,
@@ -5,6 +5,7 @@
 
 use std::time::{Duration, Instant};
 
+use dungeon_generator::biomes::Biome;
 use simple_serializer::Serialize;
 
 use crate::state::{
@@ -39,6 +40,26 @@ pub struct Monster {
     // other Monsters' spawn_chance's) the higher
     // the chance of choosing this Monster
     pub spawn_chance: u32,
+    // The biomes the Monster is most at home in, where
+    // its spawn_chance is multiplied by BIOME_BIAS
+    pub biomes: &'static [Biome],
+}
+
+/// How many times more likely a `Monster` is to spawn in one of its biomes
+pub const BIOME_BIAS: u32 = 3;
+
+impl Monster {
+    ///
+    /// The weight of choosing this `Monster` to spawn in the
+    /// given `biome`, which is its `spawn_chance`, multiplied
+    /// by `BIOME_BIAS` if it is at home in the `biome`.
+    ///
+    pub fn spawn_weight(&self, biome: Option<Biome>) -> u32 {
+        match biome {
+            Some(biome) if self.biomes.contains(&biome) => self.spawn_chance * BIOME_BIAS,
+            _ => self.spawn_chance,
+        }
+    }
 }
 
 ///
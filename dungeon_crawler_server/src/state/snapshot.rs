@@ -11,8 +11,8 @@ use super::transforms::{transform::Transform, vec2::Vec2};
 
 ///
 /// Represents a snapshot in time of the current state of a
-/// `StateManager`. Used to synchronize a new `Player` with the
-/// current state of the game
+/// `StateManager`'s floor. Used to synchronize a new `Player`, or
+/// one arriving on a new floor, with the current state of the game
 ///
 pub struct StateSnapshot {
     pub addr_for: SocketAddr,
@@ -20,5 +20,7 @@ pub struct StateSnapshot {
     pub other_players: Vec<(u32, String, Vec2, String)>,
     pub all_player_ts: Vec<(u32, Transform)>,
     pub monsters: Vec<(u32, u32, Vec2)>,
+    pub opened_doors: Vec<Vec2>,
+    pub floor: usize,
     pub dungeon: Dungeon,
 }
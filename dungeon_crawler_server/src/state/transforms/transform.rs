@@ -5,16 +5,20 @@
 
 use std::fmt::Display;
 
-use simple_serializer::Serialize;
+use simple_serializer::{
+    Decode, Deserialize, DeserializeError, DeserializeSegments, Encode, Segments, Serialize,
+};
 
 use super::vec2::Vec2;
 
 ///
 /// Represents an Entity's facing direction
 ///
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Encode, Decode)]
 pub enum Direction {
+    #[serialize(tag = "0")]
     Left,
+    #[serialize(tag = "1")]
     Right,
 }
 impl Display for Direction {
@@ -31,10 +35,27 @@ impl Direction {
     }
 }
 
+///
+/// Directions are read back as clients have always sent them -
+/// any number other than 1 faces left.
+///
+impl DeserializeSegments for Direction {
+    fn deserialize_segments(segs: &mut Segments) -> Result<Self, DeserializeError> {
+        u32::deserialize_segments(segs).map(Direction::from_u32)
+    }
+}
+
+impl Deserialize for Direction {
+    type DeserializeTo = Option<Direction>;
+    fn deserialize(from: &str) -> Option<Direction> {
+        Self::deserialize_segments(&mut Segments::new(from)).ok()
+    }
+}
+
 ///
 /// An Entity's Position and Direction
 ///
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Encode, Decode)]
 pub struct Transform {
     pub pos: Vec2,
     pub dir: Direction,
@@ -62,10 +83,3 @@ impl Default for Transform {
         Self::new()
     }
 }
-
-impl Serialize for Transform {
-    type SerializeTo = String;
-    fn serialize(&self) -> Self::SerializeTo {
-        format!("{}::{}::{}", self.pos.0, self.pos.1, self.dir)
-    }
-}
@@ -5,12 +5,14 @@
 
 use std::ops::{Add, Mul, Sub};
 
-use simple_serializer::Serialize;
+use simple_serializer::{Decode, Deserialize, Encode, Serialize};
 
 ///
 /// A 2-ple representing a 2D integer vector (x, y)
 ///
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd)]
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize, Encode, Decode,
+)]
 pub struct Vec2(pub i32, pub i32);
 impl Vec2 {
     /// Creates a new `Vec2` from the supplied `tuple`
@@ -43,10 +45,3 @@ impl Mul<i32> for Vec2 {
         Vec2(self.0 * rhs, self.1 * rhs)
     }
 }
-impl Serialize for Vec2 {
-    type SerializeTo = String;
-
-    fn serialize(&self) -> Self::SerializeTo {
-        format!("{}::{}", self.0, self.1)
-    }
-}
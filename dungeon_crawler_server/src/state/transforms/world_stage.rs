@@ -6,7 +6,18 @@
 use std::collections::{HashMap, HashSet};
 
 use crossbeam::channel::Sender;
-use rand::{prelude::IteratorRandom, thread_rng, RngCore};
+use dungeon_generator::{
+    analysis::distance_field,
+    inst::Dungeon,
+    mutate::Connectivity,
+    rooms::{Room, RoomKind},
+    sight::SightMap,
+    tiles::Tile,
+};
+use rand::{
+    prelude::{IteratorRandom, SliceRandom},
+    thread_rng, RngCore,
+};
 
 use crate::state::{
     actor::{Actor, ActorId, Status},
@@ -19,6 +30,10 @@ use super::{
     vec2::Vec2,
 };
 
+/// How far the view from each spot reaches. No `Actor` can
+/// see further than this, however far its sight range.
+pub const SIGHT_RADIUS: u32 = 8;
+
 ///
 /// A representation of the entire physical layout of the level,
 /// with Actor data.
@@ -28,12 +43,33 @@ use super::{
 pub struct WorldStage {
     // All Actors keyed by their Id
     actors: HashMap<u32, Actor>,
+    // The dungeon of the floor, with any tiles changed since
+    dungeon: Dungeon,
     // All paths available
     paths: HashSet<Vec2>,
-    // The position of the entrance
-    entrance: Vec2,
-    // The position of the exit
+    // The tiles other than plain floors and walls
+    tiles: HashMap<Vec2, Tile>,
+    // The position of the main exit
     exit: Vec2,
+    // The positions of every exit, including the main exit
+    exits: HashSet<Vec2>,
+    // The length of the walk from the entrance to each path
+    from_entrance: HashMap<Vec2, u32>,
+    // The spots visible from each path looked from so far
+    sight: SightMap,
+    // The named rooms of the dungeon, if it has any
+    rooms: Vec<Room>,
+    // The monster spawns of every vault in the dungeon
+    vault_spawns: Vec<Vec2>,
+    // Whether the exit is a stair down to another floor
+    stairs: bool,
+    // The positions of all closed doors
+    doors: HashSet<Vec2>,
+    // The positions of all keys not yet picked up,
+    // with the position of the door each opens
+    keys: HashMap<Vec2, Vec2>,
+    // The positions of all doors opened so far
+    opened_doors: Vec<Vec2>,
     // All spots currently filled by actors
     filled_spots: HashSet<Vec2>,
 
@@ -42,23 +78,59 @@ pub struct WorldStage {
 }
 
 impl WorldStage {
-    /// Creates a new `WorldStage` with the specified `paths`,
-    /// `entrance` and `exit` points, and `Sender` `s_to_event`.
-    pub fn new(
-        paths: HashSet<Vec2>,
-        entrance: Vec2,
-        exit: Vec2,
-        s_to_event: Sender<ResponseType>,
-    ) -> Self {
+    /// Creates a new `WorldStage` with the paths, rooms,
+    /// entrance and exit of the specified `dungeon`, and
+    /// `Sender` `s_to_event`.
+    pub fn new(dungeon: &Dungeon, s_to_event: Sender<ResponseType>) -> Self {
         Self {
             actors: HashMap::new(),
-            paths,
-            entrance,
-            exit,
+            dungeon: dungeon.clone(),
+            paths: dungeon.paths().map(|p| Vec2::from_tuple(*p)).collect(),
+            tiles: dungeon
+                .tiles()
+                .iter()
+                .map(|(p, t)| (Vec2::from_tuple(*p), *t))
+                .collect(),
+            exit: Vec2::from_tuple(dungeon.exit),
+            exits: dungeon
+                .exits()
+                .iter()
+                .map(|e| Vec2::from_tuple(e.pos))
+                .collect(),
+            from_entrance: distance_field(dungeon.paths_ref(), dungeon.entrance)
+                .into_iter()
+                .map(|(p, d)| (Vec2::from_tuple(p), d))
+                .collect(),
+            sight: dungeon.sight_cache(SIGHT_RADIUS),
+            rooms: dungeon.rooms().to_vec(),
+            vault_spawns: dungeon
+                .vaults()
+                .iter()
+                .flat_map(|v| v.spawns.iter().map(|s| Vec2::from_tuple(*s)))
+                .collect(),
+            stairs: false,
+            doors: dungeon
+                .locks()
+                .iter()
+                .map(|l| Vec2::from_tuple(l.door))
+                .collect(),
+            keys: dungeon
+                .locks()
+                .iter()
+                .map(|l| (Vec2::from_tuple(l.key), Vec2::from_tuple(l.door)))
+                .collect(),
+            opened_doors: Vec::new(),
             filled_spots: HashSet::new(),
             s_to_event,
         }
     }
+    /// Makes the main exit a stair down to another floor. Players
+    /// reaching it are left `Active`, rather than `Escaped`, to be
+    /// moved to the floor below. Any other exits still escape.
+    pub fn with_stairs(mut self) -> Self {
+        self.stairs = true;
+        self
+    }
     /// Retrieves an `Actor` from WorldStage via 'id', if the `Actor` exists
     pub fn actor(&mut self, id: u32) -> Option<&mut Actor> {
         self.actors.get_mut(&id)
@@ -88,12 +160,21 @@ impl WorldStage {
     pub fn remove(&mut self, id: u32) -> bool {
         self.actors.remove(&id).is_some()
     }
+    /// Removes an `Actor` by its `id`, returning it
+    /// if it exists, and freeing the spot it filled.
+    pub fn take(&mut self, id: u32) -> Option<Actor> {
+        let actor = self.actors.remove(&id)?;
+        self.filled_spots.remove(&actor.tr.pos);
+        Some(actor)
+    }
     /// Updates a Player's transform by `id`, using `Transform` `new_t`.
     /// Does **not** send the change to the `EventManager`, as that's handled
-    /// separately. Returns false if the position of `new_t` is already filled.
+    /// separately. Returns false if the position of `new_t` is already filled,
+    /// or is a closed door the Player can't walk through.
     pub fn update_pl_tr(&mut self, id: u32, new_t: Transform) -> bool {
+        let through_doors = self.actors.get(&id).is_some_and(|a| a.through_doors);
         // If the spot is filled, return false
-        if !self.is_spot_open(new_t.pos) {
+        if !self.is_spot_open_to(new_t.pos, through_doors) {
             false
         }
         // If the Actor exists, move it's position and update the WorldStage
@@ -106,9 +187,21 @@ impl WorldStage {
             // Set the Actor's transform
             act.tr = new_t;
 
-            // If the new position is the exit, send an Escaped
-            // update to the clients, and update the Actor status
-            if act.tr.pos == self.exit {
+            // If there is a key at the new position, pick it up,
+            // opening its door, and inform the clients
+            if let Some(door) = self.keys.remove(&act.tr.pos) {
+                self.doors.remove(&door);
+                self.opened_doors.push(door);
+                self.s_to_event
+                    .send(ResponseType::DoorOpened(act.id, door))
+                    .unwrap();
+            }
+
+            // If the new position is an exit, send an Escaped
+            // update to the clients, and update the Actor status.
+            // Stairs are instead left for the StateManager to handle.
+            let on_stairs = self.stairs && act.tr.pos == self.exit;
+            if self.exits.contains(&act.tr.pos) && !on_stairs {
                 act.status = Status::Escaped;
                 self.filled_spots.remove(&act.tr.pos);
                 self.s_to_event.send(ResponseType::Escaped(act.id)).unwrap();
@@ -188,9 +281,22 @@ impl WorldStage {
         false
     }
 
-    /// Tests if the given `spot` is open
+    /// Tests if the given `spot` is open. Closed doors are never open.
     pub fn is_spot_open(&self, spot: Vec2) -> bool {
-        self.paths.contains(&spot) && !self.filled_spots.contains(&spot)
+        self.is_spot_open_to(spot, false)
+    }
+
+    /// Tests if the given `spot` is open to an `Actor` which
+    /// may walk `through_doors`, closed or not.
+    pub fn is_spot_open_to(&self, spot: Vec2, through_doors: bool) -> bool {
+        self.paths.contains(&spot)
+            && !self.filled_spots.contains(&spot)
+            && (through_doors || !self.doors.contains(&spot))
+    }
+
+    /// The positions of all doors opened so far
+    pub fn opened_doors(&self) -> &[Vec2] {
+        &self.opened_doors
     }
 
     /// Tests if the `actor_id` is at the position `spot`.
@@ -200,9 +306,137 @@ impl WorldStage {
             .find(|a| a.actor_id == actor_id && a.tr.pos == spot && a.status == Status::Active)
     }
 
-    /// Tests if the given `spot` is on a path
+    /// The id and position of every active `Actor` of the given `actor_ids`
+    pub fn active_of(&self, actor_ids: &[ActorId]) -> Vec<(u32, Vec2)> {
+        self.actors
+            .values()
+            .filter(|a| actor_ids.contains(&a.actor_id) && a.status == Status::Active)
+            .map(|a| (a.id, a.tr.pos))
+            .collect()
+    }
+
+    /// Tests if the given `spot` is on a path, and can be walked over
     pub fn is_on_path(&self, spot: Vec2) -> bool {
-        self.paths.contains(&spot)
+        self.tile_at(spot).is_walkable()
+    }
+
+    /// The `Tile` at the given `spot`
+    pub fn tile_at(&self, spot: Vec2) -> Tile {
+        match self.tiles.get(&spot) {
+            Some(tile) => *tile,
+            None if self.paths.contains(&spot) => Tile::Floor,
+            None => Tile::Wall,
+        }
+    }
+
+    /// The cost of stepping onto the given `spot`, if it can be walked over
+    pub fn move_cost(&self, spot: Vec2) -> Option<u32> {
+        self.tile_at(spot).cost()
+    }
+
+    /// Tests if the given `spot` blocks the view past it
+    pub fn blocks_sight(&self, spot: Vec2) -> bool {
+        self.tile_at(spot).is_opaque()
+    }
+
+    /// The spots visible from each path looked from so far
+    pub fn sight(&self) -> &SightMap {
+        &self.sight
+    }
+
+    ///
+    /// The spots visible from `spot`, out to `SIGHT_RADIUS`, if it can be
+    /// walked over. The view from each spot is only found the first time
+    /// it is looked from, and kept until the tiles around it change.
+    ///
+    pub fn look(&mut self, spot: Vec2) -> Vec<Vec2> {
+        self.sight
+            .look((spot.0, spot.1))
+            .map(|visible| visible.iter().map(|p| Vec2::from_tuple(*p)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Tests if the spot `to` can be seen from the spot `from`
+    pub fn can_see(&mut self, from: Vec2, to: Vec2) -> bool {
+        self.sight
+            .look((from.0, from.1))
+            .is_some_and(|visible| visible.binary_search(&(to.0, to.1)).is_ok())
+    }
+
+    /// The dungeon of the floor, with any tiles changed since
+    pub fn dungeon(&self) -> &Dungeon {
+        &self.dungeon
+    }
+
+    ///
+    /// Changes the tiles of the floor, as `Dungeon::set_tiles` does,
+    /// returning the tiles which changed. Changes which would leave
+    /// an `Actor` standing on a tile that can't be walked over are
+    /// refused, as are any the dungeon refuses.
+    ///
+    pub fn set_tiles(
+        &mut self,
+        changes: &[(Vec2, Tile)],
+        connectivity: Connectivity,
+    ) -> Option<Vec<(Vec2, Tile)>> {
+        if changes.iter().any(|(spot, tile)| {
+            !tile.is_walkable() && self.actors.values().any(|a| a.tr.pos == *spot)
+        }) {
+            return None;
+        }
+        let changes: Vec<((i32, i32), Tile)> =
+            changes.iter().map(|(p, t)| ((p.0, p.1), *t)).collect();
+        let changed = self.dungeon.set_tiles(&changes, connectivity)?;
+
+        for (point, tile) in changed.iter() {
+            let spot = Vec2::from_tuple(*point);
+            if tile.is_walkable() {
+                self.paths.insert(spot);
+            } else {
+                self.paths.remove(&spot);
+            }
+            if matches!(tile, Tile::Floor | Tile::Wall) {
+                self.tiles.remove(&spot);
+            } else {
+                self.tiles.insert(spot, *tile);
+            }
+        }
+        let points: Vec<(i32, i32)> = changed.iter().map(|(p, _)| *p).collect();
+        self.sight.update(&self.dungeon, &points);
+        self.from_entrance = distance_field(self.dungeon.paths_ref(), self.dungeon.entrance)
+            .into_iter()
+            .map(|(p, d)| (Vec2::from_tuple(p), d))
+            .collect();
+
+        Some(
+            changed
+                .into_iter()
+                .map(|(p, t)| (Vec2::from_tuple(p), t))
+                .collect(),
+        )
+    }
+
+    ///
+    /// Caves in a passage of the floor, collapsing a floor tile, chosen
+    /// at random from those other than the entrance and exits, into wall.
+    /// Returns the tile which changed, or `None` if the cave-in was
+    /// refused, as `set_tiles` refuses changes, or there was nothing
+    /// left to cave in.
+    ///
+    pub fn cave_in(&mut self) -> Option<Vec<(Vec2, Tile)>> {
+        let dungeon = &self.dungeon;
+        let spot = dungeon
+            .paths()
+            .filter(|p| dungeon.tile_at(**p) == Tile::Floor)
+            .filter(|p| **p != dungeon.entrance && !dungeon.is_exit(**p))
+            .choose(&mut thread_rng())?;
+        self.set_tiles(&[(Vec2::from_tuple(*spot), Tile::Wall)], Connectivity::Keep)
+    }
+
+    /// Tests if the `Actor` with the given `id` is standing on
+    /// the stairs down to the next floor
+    pub fn is_on_stairs(&self, id: u32) -> bool {
+        self.stairs && self.pos(id) == Some(self.exit)
     }
 
     /// Tests if the `Actor` with the given `id` is at position `spot`
@@ -214,16 +448,56 @@ impl WorldStage {
     /// Finds a currently open spot on the map,
     /// retrieving the current `dungeon`, and checking
     /// current `monsters` and `players` positions,
-    /// filtering them out
+    /// filtering them out. Spots more than a short walk from the
+    /// entrance are preferred, if the map is large enough to have any.
     ///
     pub fn open_spot(&self) -> Vec2 {
-        *self
-            .paths
+        *self.spawn_candidates().choose(&mut thread_rng()).unwrap()
+    }
+
+    ///
+    /// Every spot `open_spot` currently chooses between - the open
+    /// spots more than a short walk from the entrance, or every
+    /// open spot if there are none that far away.
+    ///
+    pub fn spawn_candidates(&self) -> Vec<Vec2> {
+        let open = self.paths.iter().filter(|path| self.is_spot_open(**path));
+        let far: Vec<Vec2> = open
+            .clone()
+            .filter(|path| self.from_entrance.get(path).is_some_and(|d| *d > 15))
+            .cloned()
+            .collect();
+        if far.is_empty() {
+            open.cloned().collect()
+        } else {
+            far
+        }
+    }
+
+    ///
+    /// Finds a currently open spot within a random room
+    /// of one of the given `kinds`. Returns `None` if there
+    /// are no such rooms, or they are all filled.
+    ///
+    pub fn open_spot_in(&self, kinds: &[RoomKind]) -> Option<Vec2> {
+        self.rooms
             .iter()
-            .filter(|path| !self.filled_spots.contains(path))
-            .filter(|path| path.distance(self.entrance) > 15.0)
+            .filter(|room| kinds.contains(&room.kind))
+            .flat_map(|room| room.bounds.points().map(Vec2::from_tuple))
+            .filter(|spot| self.is_spot_open(*spot))
             .choose(&mut thread_rng())
-            .unwrap()
+    }
+
+    ///
+    /// Finds the first currently open monster spawn of
+    /// the dungeon's vaults. Returns `None` if there are
+    /// no vaults, or every spawn is filled.
+    ///
+    pub fn open_vault_spawn(&self) -> Option<Vec2> {
+        self.vault_spawns
+            .iter()
+            .find(|spot| self.is_spot_open(**spot))
+            .cloned()
     }
 
     ///
@@ -237,9 +511,7 @@ impl WorldStage {
             if let Some(spot) = self
                 .paths
                 .iter()
-                .filter(|path| {
-                    path.distance(a.tr.pos) <= range as f32 && !self.filled_spots.contains(path)
-                })
+                .filter(|path| path.distance(a.tr.pos) <= range as f32 && self.is_spot_open(**path))
                 .choose(&mut thread_rng())
             {
                 return Some(*spot);
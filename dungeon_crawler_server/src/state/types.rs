@@ -6,6 +6,8 @@
 
 use std::net::SocketAddr;
 
+use dungeon_generator::{mutate::Connectivity, tiles::Tile};
+
 use crate::state::snapshot::StateSnapshot;
 
 use super::transforms::{
@@ -18,10 +20,12 @@ use super::transforms::{
 /// which it parses and performs changes in state on.
 ///
 pub enum RequestType {
-    NewPlayer(SocketAddr, u32, String), // (client address, id, name)
-    DropPlayer(u32),                    // (id)
-    PlayerMoved(u32, Transform),        // (id, player transform)
-    SpawnMonster(u32),                  // (id)
+    NewPlayer(SocketAddr, u32, String, bool), // (client address, id, name, sees doors)
+    DropPlayer(u32),                          // (id)
+    PlayerMoved(u32, Transform),              // (id, player transform)
+    SpawnMonster(u32, usize),                 // (id, floor)
+    ChangeTiles(usize, Vec<(Vec2, Tile)>, Connectivity), // (floor, tile changes, connectivity)
+    CaveIn(usize),                            // (floor)
     Abort,
 }
 
@@ -30,12 +34,15 @@ pub enum RequestType {
 /// which are used to synchronize clients to the game
 ///
 pub enum ResponseType {
-    StateSnapshot(StateSnapshot),
+    StateSnapshot(Box<StateSnapshot>),
+    Descended(Box<StateSnapshot>), // (snapshot of the floor below)
     NewMonster(u32, u32, Vec2, Direction), // (temp_id, inst_id, pos, dir)
-    MonsterMoved(u32, Transform),          // (inst_id, transform)
-    Hit(u32, u32, i32),                    // (attk_id, defd_id, defd health left)
-    Miss(u32, u32),                        // (attk_id, defd_id)
-    Dead(u32),                             // (id)
-    Escaped(u32),                          // (id)
+    MonsterMoved(u32, Transform),  // (inst_id, transform)
+    Hit(u32, u32, i32),            // (attk_id, defd_id, defd health left)
+    Miss(u32, u32),                // (attk_id, defd_id)
+    Dead(u32),                     // (id)
+    Escaped(u32),                  // (id)
+    DoorOpened(u32, Vec2),         // (id of opener, door pos)
+    TilesChanged(usize, Vec<(Vec2, Tile)>), // (floor, changed tiles)
     DungeonComplete,
 }
@@ -12,34 +12,59 @@ mod event_handler_tests {
         time::{Duration, Instant},
     };
 
-    use dungeon_crawler_server::events::{manager::EventManager, types::Type};
-    use simple_serializer::Serialize;
+    use dungeon_crawler_server::{
+        events::{
+            manager::EventManager,
+            protocol::{Handshake, PROTOCOL_VERSION},
+            types::Type,
+        },
+        state::transforms::{
+            transform::{Direction, Transform},
+            vec2::Vec2,
+        },
+    };
+    use dungeon_generator::{
+        config::DungeonConfig, generators::bsp::BspGenerator, inst::Dungeon, tiles::Tile,
+        tower::Tower, traits::Generator, vaults::Vault,
+    };
+    use simple_serializer::{Deserialize, Serialize, TryDeserialize, Versioned};
     use udp_server::{
         manager::DatagramManager,
-        packets::{ReceivePacket, SendPacket},
+        packets::{Encoding, PacketReceiver, Payload, ReceivePacket, SendPacket},
+        types::Type as Datagram,
     };
     use ReceivePacket::ClientMessage;
 
     /// Generates a `DatagramManager` and `EventManager` on `port1` and `port2`.
     /// The `DatagramManager` is set up ready to send information to the newly
-    /// created `EventManager` given the returned `SocketAddr`.
+    /// created `EventManager` given the returned `SocketAddr`. The `EventManager`
+    /// plays a small seeded floor, which is ready in moments - the Towers
+    /// `EventManager::new` generates can take seconds in a debug build.
     fn gen_managers(port1: u32, port2: u32) -> (DatagramManager, SocketAddr) {
         let dgm = DatagramManager::new(port1).unwrap();
+        let tower = Tower::from_floors(vec![Dungeon::from_seed(1, 20, 20)]).unwrap();
+        let evt_addr = gen_tower_manager(port2, tower, None);
 
+        (dgm, evt_addr)
+    }
+
+    /// Starts an `EventManager` on `port` playing the hand-authored
+    /// `tower`, with a passage caving in as often as `cave_ins`.
+    /// Returns the `SocketAddr` to send it packets at.
+    fn gen_tower_manager(port: u32, tower: Tower, cave_ins: Option<Duration>) -> SocketAddr {
         thread::spawn(move || {
-            let evt_man_dgm = DatagramManager::new(port2).unwrap();
+            let evt_man_dgm = DatagramManager::new(port).unwrap();
             let (s, r) = evt_man_dgm.get_sender_receiver();
-            let mut evt_man = EventManager::new(r, s);
-            evt_man.start()
+            EventManager::with_tower(r, s, tower)
+                .with_cave_ins(cave_ins)
+                .start()
         });
-
-        let evt_addr = SocketAddr::from_str(&format!("127.0.0.1:{}", port2)).unwrap();
-
-        (dgm, evt_addr)
+        SocketAddr::from_str(&format!("127.0.0.1:{}", port)).unwrap()
     }
 
     /// Tests that clients which send a `Hello` `EventType` receive
-    /// a `Welcome` packet in return.
+    /// a `Welcome` packet in return, whose dungeon is read back
+    /// whole from the binary encoding.
     #[test]
     fn test_new_player() {
         // Generate the managers
@@ -50,7 +75,10 @@ mod event_handler_tests {
         s2.send(SendPacket {
             addrs: vec![evt_addr],
             is_rel: true,
-            msg: Type::Hello("Sam".to_string()).serialize(),
+            msg: Type::Hello("Sam".to_string(), Handshake::default())
+                .serialize_for(1)
+                .unwrap()
+                .into(),
         })
         .unwrap();
         thread::sleep(Duration::from_secs_f32(1.5));
@@ -58,7 +86,7 @@ mod event_handler_tests {
         s1.send(SendPacket {
             addrs: vec![evt_addr],
             is_rel: true,
-            msg: Type::Hello("Phil".to_string()).serialize(),
+            msg: Type::Hello("Phil".to_string(), Handshake::current()).payload(Encoding::Binary),
         })
         .unwrap();
 
@@ -67,10 +95,463 @@ mod event_handler_tests {
             if Instant::now() - now > Duration::from_secs(3) {
                 break false;
             } else if let Ok(ClientMessage(_, msg)) = r1.try_recv() {
-                if msg.starts_with("Welcome::") {
-                    break true;
+                // Phil said hello in binary, so is welcomed in binary
+                if let Ok(Type::Welcome(_, dungeon)) = Type::from_payload(&msg) {
+                    break msg.encoding() == Encoding::Binary
+                        && *dungeon == Dungeon::from_seed(1, 20, 20);
                 }
             }
         });
     }
+
+    /// Tests that every `EventType` is read back as it was written,
+    /// in the format the client expects, and that malformed events
+    /// are rejected with the field that failed.
+    #[test]
+    fn test_event_types() {
+        let moved = Transform::with_values(Vec2(3, -4), Direction::Left);
+        let dungeon = Dungeon::from_text("dungeon 1\nmap\n####\n#<>#\n####\n").unwrap();
+        let welcome = format!("Welcome::2::{}", dungeon.serialize());
+        let events = [
+            (
+                Type::Hello("Sam".to_string(), Handshake::current()),
+                "Hello::Sam::2::2::doors::tiles",
+            ),
+            (Type::Welcome(2, Box::new(dungeon)), welcome.as_str()),
+            (
+                Type::NewPlayer(2, "Sam".to_string(), Vec2(1, 2)),
+                "NewPlayer::2::Sam::1::2",
+            ),
+            (Type::NewMonster(0, 7, Vec2(1, 2)), "NewMonster::0::7::1::2"),
+            (Type::Moved(2, moved), "Moved::2::3::-4::0"),
+            (Type::PlayerLeft(2), "PlayerLeft::2"),
+            (Type::Hit(2, 7, 3), "Hit::2::7::3"),
+            (Type::Miss(2, 7), "Miss::2::7"),
+            (Type::Dead(2), "Dead::2"),
+            (Type::Escaped(2), "Escaped::2"),
+            (Type::DoorOpened(Vec2(1, 2)), "DoorOpened::1::2"),
+            (
+                Type::TileChanged(Vec2(1, 2), Tile::Water),
+                "TileChanged::1::2::2",
+            ),
+            (Type::DungeonComplete, "DungeonComplete::"),
+            (Type::Reconnect, "Reconnect::"),
+            (Type::Agreed(Handshake::default()), "Agreed::1::0"),
+            (Type::Rejected(1, 2), "Rejected::1::2"),
+        ];
+        for (event, text) in events.iter() {
+            assert_eq!(event.serialize(), *text);
+            assert_eq!(Type::try_deserialize(text).unwrap().serialize(), *text);
+        }
+
+        // Unit events end in `::` in every version, as the Unity
+        // client expects, and are read with or without it
+        for version in 1..=PROTOCOL_VERSION {
+            assert_eq!(
+                Type::DungeonComplete.serialize_for(version).as_deref(),
+                Some("DungeonComplete::")
+            );
+            assert_eq!(
+                Type::Reconnect.serialize_for(version).as_deref(),
+                Some("Reconnect::")
+            );
+        }
+
+        // Events as earlier servers and clients wrote them are still read
+        let old = [
+            ("DungeonComplete", "DungeonComplete::"),
+            ("Reconnect", "Reconnect::"),
+            ("Moved::2::3::-4::7", "Moved::2::3::-4::0"),
+        ];
+        for (text, read) in old.iter() {
+            assert_eq!(Type::try_deserialize(text).unwrap().serialize(), *read);
+        }
+        let errors = [
+            ("", "message has unknown tag ``"),
+            ("Left::2", "message has unknown tag `Left`"),
+            ("Hello", "field `Hello.0` is missing"),
+            ("Moved::2::3", "field `Moved.1.pos.1` is missing"),
+            ("Moved::2::3::4::x", "field `Moved.1.dir` is malformed: `x`"),
+            (
+                "TileChanged::1::2::9",
+                "field `TileChanged.1` is malformed: `9`",
+            ),
+            ("Hit::a::b::c", "field `Hit.0` is malformed: `a`"),
+        ];
+        for (bad, error) in errors.iter() {
+            assert_eq!(Type::try_deserialize(bad).unwrap_err().to_string(), *error);
+        }
+    }
+
+    /// Tests that hostile player names survive the trip through
+    /// each message carrying them, and the datagram wrapped around it,
+    /// without breaking the message apart.
+    #[test]
+    fn test_hostile_names() {
+        let names = [
+            "a::b",
+            "Moved::1::2::3::0",
+            "a:",
+            "\\",
+            "\\::",
+            "line\nbreak\r",
+            "",
+        ];
+        for name in names.iter() {
+            let events = [
+                Type::Hello(name.to_string(), Handshake::default()),
+                Type::NewPlayer(4, name.to_string(), Vec2(1, 2)),
+            ];
+            for event in events.iter() {
+                let text = event.serialize();
+                assert!(!text.contains('\n'));
+                let datagram = Datagram::Rel(9, text.clone().into()).serialize();
+                let text = match Datagram::try_deserialize(&String::from_utf8(datagram).unwrap()) {
+                    Ok(Datagram::Rel(9, Payload::Text(text))) => text,
+                    other => panic!("{:?}", other),
+                };
+                match Type::try_deserialize(&text) {
+                    Ok(Type::Hello(n, _)) | Ok(Type::NewPlayer(4, n, Vec2(1, 2))) => {
+                        assert_eq!(n, *name)
+                    }
+                    other => panic!("{:?}", other),
+                }
+            }
+
+            // Vault names and exit destinations are carried
+            // whole inside the dungeon of a Welcome
+            let vault = Vault::parse(name, "#+###\n#.M$#\n#####\n").unwrap();
+            let mut config = DungeonConfig::new(30, 30)
+                .with_seed(2)
+                .with_vaults(vec![vault], 1);
+            if !name.is_empty() {
+                config = config.with_exit_destination(name);
+            }
+            let dungeon = BspGenerator::default().generate(&config);
+            let text = Type::Welcome(3, Box::new(dungeon.clone())).serialize();
+            assert!(!text.contains('\n'));
+            match Type::try_deserialize(&text) {
+                Ok(Type::Welcome(3, d)) => assert!(*d == dungeon),
+                other => panic!("{:?}", other),
+            }
+        }
+    }
+
+    /// Tests that every `EventType` is read back from its binary
+    /// encoding, and that binary datagrams are much smaller
+    /// than their text equivalents.
+    #[test]
+    fn test_binary_events() {
+        let moved = || Type::Moved(3, Transform::with_values(Vec2(23, 2), Direction::Left));
+        let events = [
+            Type::Hello("名前::a".to_string(), Handshake::current()),
+            Type::Welcome(2, Box::new(Dungeon::from_seed(1, 20, 20))),
+            Type::NewPlayer(2, "Sam".to_string(), Vec2(1, -2)),
+            Type::NewMonster(0, 7, Vec2(1, 2)),
+            moved(),
+            Type::PlayerLeft(2),
+            Type::Hit(2, 7, 3),
+            Type::Miss(2, 7),
+            Type::Dead(2),
+            Type::Escaped(2),
+            Type::DoorOpened(Vec2(1, 2)),
+            Type::TileChanged(Vec2(1, 2), Tile::Water),
+            Type::DungeonComplete,
+            Type::Reconnect,
+            Type::Agreed(Handshake::current()),
+            Type::Rejected(1, 2),
+        ];
+        for event in events.iter() {
+            let payload = event.payload(Encoding::Binary);
+            assert_eq!(payload.encoding(), Encoding::Binary);
+            let read = Type::from_payload(&payload).unwrap();
+            assert_eq!(read.serialize(), event.serialize());
+        }
+        assert!(Type::from_payload(&Payload::Binary(vec![99])).is_err());
+
+        let text = Datagram::Unrel(moved().payload(Encoding::Text)).frame(Encoding::Text);
+        let binary = Datagram::Unrel(moved().payload(Encoding::Binary)).frame(Encoding::Binary);
+        assert_eq!(text.len(), 23);
+        assert!(binary.len() * 3 <= text.len());
+    }
+
+    /// Tests that clients agree on the protocol they speak with
+    /// the server, that clients from before the handshake are still
+    /// understood, and that clients speaking no version the server
+    /// does are rejected.
+    #[test]
+    fn test_handshake() {
+        let handshake = |version, capabilities: &[&str]| Handshake {
+            version,
+            capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+        };
+        assert_eq!(Handshake::current().agree(), Some(Handshake::current()));
+        assert_eq!(
+            handshake(9, &["tiles", "flying"]).agree(),
+            Some(handshake(PROTOCOL_VERSION, &["tiles"]))
+        );
+        assert_eq!(handshake(1, &[]).agree(), Some(Handshake::default()));
+        assert_eq!(handshake(0, &["doors"]).agree(), None);
+
+        // Older clients send, and are sent, events as their version was
+        let hello = Type::Hello("Sam".to_string(), Handshake::current());
+        assert_eq!(hello.serialize_for(1).unwrap(), "Hello::Sam");
+        assert!(matches!(
+            Type::try_deserialize("Hello::Sam"),
+            Ok(Type::Hello(name, h)) if name == "Sam" && h == Handshake::default()
+        ));
+        let agreed = Type::Agreed(Handshake::current());
+        assert_eq!(agreed.payload_for(Encoding::Text, 1), None);
+        assert_eq!(agreed.payload_for(Encoding::Binary, 1), None);
+        assert!(agreed.payload_for(Encoding::Binary, 2).is_some());
+        assert_eq!(Type::DoorOpened(Vec2(1, 2)).capability(), Some("doors"));
+        assert_eq!(Type::Dead(1).capability(), None);
+
+        let (dgm, evt_addr) = gen_managers(3002, 3003);
+        let (s1, r1) = dgm.get_sender_receiver();
+        let dgm2 = DatagramManager::new(3005).unwrap();
+        let (s2, r2) = dgm2.get_sender_receiver();
+        s1.send(SendPacket {
+            addrs: vec![evt_addr],
+            is_rel: true,
+            msg: Type::Hello("Old".to_string(), handshake(0, &[]))
+                .serialize()
+                .into(),
+        })
+        .unwrap();
+        s2.send(SendPacket {
+            addrs: vec![evt_addr],
+            is_rel: true,
+            msg: Type::Hello("New".to_string(), handshake(3, &["doors"])).payload(Encoding::Binary),
+        })
+        .unwrap();
+
+        // Each client's first reply answers its handshake, within
+        // a few seconds, so a lost reply fails the test rather than
+        // hanging it
+        let reply = |r: &PacketReceiver| match r.recv_timeout(Duration::from_secs(5)) {
+            Ok(ClientMessage(_, msg)) => Type::from_payload(&msg).unwrap(),
+            other => panic!("{:?}", other),
+        };
+        assert!(matches!(reply(&r1), Type::Rejected(1, 2)));
+        assert!(matches!(
+            reply(&r2),
+            Type::Agreed(h) if h == handshake(2, &["doors"])
+        ));
+    }
+
+    /// Tests that a Player who steps onto the exit is welcomed to the
+    /// floor below, and is only told that other Players left the floor
+    /// above - never the monsters on it.
+    #[test]
+    fn test_descend() {
+        let top = "dungeon 1\nmap\n########\n#<.....#\n#......>\n#......#\n########\n";
+        let below = "dungeon 1\nmap\n########\n#......#\n#......<\n#>.....#\n########\n";
+        let tower = Tower::from_floors(vec![
+            Dungeon::from_text(top).unwrap(),
+            Dungeon::from_text(below).unwrap(),
+        ])
+        .unwrap();
+        let stairs = tower.floors()[0].exit;
+
+        let dgm = DatagramManager::new(3006).unwrap();
+        let evt_addr = gen_tower_manager(3007, tower, None);
+
+        let (s, r) = dgm.get_sender_receiver();
+        s.send(SendPacket {
+            addrs: vec![evt_addr],
+            is_rel: true,
+            msg: Type::Hello("Ann".to_string(), Handshake::current())
+                .serialize()
+                .into(),
+        })
+        .unwrap();
+
+        // Collects each event up to and including the next `Welcome`
+        let until_welcome = || {
+            let mut events = Vec::new();
+            loop {
+                let received = r.recv_timeout(Duration::from_secs(5));
+                assert!(received.is_ok(), "no Welcome was sent");
+                if let Ok(ClientMessage(_, msg)) = received {
+                    match Type::from_payload(&msg) {
+                        Ok(Type::Welcome(id, _)) => break (id, events),
+                        Ok(event) => events.push(event),
+                        Err(_) => (),
+                    }
+                }
+            }
+        };
+        let (id, _) = until_welcome();
+
+        let moved = Transform::with_values(Vec2::from_tuple(stairs), Direction::Left);
+        s.send(SendPacket {
+            addrs: vec![evt_addr],
+            is_rel: true,
+            msg: Type::Moved(id, moved).serialize().into(),
+        })
+        .unwrap();
+
+        let (again, events) = until_welcome();
+        assert_eq!(again, id);
+        assert!(!events.iter().any(|e| matches!(e, Type::PlayerLeft(_))));
+    }
+
+    /// Tests that clients from before the handshake are sent the
+    /// dungeon in their `Welcome` as the first servers wrote it -
+    /// its paths, entrance and exit - and that it is read back.
+    #[test]
+    fn test_versioned_welcome() {
+        let map = "dungeon 1\nmap\n####\n#<>#\n####\n";
+        let dungeon = Dungeon::from_text(map).unwrap();
+        let tower = Tower::from_floors(vec![dungeon.clone()]).unwrap();
+        assert_eq!(dungeon.serialize_for(2), Some(dungeon.serialize()));
+
+        let dgm = DatagramManager::new(3010).unwrap();
+        let evt_addr = gen_tower_manager(3011, tower, None);
+        let (s, r) = dgm.get_sender_receiver();
+        s.send(SendPacket {
+            addrs: vec![evt_addr],
+            is_rel: true,
+            msg: Type::Hello("Sam".to_string(), Handshake::default())
+                .serialize_for(1)
+                .unwrap()
+                .into(),
+        })
+        .unwrap();
+
+        let (text, read) = loop {
+            let received = r.recv_timeout(Duration::from_secs(5));
+            assert!(received.is_ok(), "no Welcome was sent");
+            if let Ok(ClientMessage(_, Payload::Text(text))) = received {
+                if let Ok(Type::Welcome(id, read)) = Type::try_deserialize(&text) {
+                    let prefix = format!("Welcome::{}::", id);
+                    break (text[prefix.len()..].to_string(), read);
+                }
+            }
+        };
+        // The path count, each path, the entrance and the exit
+        assert_eq!(text, "2::1::1::2::1::1::1::2::1");
+        let segs: Vec<i32> = text.split("::").map(|seg| seg.parse().unwrap()).collect();
+        assert_eq!(segs.len(), 1 + segs[0] as usize * 2 + 4);
+        assert!(Dungeon::deserialize(&text).as_ref() == Some(&*read));
+
+        assert!(read.paths_ref() == dungeon.paths_ref());
+        assert_eq!((read.entrance, read.exit), (dungeon.entrance, dungeon.exit));
+    }
+
+    /// Tests that passages cave in over time on a floor with a Player
+    /// on it, and that clients with the `tiles` capability are sent
+    /// each tile which collapsed into wall.
+    #[test]
+    fn test_cave_in() {
+        let map = "dungeon 1\nmap\n########\n#<.....#\n#......#\n#......>\n########\n";
+        let dungeon = Dungeon::from_text(map).unwrap();
+        let tower = Tower::from_floors(vec![dungeon.clone()]).unwrap();
+
+        let dgm = DatagramManager::new(3008).unwrap();
+        let evt_addr = gen_tower_manager(3009, tower, Some(Duration::from_millis(100)));
+        let (s, r) = dgm.get_sender_receiver();
+        s.send(SendPacket {
+            addrs: vec![evt_addr],
+            is_rel: true,
+            msg: Type::Hello("Ann".to_string(), Handshake::current())
+                .serialize()
+                .into(),
+        })
+        .unwrap();
+
+        let now = Instant::now();
+        let collapsed = loop {
+            assert!(Instant::now() - now < Duration::from_secs(5));
+            if let Ok(ClientMessage(_, msg)) = r.try_recv() {
+                if let Ok(Type::TileChanged(pos, tile)) = Type::from_payload(&msg) {
+                    break (pos, tile);
+                }
+            }
+        };
+        let (pos, tile) = collapsed;
+        assert_eq!(tile, Tile::Wall);
+        assert_eq!(dungeon.tile_at((pos.0, pos.1)), Tile::Floor);
+        assert!(pos != Vec2::from_tuple(dungeon.entrance) && !dungeon.is_exit((pos.0, pos.1)));
+    }
+    /// Tests that a client from before the handshake, which is never
+    /// told of doors or changed tiles, can walk onto a locked door it
+    /// can't see, and that the floor it is on never caves in - even
+    /// for the clients on it which would be told.
+    #[test]
+    fn test_legacy_client() {
+        let map =
+            "dungeon 1\nlock 6 3 2 1\nmap\n########\n#<.....#\n#......#\n#.....+>\n########\n";
+        let tower = Tower::from_floors(vec![Dungeon::from_text(map).unwrap()]).unwrap();
+        let door = Vec2(6, 3);
+
+        let dgm = DatagramManager::new(3012).unwrap();
+        let evt_addr = gen_tower_manager(3013, tower, Some(Duration::from_millis(100)));
+        let (s1, r1) = dgm.get_sender_receiver();
+        let dgm2 = DatagramManager::new(3014).unwrap();
+        let (s2, r2) = dgm2.get_sender_receiver();
+
+        // Collects each event up to and including the next `Welcome`
+        let until_welcome = |r: &PacketReceiver| {
+            let mut events = Vec::new();
+            loop {
+                let received = r.recv_timeout(Duration::from_secs(5));
+                assert!(received.is_ok(), "no Welcome was sent");
+                if let Ok(ClientMessage(_, msg)) = received {
+                    match Type::from_payload(&msg) {
+                        Ok(Type::Welcome(id, _)) => break (id, events),
+                        Ok(event) => events.push(event),
+                        Err(_) => (),
+                    }
+                }
+            }
+        };
+
+        s1.send(SendPacket {
+            addrs: vec![evt_addr],
+            is_rel: true,
+            msg: Type::Hello("Old".to_string(), Handshake::default())
+                .serialize_for(1)
+                .unwrap()
+                .into(),
+        })
+        .unwrap();
+        let (old, _) = until_welcome(&r1);
+        s1.send(SendPacket {
+            addrs: vec![evt_addr],
+            is_rel: true,
+            msg: Type::Moved(old, Transform::with_values(door, Direction::Right))
+                .serialize_for(1)
+                .unwrap()
+                .into(),
+        })
+        .unwrap();
+        thread::sleep(Duration::from_secs(1));
+
+        // A client joining later is sent the legacy Player standing on the door
+        s2.send(SendPacket {
+            addrs: vec![evt_addr],
+            is_rel: true,
+            msg: Type::Hello("New".to_string(), Handshake::current())
+                .serialize()
+                .into(),
+        })
+        .unwrap();
+        let (_, events) = until_welcome(&r2);
+        assert!(events
+            .iter()
+            .any(|e| matches!(e, Type::Moved(id, t) if *id == old && t.pos == door)));
+
+        // Nothing caves in while the legacy Player is on the floor
+        let now = Instant::now();
+        while Instant::now() - now < Duration::from_secs(1) {
+            if let Ok(ClientMessage(_, msg)) = r2.recv_timeout(Duration::from_millis(100)) {
+                assert!(!matches!(
+                    Type::from_payload(&msg),
+                    Ok(Type::TileChanged(..))
+                ));
+            }
+        }
+    }
 }
@@ -0,0 +1,212 @@
+//! World Stage tests
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+#[cfg(test)]
+mod world_stage_tests {
+    use std::collections::HashSet;
+
+    use crossbeam::channel::unbounded;
+    use dungeon_crawler_server::{
+        astar::visible_actors,
+        events::types::Type,
+        state::{
+            actor::{Actor, ActorId},
+            monsters::{Monster, BIOME_BIAS},
+            stats::{Attributes, Stats},
+            transforms::{
+                transform::{Direction, Transform},
+                vec2::Vec2,
+                world_stage::WorldStage,
+            },
+            types::ResponseType,
+        },
+    };
+    use dungeon_generator::{biomes::Biome, inst::Dungeon, mutate::Connectivity, tiles::Tile};
+    use simple_serializer::Serialize;
+
+    /// A room of two rows, joined to the exit by a single corridor
+    const MAP: &str = "dungeon 1
+map
+#######
+#<...##
+#.....>
+#######
+";
+
+    /// Creates an `Actor` of the kind `actor_id` at `pos`, facing right
+    fn actor(id: u32, pos: Vec2, actor_id: ActorId) -> Actor {
+        Actor::new(
+            id,
+            Stats::new(10, 10, 10),
+            Attributes::new(5, 5, 5),
+            Transform::with_values(pos, Direction::Right),
+            actor_id,
+        )
+    }
+
+    /// Tests that tiles changed on a `WorldStage` change its paths and
+    /// dungeon, and that changes which would trap an `Actor` inside a
+    /// wall, or wall off the exit, are refused.
+    #[test]
+    fn test_set_tiles() {
+        let dungeon = Dungeon::from_text(MAP).unwrap();
+        let mut stage = WorldStage::new(&dungeon, unbounded().0);
+        stage.add(0, actor(0, Vec2(3, 1), ActorId::Player));
+
+        // Actors can't be buried, and the exit can't be walled off
+        let wall = |x, y| (Vec2(x, y), Tile::Wall);
+        assert_eq!(stage.set_tiles(&[wall(3, 1)], Connectivity::Keep), None);
+        assert_eq!(stage.set_tiles(&[wall(5, 2)], Connectivity::Keep), None);
+        assert!(stage.is_on_path(Vec2(5, 2)));
+
+        // Unless connectivity is overridden
+        assert_eq!(
+            stage.set_tiles(&[wall(5, 2)], Connectivity::Override),
+            Some(vec![wall(5, 2)])
+        );
+        assert!(!stage.is_on_path(Vec2(5, 2)));
+        assert_eq!(stage.dungeon().tile_at((5, 2)), Tile::Wall);
+
+        // Only tiles which change are returned
+        let changes = [(Vec2(2, 2), Tile::Water), (Vec2(5, 2), Tile::Wall)];
+        assert_eq!(
+            stage.set_tiles(&changes, Connectivity::Override),
+            Some(vec![(Vec2(2, 2), Tile::Water)])
+        );
+        assert_eq!(stage.tile_at(Vec2(2, 2)), Tile::Water);
+        assert!(stage.is_on_path(Vec2(2, 2)));
+
+        assert_eq!(
+            Type::TileChanged(Vec2(2, 2), Tile::Water).serialize(),
+            format!("TileChanged::{}::{}", Vec2(2, 2).serialize(), Tile::Water)
+        );
+    }
+
+    /// Tests that each cave-in collapses a floor tile of the floor as
+    /// it is after the cave-ins before it, so two in a row hit
+    /// different tiles, and that the entrance and exit never cave in.
+    #[test]
+    fn test_cave_in() {
+        let map = "dungeon 1
+map
+######
+#<>..#
+######
+";
+        let dungeon = Dungeon::from_text(map).unwrap();
+        let mut stage = WorldStage::new(&dungeon, unbounded().0);
+
+        let first = stage.cave_in().unwrap();
+        let second = stage.cave_in().unwrap();
+        assert_eq!((first.len(), second.len()), (1, 1));
+        assert!(first[0].0 != second[0].0);
+        for (spot, tile) in first.iter().chain(second.iter()) {
+            assert_eq!(*tile, Tile::Wall);
+            assert!(!stage.is_on_path(*spot));
+        }
+
+        // Only the entrance and exit are left
+        assert_eq!(stage.cave_in(), None);
+        assert!(stage.is_on_path(Vec2(1, 1)) && stage.is_on_path(Vec2(2, 1)));
+    }
+
+    /// Tests that monsters only see the players in front of them,
+    /// within their sight range, and not through walls - even
+    /// after the walls have changed.
+    #[test]
+    fn test_visible_actors() {
+        let map = "dungeon 1
+map
+#######
+#<....#
+#..#..#
+#....>#
+#######
+";
+        let dungeon = Dungeon::from_text(map).unwrap();
+        let mut stage = WorldStage::new(&dungeon, unbounded().0);
+        stage.add(1, actor(1, Vec2(1, 2), ActorId::Monster));
+        for (id, pos) in [(2, Vec2(4, 2)), (3, Vec2(4, 1)), (4, Vec2(1, 1))] {
+            stage.add(id, actor(id, pos, ActorId::Player));
+        }
+        assert!(!stage.can_see(Vec2(1, 2), Vec2(4, 2)));
+        assert!(stage.can_see(Vec2(4, 1), Vec2(1, 2)));
+
+        let seen = |stage: &mut WorldStage, range| {
+            let tr = stage.actor(1).unwrap().tr;
+            visible_actors(stage, tr, &[ActorId::Player], range)
+        };
+        assert_eq!(seen(&mut stage, 3), HashSet::from([3, 4]));
+        assert_eq!(seen(&mut stage, 1), HashSet::from([4]));
+
+        // Facing away, only the player beside the monster is seen
+        stage.change_dir(1, Direction::Left);
+        assert_eq!(seen(&mut stage, 3), HashSet::from([4]));
+
+        // Once the wall collapses, the player behind it is seen
+        stage.change_dir(1, Direction::Right);
+        stage.set_tiles(&[(Vec2(3, 2), Tile::Rubble)], Connectivity::Keep);
+        assert_eq!(seen(&mut stage, 3), HashSet::from([2, 3, 4]));
+    }
+
+    /// Tests that closed doors keep Players out until their key is
+    /// picked up, except for Players who walk through doors, as the
+    /// Players of clients which can't show them do.
+    #[test]
+    fn test_doors() {
+        let map = MAP
+            .replace("map\n", "lock 5 2 1 2\nmap\n")
+            .replace("#.....>", "#....+>");
+        let dungeon = Dungeon::from_text(&map).unwrap();
+        let (s_to_event, r_at_event) = unbounded();
+        let mut stage = WorldStage::new(&dungeon, s_to_event);
+        let to = |x, y| Transform::with_values(Vec2(x, y), Direction::Right);
+        stage.add(0, actor(0, Vec2(4, 2), ActorId::Player));
+        let mut legacy = actor(1, Vec2(4, 1), ActorId::Player);
+        legacy.through_doors = true;
+        stage.add(1, legacy);
+
+        assert!(!stage.is_spot_open(Vec2(5, 2)));
+        assert!(stage.is_spot_open_to(Vec2(5, 2), true));
+        assert!(!stage.update_pl_tr(0, to(5, 2)));
+        assert!(stage.update_pl_tr(1, to(5, 2)));
+        assert!(stage.opened_doors().is_empty());
+
+        // Picking up the key opens the door to everyone
+        assert!(stage.update_pl_tr(1, to(4, 1)));
+        assert!(stage.update_pl_tr(0, to(1, 2)));
+        assert_eq!(stage.opened_doors(), &[Vec2(5, 2)]);
+        assert!(matches!(
+            r_at_event.try_recv(),
+            Ok(ResponseType::DoorOpened(0, Vec2(5, 2)))
+        ));
+        assert!(stage.update_pl_tr(0, to(5, 2)));
+    }
+
+    /// Tests that a `WorldStage` reports the biome of each spot of a
+    /// themed floor, and that monsters are more likely to spawn in
+    /// their own biomes.
+    #[test]
+    fn test_spawn_weight() {
+        let map = MAP.replace("map\n", "region cave 1 1\nregion sewer 5 2\nmap\n");
+        let dungeon = Dungeon::from_text(&map).unwrap();
+        let stage = WorldStage::new(&dungeon, unbounded().0);
+        assert_eq!(stage.dungeon().biome_at((2, 1)), Some(Biome::Cave));
+        assert_eq!(stage.dungeon().biome_at((5, 2)), Some(Biome::Sewer));
+
+        let monster = Monster {
+            stats: Stats::new(10, 10, 10),
+            attrs: Attributes::new(5, 5, 5),
+            sight_range: 3,
+            id: 0,
+            name: "Rat",
+            spawn_chance: 4,
+            biomes: &[Biome::Sewer],
+        };
+        assert_eq!(monster.spawn_weight(None), 4);
+        assert_eq!(monster.spawn_weight(Some(Biome::Cave)), 4);
+        assert_eq!(monster.spawn_weight(Some(Biome::Sewer)), 4 * BIOME_BIAS);
+    }
+}
@@ -0,0 +1,144 @@
+//! Dungeon Viewer - Draws dungeons to the terminal, with overlays
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use std::io::{stdout, Result, Write};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{
+        read, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseButton,
+        MouseEventKind,
+    },
+    execute, queue,
+    style::{Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, size, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+use dungeon_generator::config::DungeonConfig;
+use dungeon_viewer::view::{Overlay, Style, Viewer};
+use rand::random;
+
+/// The keys the viewer responds to, shown below the map
+const HELP: &str = "arrows scroll | n/p next/prev seed | r random seed | g generator \
+                    | 1 distance | 2 chokepoints | 3 spawns | 4 sight | click route | esc clear | q quit";
+
+fn main() -> Result<()> {
+    // Read the seed, generator, size and generation parameters from
+    // the arguments, in that order. A missing or unreadable seed
+    // picks a random one, and the rest default to the size, loops,
+    // locks and hazards of the server's floors.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let num = |i: usize, default: u32| args.get(i).and_then(|a| a.parse().ok()).unwrap_or(default);
+
+    let mut config = DungeonConfig::new(num(2, 75), num(3, 75))
+        .with_loops(num(4, 4))
+        .with_locks(num(5, 2))
+        .with_hazards(num(6, 20));
+    if let Some(seed) = args.first().and_then(|s| s.parse().ok()) {
+        config = config.with_seed(seed);
+    }
+    let style = args
+        .get(1)
+        .and_then(|s| Style::from_name(s))
+        .unwrap_or(Style::Walk);
+
+    let mut viewer = Viewer::new(config, style);
+
+    let mut out = stdout();
+    enable_raw_mode()?;
+    execute!(out, EnterAlternateScreen, EnableMouseCapture, Hide)?;
+    let result = run(&mut viewer);
+    execute!(out, Show, DisableMouseCapture, LeaveAlternateScreen)?;
+    disable_raw_mode()?;
+    result
+}
+
+/// Draws the `viewer` and responds to input until the viewer is quit
+fn run(viewer: &mut Viewer) -> Result<()> {
+    loop {
+        let (view_w, view_h) = view_size()?;
+        draw(viewer, view_w, view_h)?;
+
+        match read()? {
+            // Windows reports key releases too, which would
+            // apply every key a second time
+            Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('q') => return Ok(()),
+                KeyCode::Left => viewer.scroll_by(-4, 0, view_w, view_h),
+                KeyCode::Right => viewer.scroll_by(4, 0, view_w, view_h),
+                KeyCode::Up => viewer.scroll_by(0, -4, view_w, view_h),
+                KeyCode::Down => viewer.scroll_by(0, 4, view_w, view_h),
+                KeyCode::Char('n') => viewer.regenerate(viewer.seed().wrapping_add(1)),
+                KeyCode::Char('p') => viewer.regenerate(viewer.seed().wrapping_sub(1)),
+                KeyCode::Char('r') => viewer.regenerate(random()),
+                KeyCode::Char('g') => viewer.next_style(),
+                KeyCode::Char('1') => viewer.toggle(Overlay::Distance),
+                KeyCode::Char('2') => viewer.toggle(Overlay::Chokepoints),
+                KeyCode::Char('3') => viewer.toggle(Overlay::Spawns),
+                KeyCode::Char('4') => viewer.toggle(Overlay::Sight),
+                KeyCode::Esc => viewer.clear_route(),
+                _ => {}
+            },
+            Event::Mouse(mouse) => match mouse.kind {
+                // Each tile is drawn two characters wide
+                MouseEventKind::Down(MouseButton::Left) if (mouse.row as i32) < view_h => {
+                    let (x, y) = viewer.scroll();
+                    viewer.click((x + mouse.column as i32 / 2, y + mouse.row as i32));
+                }
+                MouseEventKind::Down(MouseButton::Right) => viewer.clear_route(),
+                MouseEventKind::ScrollUp => viewer.scroll_by(0, -1, view_w, view_h),
+                MouseEventKind::ScrollDown => viewer.scroll_by(0, 1, view_w, view_h),
+                _ => {}
+            },
+            // Keep the view within the dungeon at its new size
+            Event::Resize(..) => viewer.scroll_by(0, 0, view_w, view_h),
+            _ => {}
+        }
+    }
+}
+
+/// The number of tiles which fit in the terminal, above the two status lines
+fn view_size() -> Result<(i32, i32)> {
+    let (cols, rows) = size()?;
+    Ok((cols as i32 / 2, (rows as i32 - 2).max(0)))
+}
+
+/// Draws the visible part of the dungeon, then the status and help lines
+fn draw(viewer: &Viewer, view_w: i32, view_h: i32) -> Result<()> {
+    let mut out = stdout();
+    let dun = viewer.dungeon();
+    let (sx, sy) = viewer.scroll();
+    let (w, h) = (
+        view_w.min(dun.width() as i32 - sx),
+        view_h.min(dun.height() as i32 - sy),
+    );
+
+    for row in 0..h {
+        queue!(out, MoveTo(0, row as u16))?;
+        for col in 0..w {
+            let cell = viewer.cell((sx + col, sy + row));
+            queue!(
+                out,
+                SetForegroundColor(cell.fg),
+                SetBackgroundColor(cell.bg),
+                Print(cell.symbol),
+                Print(' ')
+            )?;
+        }
+        queue!(out, ResetColor, Clear(ClearType::UntilNewLine))?;
+    }
+    queue!(
+        out,
+        MoveTo(0, h as u16),
+        Clear(ClearType::FromCursorDown),
+        MoveTo(0, view_h as u16),
+        Print(viewer.status()),
+        MoveTo(0, view_h as u16 + 1),
+        Print(HELP)
+    )?;
+    out.flush()
+}
@@ -0,0 +1,334 @@
+//! The state of the Dungeon Viewer, and how each tile is drawn
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+use std::collections::HashSet;
+
+use crossbeam::channel::unbounded;
+use crossterm::style::Color;
+use dungeon_crawler_server::{
+    astar::find_shortest_path,
+    state::transforms::{
+        vec2::Vec2,
+        world_stage::{WorldStage, SIGHT_RADIUS},
+    },
+};
+use dungeon_generator::{
+    analysis::Analysis,
+    config::DungeonConfig,
+    generators::{bsp::BspGenerator, caves::CaveGenerator, walk::WalkGenerator, wfc::WfcGenerator},
+    inst::Dungeon,
+    sight::SightMap,
+    tiles::Tile,
+    traits::Generator,
+};
+
+///
+/// The `Generator` the viewer builds its dungeons with.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    Walk,
+    Bsp,
+    Caves,
+    Wfc,
+}
+
+impl Style {
+    /// Finds the `Style` with the given `name`, if there is one
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "walk" => Some(Style::Walk),
+            "bsp" => Some(Style::Bsp),
+            "caves" => Some(Style::Caves),
+            "wfc" => Some(Style::Wfc),
+            _ => None,
+        }
+    }
+    /// The name of the `Style`, as given on the command line
+    pub fn name(&self) -> &'static str {
+        match self {
+            Style::Walk => "walk",
+            Style::Bsp => "bsp",
+            Style::Caves => "caves",
+            Style::Wfc => "wfc",
+        }
+    }
+    /// The `Style` after this one, wrapping back round to the first
+    pub fn next(&self) -> Self {
+        match self {
+            Style::Walk => Style::Bsp,
+            Style::Bsp => Style::Caves,
+            Style::Caves => Style::Wfc,
+            Style::Wfc => Style::Walk,
+        }
+    }
+    fn generator(&self) -> Box<dyn Generator> {
+        match self {
+            Style::Walk => Box::new(WalkGenerator),
+            Style::Bsp => Box::new(BspGenerator::default()),
+            Style::Caves => Box::new(CaveGenerator::default()),
+            Style::Wfc => Box::new(WfcGenerator::default()),
+        }
+    }
+}
+
+///
+/// The extra layers of information which can be
+/// drawn over the dungeon.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Overlay {
+    /// Shades each tile by how far it is to walk from the entrance
+    Distance,
+    /// Marks the tiles which split the paths in two when walled off
+    Chokepoints,
+    /// Marks the tiles the server may spawn monsters on
+    Spawns,
+    /// Hides the tiles which can't be seen from the point last
+    /// clicked, or from the entrance, as fog of war
+    Sight,
+}
+
+///
+/// A single tile as drawn to the terminal.
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub symbol: char,
+    pub fg: Color,
+    pub bg: Color,
+}
+
+///
+/// The state of the viewer - the dungeon being viewed, the
+/// overlays shown over it, how far it is scrolled, and the
+/// route between the two points last clicked.
+///
+pub struct Viewer {
+    config: DungeonConfig,
+    style: Style,
+    dungeon: Dungeon,
+    analysis: Analysis,
+    furthest: u32,
+    stage: WorldStage,
+    sight: SightMap,
+    spawns: HashSet<(i32, i32)>,
+    overlays: HashSet<Overlay>,
+    scroll: (i32, i32),
+    ends: Vec<(i32, i32)>,
+    route: Vec<(i32, i32)>,
+}
+
+impl Viewer {
+    /// Creates a new `Viewer`, generating a dungeon
+    /// from the `config` in the given `style`.
+    pub fn new(config: DungeonConfig, style: Style) -> Self {
+        let dungeon = style.generator().generate(&config);
+        let analysis = dungeon.analyze();
+        let stage = WorldStage::new(&dungeon, unbounded().0);
+        Self {
+            config: config.with_seed(dungeon.seed()),
+            style,
+            furthest: analysis.from_entrance.values().cloned().max().unwrap_or(0),
+            spawns: stage
+                .spawn_candidates()
+                .iter()
+                .map(|p| (p.0, p.1))
+                .collect(),
+            sight: dungeon.sight_map(SIGHT_RADIUS),
+            dungeon,
+            analysis,
+            stage,
+            overlays: HashSet::new(),
+            scroll: (0, 0),
+            ends: Vec::new(),
+            route: Vec::new(),
+        }
+    }
+    /// Regenerates the dungeon from the given `seed`
+    pub fn regenerate(&mut self, seed: u64) {
+        *self = Self {
+            overlays: self.overlays.clone(),
+            ..Self::new(self.config.clone().with_seed(seed), self.style)
+        };
+    }
+    /// Regenerates the dungeon from the same seed in the next `Style`
+    pub fn next_style(&mut self) {
+        self.style = self.style.next();
+        self.regenerate(self.seed());
+    }
+    /// Shows the `overlay` if it is hidden, or hides it if it is shown
+    pub fn toggle(&mut self, overlay: Overlay) {
+        if !self.overlays.remove(&overlay) {
+            self.overlays.insert(overlay);
+        }
+    }
+    /// Tests if the `overlay` is shown
+    pub fn shows(&self, overlay: Overlay) -> bool {
+        self.overlays.contains(&overlay)
+    }
+    ///
+    /// Scrolls the view by `dx` and `dy` tiles, keeping a
+    /// view of `view_w` by `view_h` tiles within the dungeon.
+    ///
+    pub fn scroll_by(&mut self, dx: i32, dy: i32, view_w: i32, view_h: i32) {
+        let max_x = (self.dungeon.width() as i32 - view_w).max(0);
+        let max_y = (self.dungeon.height() as i32 - view_h).max(0);
+        self.scroll = (
+            (self.scroll.0 + dx).clamp(0, max_x),
+            (self.scroll.1 + dy).clamp(0, max_y),
+        );
+    }
+    ///
+    /// Picks the `point` as one end of a route. Once both ends are
+    /// picked, the route a monster would take between them is
+    /// found. Picking a third point starts a new route.
+    ///
+    pub fn click(&mut self, point: (i32, i32)) {
+        if self.ends.len() == 2 {
+            self.clear_route();
+        }
+        self.ends.push(point);
+        if let [from, to] = self.ends[..] {
+            let route =
+                find_shortest_path(&self.stage, Vec2::from_tuple(from), Vec2::from_tuple(to));
+            self.route = route.iter().map(|p| (p.0, p.1)).collect();
+        }
+    }
+    /// Forgets the ends of the route, and the route between them
+    pub fn clear_route(&mut self) {
+        self.ends.clear();
+        self.route.clear();
+    }
+    /// The seed of the dungeon being viewed
+    pub fn seed(&self) -> u64 {
+        self.dungeon.seed()
+    }
+    /// The `Style` of the dungeon being viewed
+    pub fn style(&self) -> Style {
+        self.style
+    }
+    /// The dungeon being viewed
+    pub fn dungeon(&self) -> &Dungeon {
+        &self.dungeon
+    }
+    /// The top-left tile of the view
+    pub fn scroll(&self) -> (i32, i32) {
+        self.scroll
+    }
+    /// The tiles the server may spawn monsters on
+    pub fn spawns(&self) -> &HashSet<(i32, i32)> {
+        &self.spawns
+    }
+    /// The route between the two points last clicked, without its start
+    pub fn route(&self) -> &[(i32, i32)] {
+        &self.route
+    }
+
+    /// Draws the tile at `point`, with every shown overlay over it
+    pub fn cell(&self, point: (i32, i32)) -> Cell {
+        let mut cell = self.base_cell(point);
+        if self.shows(Overlay::Distance) {
+            if let Some(dist) = self.analysis.from_entrance.get(&point) {
+                cell.bg = shade(*dist, self.furthest);
+            }
+        }
+        if self.shows(Overlay::Chokepoints) && self.analysis.chokepoints.contains(&point) {
+            cell.bg = Color::DarkRed;
+        }
+        if self.shows(Overlay::Spawns) && self.spawns.contains(&point) {
+            cell.fg = Color::Green;
+            if cell.symbol == ' ' {
+                cell.symbol = 'o';
+            }
+        }
+        if self.shows(Overlay::Sight) && !self.sight.can_see(self.eye(), point) {
+            cell = Cell {
+                symbol: ' ',
+                fg: Color::Reset,
+                bg: Color::Reset,
+            };
+        }
+        if self.ends.contains(&point) {
+            cell = Cell {
+                symbol: '@',
+                fg: Color::Magenta,
+                ..cell
+            };
+        } else if self.route.contains(&point) {
+            cell = Cell {
+                symbol: '*',
+                fg: Color::Yellow,
+                ..cell
+            };
+        }
+        cell
+    }
+
+    /// A single line describing the dungeon being viewed
+    pub fn status(&self) -> String {
+        let report = match self.dungeon.difficulty_report() {
+            Some(report) => format!(
+                "path {} steps, {} chokepoints, branching {:.2}",
+                report.path_length, report.chokepoints, report.branching
+            ),
+            None => "exit unreachable".to_string(),
+        };
+        format!(
+            "seed {} | {} {}x{} | {} | route {} steps",
+            self.seed(),
+            self.style.name(),
+            self.dungeon.width(),
+            self.dungeon.height(),
+            report,
+            self.route.len()
+        )
+    }
+
+    /// The point the `Sight` overlay is seen from
+    fn eye(&self) -> (i32, i32) {
+        *self.ends.last().unwrap_or(&self.dungeon.entrance)
+    }
+
+    /// Draws the tile at `point` without any overlays
+    fn base_cell(&self, point: (i32, i32)) -> Cell {
+        let dun = &self.dungeon;
+        let (symbol, fg) = if dun.entrance == point {
+            ('<', Color::Cyan)
+        } else if dun.is_exit(point) {
+            ('>', Color::Cyan)
+        } else if dun.locks().iter().any(|l| l.door == point) {
+            ('D', Color::DarkYellow)
+        } else if dun.locks().iter().any(|l| l.key == point) {
+            ('k', Color::DarkYellow)
+        } else if dun.vaults().iter().any(|v| v.spawns.contains(&point)) {
+            ('M', Color::Red)
+        } else if dun.vaults().iter().any(|v| v.treasure.contains(&point)) {
+            ('$', Color::Yellow)
+        } else {
+            match dun.tile_at(point) {
+                Tile::Floor => (' ', Color::Reset),
+                Tile::Wall => ('#', Color::DarkGrey),
+                Tile::Water => ('~', Color::Blue),
+                tile => (tile.symbol(), Color::Grey),
+            }
+        };
+        Cell {
+            symbol,
+            fg,
+            bg: Color::Reset,
+        }
+    }
+}
+
+/// Shades a distance of `dist` out of `max` from blue to red
+fn shade(dist: u32, max: u32) -> Color {
+    let t = dist.min(max) as f32 / max.max(1) as f32;
+    Color::Rgb {
+        r: (40.0 + 180.0 * t) as u8,
+        g: 40,
+        b: (220.0 - 180.0 * t) as u8,
+    }
+}
@@ -0,0 +1,149 @@
+//! Dungeon Viewer tests
+//!
+//! Christian Schmid - June 2021
+//! CS510 - Rust Programming
+
+#[cfg(test)]
+mod viewer_tests {
+    use crossterm::style::Color;
+    use dungeon_crawler_server::state::transforms::world_stage::SIGHT_RADIUS;
+    use dungeon_generator::{analysis::distance_field, config::DungeonConfig};
+    use dungeon_viewer::view::{Cell, Overlay, Style, Viewer};
+
+    /// Creates a `Viewer` of a seeded 40x40 dungeon in the given `style`
+    fn viewer(seed: u64, style: Style) -> Viewer {
+        let config = DungeonConfig::new(40, 40)
+            .with_seed(seed)
+            .with_loops(2)
+            .with_locks(1)
+            .with_hazards(10);
+        Viewer::new(config, style)
+    }
+
+    /// Tests that regenerating by seed and changing the generator
+    /// reproduce the same dungeons, and that overlays are kept.
+    #[test]
+    fn test_regenerate() {
+        let mut view = viewer(7, Style::Walk);
+        let first = view.dungeon().clone();
+        view.toggle(Overlay::Distance);
+
+        view.regenerate(8);
+        assert_eq!(view.seed(), 8);
+        assert!(view.shows(Overlay::Distance));
+        view.regenerate(7);
+        assert!(*view.dungeon() == first);
+
+        for style in [Style::Bsp, Style::Caves, Style::Wfc, Style::Walk] {
+            view.next_style();
+            assert_eq!(view.style(), style);
+            assert_eq!(view.seed(), 7);
+            assert!(view.dungeon() == viewer(7, style).dungeon());
+        }
+    }
+
+    /// Tests that each overlay marks the tiles it describes, and
+    /// only when it is shown.
+    #[test]
+    fn test_overlays() {
+        for style in [Style::Walk, Style::Bsp, Style::Caves, Style::Wfc] {
+            let mut view = viewer(3, style);
+            let dun = view.dungeon().clone();
+            let analysis = dun.analyze();
+            let points: Vec<(i32, i32)> = (0..dun.height() as i32)
+                .flat_map(|y| (0..dun.width() as i32).map(move |x| (x, y)))
+                .collect();
+
+            assert!(points.iter().all(|p| view.cell(*p).bg == Color::Reset));
+
+            view.toggle(Overlay::Chokepoints);
+            for p in points.iter() {
+                let marked = view.cell(*p).bg == Color::DarkRed;
+                assert_eq!(marked, analysis.chokepoints.contains(p));
+            }
+            view.toggle(Overlay::Chokepoints);
+
+            view.toggle(Overlay::Distance);
+            for p in points.iter() {
+                let shaded = view.cell(*p).bg != Color::Reset;
+                assert_eq!(shaded, analysis.from_entrance.contains_key(p));
+            }
+            view.toggle(Overlay::Distance);
+
+            // Spawns are open tiles more than 15 steps from the entrance
+            let dist = distance_field(dun.paths_ref(), dun.entrance);
+            assert!(!view.spawns().is_empty());
+            for spawn in view.spawns() {
+                assert!(dun.paths_ref().contains(spawn));
+                assert!(dist[spawn] > 15);
+            }
+            view.toggle(Overlay::Spawns);
+            for p in points.iter() {
+                let marked = view.cell(*p).fg == Color::Green;
+                assert_eq!(marked, view.spawns().contains(p));
+            }
+            view.toggle(Overlay::Spawns);
+
+            // Only the tiles in sight of the entrance are drawn, until
+            // another point is clicked
+            let sight = dun.sight_map(SIGHT_RADIUS);
+            let plain: Vec<Cell> = points.iter().map(|p| view.cell(*p)).collect();
+            view.toggle(Overlay::Sight);
+            for (p, cell) in points.iter().zip(plain) {
+                if sight.can_see(dun.entrance, *p) {
+                    assert_eq!(view.cell(*p), cell);
+                } else {
+                    assert_eq!(view.cell(*p).symbol, ' ');
+                }
+            }
+            let far = *view.spawns().iter().min().unwrap();
+            view.click(far);
+            assert!(view.cell(far).symbol == '@');
+            for p in points
+                .iter()
+                .filter(|p| !sight.can_see(far, **p) && **p != far)
+            {
+                assert_eq!(view.cell(*p).symbol, ' ');
+            }
+            view.clear_route();
+            view.toggle(Overlay::Sight);
+        }
+    }
+
+    /// Tests that routes between clicked points are walkable
+    /// and end at the second point, and that scrolling stays
+    /// within the dungeon.
+    #[test]
+    fn test_route_scroll() {
+        // Without locks, as monsters can't path through closed doors
+        let config = DungeonConfig::new(40, 40).with_seed(11).with_hazards(10);
+        let mut view = Viewer::new(config, Style::Bsp);
+        let dun = view.dungeon().clone();
+        let far = *view.spawns().iter().min().unwrap();
+
+        view.click(dun.entrance);
+        assert!(view.route().is_empty());
+        view.click(far);
+        let route = view.route().to_vec();
+        assert_eq!(route.first(), Some(&far));
+        let mut prev = dun.entrance;
+        for step in route.iter().rev() {
+            assert_eq!((step.0 - prev.0).abs() + (step.1 - prev.1).abs(), 1);
+            assert!(dun.tile_at(*step).is_walkable());
+            prev = *step;
+        }
+        assert_eq!(view.cell(far).symbol, '@');
+
+        // A third click starts a new route
+        view.click(far);
+        assert!(view.route().is_empty());
+        view.clear_route();
+
+        view.scroll_by(-5, -5, 20, 20);
+        assert_eq!(view.scroll(), (0, 0));
+        view.scroll_by(100, 7, 20, 20);
+        assert_eq!(view.scroll(), (20, 7));
+        view.scroll_by(0, 0, 60, 60);
+        assert_eq!(view.scroll(), (0, 0));
+    }
+}